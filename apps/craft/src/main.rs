@@ -1,13 +1,33 @@
 use cli::Cli;
-use contract::Result;
+use contract::{ProcessError, Result, SilentExit};
 use manager::CraftManager;
+use package::WorkspaceFilter;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
+    if let Err(error) = run().await {
+        // A failed script decides craft's exit status, its own output already explains why
+        if let Some(process_error) = error.downcast_ref::<ProcessError>() {
+            if process_error.signal.is_none() {
+                eprintln!("Error: {}", process_error);
+            }
+            std::process::exit(process_error.exit_code());
+        }
+        if let Some(exit) = error.downcast_ref::<SilentExit>() {
+            std::process::exit(exit.code);
+        }
+
+        eprintln!("Error: {:?}", error);
+        std::process::exit(1);
+    }
+}
+
+async fn run() -> Result<()> {
     let cli = Cli::parse_args();
     let command = cli.normalize();
 
-    let manager = CraftManager::new();
+    let filter = WorkspaceFilter::parse(&cli.filter, cli.workspace_root)?;
+    let manager = CraftManager::new().with_filter(filter);
 
     // Initialize debug logging if verbose mode is enabled
     if cli.verbose {
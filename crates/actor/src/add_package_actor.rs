@@ -1,11 +1,19 @@
-use contract::{Actor, Pipeline};
-use package::{InstallPackage, PackageJson};
-use pipeline::{InstallPipe, LinkerPipe};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use contract::{Actor, LOCKFILE_NAME, Pipeline};
+use lockfile::Lockfile;
+use package::{InstallPackage, Overrides, PackageJson, Workspace, WorkspaceFilter};
+use patch::PackagePatch;
+use pipeline::{LinkerPipe, LockfilePipe};
+
+use crate::{InstallActor, InstallActorPayload};
 
 #[derive(Debug)]
 pub struct AddActorPayload {
     pub packages: Vec<String>,
     pub is_dev: bool,
+    pub filter: WorkspaceFilter,
 }
 
 impl std::fmt::Display for AddActorPayload {
@@ -18,41 +26,106 @@ pub struct AddPackageActor {
     payload: AddActorPayload,
 }
 
-impl Actor<AddActorPayload> for AddPackageActor {
-    fn with(payload: AddActorPayload) -> Self {
-        Self { payload }
-    }
-
-    async fn run(&self) -> contract::Result<()> {
+impl AddPackageActor {
+    /// Add the requested packages to the project in `dir`
+    async fn add_to(
+        &self,
+        dir: &Path,
+        workspace: Option<&Workspace>,
+        lockfile_dir: &Path,
+        overrides: &Overrides,
+        patches: &BTreeMap<String, PackagePatch>,
+    ) -> contract::Result<()> {
         let mut pkgs = Vec::new();
+        // Ranges written to package.json for packages that aren't resolved by version
+        let mut fixed_ranges = std::collections::HashMap::new();
+
+        for literal in &self.payload.packages {
+            let pkg = InstallPackage::from_literal(literal, self.payload.is_dev);
+
+            // Workspace packages are linked rather than fetched from the registry
+            if pkg.version.is_none()
+                && let Some(member) = workspace.and_then(|w| w.get(&pkg.name))
+            {
+                fixed_ranges.insert(pkg.name.clone(), "workspace:^".to_string());
+                pkgs.push(InstallPackage::new(
+                    pkg.name,
+                    Some(format!("link:{}", member.dir.display())),
+                    self.payload.is_dev,
+                ));
+                continue;
+            }
 
-        for pkg in &self.payload.packages {
-            pkgs.push(InstallPackage::from_literal(pkg, self.payload.is_dev));
+            pkgs.push(pkg);
         }
 
-        let artifacts = InstallPipe::new(pkgs.clone()).run().await?;
-
-        LinkerPipe::new(artifacts.clone(), pkgs).run().await?;
+        let install_pipe = InstallActor::install_pipe(lockfile_dir, pkgs.clone())
+            .await?
+            .with_overrides(overrides.clone())
+            .with_patches(patches.clone());
+        let artifacts = install_pipe.run().await?;
+
+        LinkerPipe::new(artifacts.clone(), pkgs.clone())
+            .with_project_dir(dir.to_path_buf())
+            .run()
+            .await?;
+        crate::lifecycle::run_install_scripts(
+            artifacts.clone(),
+            lockfile_dir,
+            &[dir.to_path_buf()],
+        )
+        .await?;
+
+        // Merge the new packages into the existing lockfile
+        let lockfile_path = lockfile_dir.join(LOCKFILE_NAME);
+        let mut lockfile_pipe = LockfilePipe::new(artifacts.clone())
+            .with_project_dir(lockfile_dir.to_path_buf())
+            .with_overrides(install_pipe.applied_overrides().await)
+            .with_declared_overrides(overrides.to_map())
+            .with_patches(InstallActor::patch_hashes(patches));
+        if lockfile_path.exists() {
+            lockfile_pipe = lockfile_pipe.with_existing(Lockfile::load(&lockfile_path).await?);
+        }
+        lockfile_pipe.run().await?;
 
-        let mut package_json = PackageJson::from_file().await?;
+        let package_json_path = dir.join("package.json");
+        let mut package_json = PackageJson::from_path(&package_json_path).await?;
 
         // Add only the explicitly requested packages to dependencies or devDependencies
-        for pkg_name in &self.payload.packages {
-            // Find the corresponding artifact for this package
-            if let Some(artifact) = artifacts.iter().find(|a| &a.name == pkg_name) {
+        for pkg in &pkgs {
+            // Find the corresponding artifact for this package, non-registry packages
+            // are named after their package.json and keep the spec they were added with
+            let entry = if let Some(range) = fixed_ranges.get(&pkg.name) {
+                artifacts
+                    .iter()
+                    .find(|a| a.matches_spec(pkg.spec()))
+                    .map(|a| (a, range.clone()))
+            } else if !pkg.is_registry() {
+                artifacts
+                    .iter()
+                    .find(|a| a.matches_spec(pkg.spec()))
+                    .map(|a| (a, pkg.spec().to_string()))
+            } else {
+                artifacts
+                    .iter()
+                    .find(|a| a.name == pkg.name)
+                    .map(|a| (a, format!("^{}", a.version)))
+            };
+
+            if let Some((artifact, range)) = entry {
                 if self.payload.is_dev {
                     // Add to devDependencies
                     let dev_deps = package_json
                         .dev_dependencies
                         .get_or_insert_with(std::collections::HashMap::new);
-                    dev_deps.insert(artifact.name.clone(), format!("^{}", artifact.version));
+                    dev_deps.insert(artifact.name.clone(), range);
                     debug::info!("Added {} to devDependencies", artifact.name);
                 } else {
                     // Add to dependencies
                     let deps = package_json
                         .dependencies
                         .get_or_insert_with(std::collections::HashMap::new);
-                    deps.insert(artifact.name.clone(), format!("^{}", artifact.version));
+                    deps.insert(artifact.name.clone(), range);
                     debug::info!("Added {} to dependencies", artifact.name);
                 }
             }
@@ -60,8 +133,49 @@ impl Actor<AddActorPayload> for AddPackageActor {
 
         // Write updated package.json
         let updated_content = serde_json::to_string_pretty(&package_json)?;
-        tokio::fs::write(&PackageJson::file_path()?, updated_content).await?;
-        debug::info!("Updated package.json");
+        tokio::fs::write(&package_json_path, updated_content).await?;
+        debug::info!("Updated {:?}", package_json_path);
+
+        Ok(())
+    }
+}
+
+impl Actor<AddActorPayload> for AddPackageActor {
+    fn with(payload: AddActorPayload) -> Self {
+        Self { payload }
+    }
+
+    async fn run(&self) -> contract::Result<()> {
+        let cwd = std::env::current_dir()?;
+        let dirs = self.payload.filter.target_dirs(&cwd).await?;
+
+        // Workspace packages share the lockfile at the workspace root
+        let workspace = Workspace::find(&cwd).await?;
+        let lockfile_dir = workspace.as_ref().map_or(cwd.clone(), |w| w.root.clone());
+
+        let root_package_json = PackageJson::from_path(&lockfile_dir.join("package.json")).await?;
+        let (overrides, overrides_changed) =
+            InstallActor::overrides(&root_package_json, &lockfile_dir).await?;
+        let patches = PackagePatch::load_all(&root_package_json, &lockfile_dir).await?;
+
+        for dir in dirs {
+            self.add_to(
+                &dir,
+                workspace.as_ref(),
+                &lockfile_dir,
+                &overrides,
+                &patches,
+            )
+            .await?;
+        }
+
+        // Locked entries may depend on the old overrides, resolve everything again
+        if overrides_changed {
+            println!("Overrides changed, reinstalling");
+            return InstallActor::with(InstallActorPayload::default())
+                .run()
+                .await;
+        }
 
         Ok(())
     }
@@ -76,6 +190,7 @@ mod tests {
         let payload = AddActorPayload {
             packages: vec!["react".to_string(), "lodash".to_string()],
             is_dev: false,
+            filter: WorkspaceFilter::default(),
         };
 
         assert_eq!(format!("{}", payload), "react lodash");
@@ -86,10 +201,11 @@ mod tests {
         let payload = AddActorPayload {
             packages: vec!["test".to_string()],
             is_dev: true,
+            filter: WorkspaceFilter::default(),
         };
 
         let actor = AddPackageActor::with(payload);
         assert_eq!(actor.payload.packages.len(), 1);
-        assert_eq!(actor.payload.is_dev, true);
+        assert!(actor.payload.is_dev);
     }
 }
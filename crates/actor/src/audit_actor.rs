@@ -0,0 +1,902 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use config::{AuditIgnore, Config};
+use contract::{Actor, LOCKFILE_NAME, Result, SilentExit};
+use lockfile::{DependencyGraph, Edge, Lockfile};
+use network::Network;
+use node_semver::{Range, Version};
+use package::{PackageJson, Workspace, WorkspaceFilter};
+use resolver::NpmResolver;
+use serde::Deserialize;
+
+use crate::audit_fix::{self, FixPlan};
+use crate::dependency_versions;
+use crate::importers::{self, Importer};
+use crate::why_actor::WhyActor;
+use crate::{InstallActor, InstallActorPayload};
+
+/// Advisory severities, lowest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditLevel {
+    Info,
+    Low,
+    Moderate,
+    High,
+    Critical,
+}
+
+impl AuditLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Low => "low",
+            Self::Moderate => "moderate",
+            Self::High => "high",
+            Self::Critical => "critical",
+        }
+    }
+
+    /// SARIF result level, shown by code scanning
+    fn sarif_level(&self) -> &'static str {
+        match self {
+            Self::Critical | Self::High => "error",
+            Self::Moderate => "warning",
+            Self::Low | Self::Info => "note",
+        }
+    }
+
+    /// CVSS-like score code scanning sorts security alerts by
+    fn security_severity(&self) -> &'static str {
+        match self {
+            Self::Critical => "9.0",
+            Self::High => "7.0",
+            Self::Moderate => "4.0",
+            Self::Low => "0.1",
+            Self::Info => "0.0",
+        }
+    }
+}
+
+impl FromStr for AuditLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "info" => Ok(Self::Info),
+            "low" => Ok(Self::Low),
+            "moderate" => Ok(Self::Moderate),
+            "high" => Ok(Self::High),
+            "critical" => Ok(Self::Critical),
+            _ => anyhow::bail!(
+                "Invalid audit level {:?}, expected info, low, moderate, high or critical",
+                s
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AuditActorPayload {
+    /// Lowest severity to report, `audit-level` from `.craftrc` when unset
+    pub audit_level: Option<AuditLevel>,
+    /// Print a JSON report instead of a table
+    pub json: bool,
+    /// Print a SARIF log for code scanning
+    pub sarif: bool,
+    /// Upgrade vulnerable packages instead of reporting them
+    pub fix: bool,
+    /// With `fix`, print the changes without making them
+    pub dry_run: bool,
+    pub filter: WorkspaceFilter,
+}
+
+impl std::fmt::Display for AuditActorPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.audit_level.map_or("default", |level| level.as_str())
+        )
+    }
+}
+
+pub struct AuditActor {
+    payload: AuditActorPayload,
+}
+
+/// An advisory of the registry's bulk endpoint
+#[derive(Debug, Clone, Deserialize)]
+struct Advisory {
+    /// The registry's id, usually a number
+    id: serde_json::Value,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    url: String,
+    severity: AuditLevel,
+    vulnerable_versions: String,
+}
+
+impl Advisory {
+    /// The GHSA id from the advisory's URL, else the registry's id
+    fn id(&self) -> String {
+        match self.url.rsplit('/').next() {
+            Some(ghsa) if ghsa.starts_with("GHSA-") => ghsa.to_string(),
+            _ => self.registry_id(),
+        }
+    }
+
+    fn registry_id(&self) -> String {
+        match &self.id {
+            serde_json::Value::String(id) => id.clone(),
+            id => id.to_string(),
+        }
+    }
+
+    /// Whether an `audit-ignore` entry names this advisory, by either id
+    fn is_named(&self, id: &str) -> bool {
+        id == self.id() || id == self.registry_id()
+    }
+}
+
+/// A locked package affected by an advisory
+#[derive(Debug, Clone)]
+struct Finding {
+    name: String,
+    version: String,
+    advisory: Advisory,
+    /// Dependency chains leading to the package, as printed by `craft why`
+    paths: Vec<String>,
+}
+
+/// Chains printed per finding before the rest are summarised
+const MAX_PATHS: usize = 5;
+
+impl AuditActor {
+    /// Locked versions of registry packages by name, the bulk endpoint's request
+    fn request(lockfile: &Lockfile) -> BTreeMap<String, BTreeSet<String>> {
+        let mut request: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for entry in lockfile.packages.values() {
+            if entry.resolved.starts_with("http://") || entry.resolved.starts_with("https://") {
+                request
+                    .entry(entry.name.clone())
+                    .or_default()
+                    .insert(entry.version.clone());
+            }
+        }
+        request
+    }
+
+    async fn fetch(
+        url: &str,
+        request: &BTreeMap<String, BTreeSet<String>>,
+    ) -> Result<BTreeMap<String, Vec<Advisory>>> {
+        Network::new()
+            .post(url, request)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to fetch advisories from {}: {}", url, e))
+    }
+
+    /// Locked packages whose version is in an advisory's vulnerable range,
+    /// most severe first
+    fn findings(lockfile: &Lockfile, advisories: &BTreeMap<String, Vec<Advisory>>) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for entry in lockfile.packages.values() {
+            let Ok(version) = Version::parse(&entry.version) else {
+                continue;
+            };
+
+            for advisory in advisories.get(&entry.name).into_iter().flatten() {
+                let vulnerable = Range::parse(&advisory.vulnerable_versions)
+                    .is_ok_and(|range| range.satisfies(&version));
+                if vulnerable {
+                    findings.push(Finding {
+                        name: entry.name.clone(),
+                        version: entry.version.clone(),
+                        advisory: advisory.clone(),
+                        paths: Vec::new(),
+                    });
+                }
+            }
+        }
+
+        findings.sort_by(|a, b| {
+            b.advisory
+                .severity
+                .cmp(&a.advisory.severity)
+                .then_with(|| a.name.cmp(&b.name))
+                .then_with(|| a.version.cmp(&b.version))
+        });
+        findings
+    }
+
+    /// The ignore entry hiding `finding`, unless it expired before `today`
+    fn ignored_by<'a>(
+        finding: &Finding,
+        ignores: &'a [AuditIgnore],
+        today: &str,
+    ) -> Option<&'a AuditIgnore> {
+        ignores
+            .iter()
+            .find(|ignore| finding.advisory.is_named(&ignore.id) && !ignore.is_expired(today))
+    }
+
+    /// `3 vulnerabilities (1 moderate, 2 high)`
+    fn summary(findings: &[Finding]) -> String {
+        let mut counts: BTreeMap<AuditLevel, usize> = BTreeMap::new();
+        for finding in findings {
+            *counts.entry(finding.advisory.severity).or_default() += 1;
+        }
+
+        let noun = if findings.len() == 1 {
+            "vulnerability"
+        } else {
+            "vulnerabilities"
+        };
+        let counts: Vec<String> = counts
+            .iter()
+            .map(|(level, count)| format!("{} {}", count, level.as_str()))
+            .collect();
+
+        if counts.is_empty() {
+            format!("0 {}", noun)
+        } else {
+            format!("{} {} ({})", findings.len(), noun, counts.join(", "))
+        }
+    }
+
+    fn table(findings: &[Finding]) -> String {
+        let mut lines = Vec::new();
+        for finding in findings {
+            let advisory = &finding.advisory;
+            lines.push(format!(
+                "{:8}  {}",
+                advisory.severity.as_str(),
+                advisory.title
+            ));
+            lines.push(format!(
+                "{:8}  {}@{} (vulnerable: {})",
+                "", finding.name, finding.version, advisory.vulnerable_versions
+            ));
+            if !advisory.url.is_empty() {
+                lines.push(format!("{:8}  {}", "", advisory.url));
+            }
+            for path in finding.paths.iter().take(MAX_PATHS) {
+                lines.push(format!("{:8}  {}", "", path));
+            }
+            if finding.paths.len() > MAX_PATHS {
+                lines.push(format!(
+                    "{:8}  … and {} more",
+                    "",
+                    finding.paths.len() - MAX_PATHS
+                ));
+            }
+            lines.push(String::new());
+        }
+
+        lines.push(Self::summary(findings));
+        lines.join("\n")
+    }
+
+    fn json(findings: &[Finding], ignored: usize) -> Result<String> {
+        let advisories: Vec<serde_json::Value> = findings
+            .iter()
+            .map(|finding| {
+                serde_json::json!({
+                    "id": finding.advisory.id(),
+                    "name": finding.name,
+                    "version": finding.version,
+                    "severity": finding.advisory.severity.as_str(),
+                    "title": finding.advisory.title,
+                    "url": finding.advisory.url,
+                    "vulnerableVersions": finding.advisory.vulnerable_versions,
+                    "paths": finding.paths,
+                })
+            })
+            .collect();
+
+        Ok(serde_json::to_string_pretty(&serde_json::json!({
+            "advisories": advisories,
+            "ignored": ignored,
+        }))?)
+    }
+
+    /// A SARIF 2.1.0 log with one rule per advisory, located at the lockfile
+    fn sarif(findings: &[Finding]) -> Result<String> {
+        let mut rules: BTreeMap<String, serde_json::Value> = BTreeMap::new();
+        let mut results = Vec::new();
+
+        for finding in findings {
+            let advisory = &finding.advisory;
+            let id = advisory.id();
+            rules.entry(id.clone()).or_insert_with(|| {
+                serde_json::json!({
+                    "id": id,
+                    "shortDescription": { "text": advisory.title },
+                    "helpUri": advisory.url,
+                    "defaultConfiguration": { "level": advisory.severity.sarif_level() },
+                    "properties": {
+                        "tags": ["security"],
+                        "security-severity": advisory.severity.security_severity(),
+                    },
+                })
+            });
+
+            let mut message = format!(
+                "{}@{} is vulnerable ({} {}): {}",
+                finding.name,
+                finding.version,
+                advisory.vulnerable_versions,
+                advisory.severity.as_str(),
+                advisory.title
+            );
+            if let Some(path) = finding.paths.first() {
+                message.push_str(&format!(". Introduced by {}", path));
+            }
+
+            results.push(serde_json::json!({
+                "ruleId": id,
+                "level": advisory.severity.sarif_level(),
+                "message": { "text": message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": LOCKFILE_NAME },
+                    },
+                }],
+                "partialFingerprints": {
+                    "package": format!("{}@{}/{}", finding.name, finding.version, id),
+                },
+            }));
+        }
+
+        Ok(serde_json::to_string_pretty(&serde_json::json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "craft audit",
+                        "informationUri": "https://github.com/craftpkg/craft",
+                        "rules": rules.into_values().collect::<Vec<_>>(),
+                    },
+                },
+                "results": results,
+            }],
+        }))?)
+    }
+}
+
+impl AuditActor {
+    /// Findings in the packages the importers depend on, with the chains leading
+    /// to them, and how many of them are `ignored`
+    fn report(
+        &self,
+        lockfile: &Lockfile,
+        advisories: &BTreeMap<String, Vec<Advisory>>,
+        importers: &[Importer],
+        ignored: impl Fn(&Finding) -> bool,
+    ) -> (Vec<Finding>, usize) {
+        let (ignored, mut findings): (Vec<Finding>, Vec<Finding>) =
+            Self::findings(lockfile, advisories)
+                .into_iter()
+                .partition(|finding| ignored(finding));
+
+        // Chains from the selected importers, like `craft why`
+        let graph = DependencyGraph::new(lockfile);
+        for finding in &mut findings {
+            let target = format!("{}@{}", finding.name, finding.version);
+            for importer in importers {
+                let edges = importer.edges(&graph);
+                for is_dev in [false, true] {
+                    let roots: Vec<Edge> = edges
+                        .iter()
+                        .filter(|(_, dev)| *dev == is_dev)
+                        .map(|(edge, _)| edge.clone())
+                        .collect();
+                    for path in graph.paths(&roots, |entry| entry.key() == target) {
+                        finding
+                            .paths
+                            .push(WhyActor::chain(&importer.name, is_dev, &path));
+                    }
+                }
+            }
+        }
+        // With --filter, only packages the selected importers depend on are reported
+        if !self.payload.filter.is_empty() {
+            findings.retain(|finding| !finding.paths.is_empty());
+        }
+
+        (findings, ignored.len())
+    }
+
+    /// Plans moving each vulnerable package to the lowest version clearing its
+    /// advisories, among the versions published to `registry`
+    async fn plans(
+        registry: &str,
+        lockfile: &Lockfile,
+        advisories: &BTreeMap<String, Vec<Advisory>>,
+        importers: &[Importer],
+        findings: &[Finding],
+    ) -> Result<Vec<FixPlan>> {
+        let graph = DependencyGraph::new(lockfile);
+        let resolver = NpmResolver::new().with_registry(registry.to_string());
+        let vulnerable: BTreeSet<(&str, &str)> = findings
+            .iter()
+            .map(|finding| (finding.name.as_str(), finding.version.as_str()))
+            .collect();
+
+        let mut plans = Vec::new();
+        for (name, version) in vulnerable {
+            let ranges: Vec<&str> = advisories
+                .get(name)
+                .into_iter()
+                .flatten()
+                .map(|advisory| advisory.vulnerable_versions.as_str())
+                .collect();
+            let npm_package = resolver.fetch_package(name).await?;
+            let Some(to) = audit_fix::minimal_fix(npm_package.versions.keys(), version, &ranges)
+            else {
+                println!("{}@{} has no fixed version", name, version);
+                continue;
+            };
+
+            let key = format!("{}@{}", name, version);
+            let direct: Vec<(usize, bool, String)> = importers
+                .iter()
+                .enumerate()
+                .flat_map(|(i, importer)| {
+                    importer
+                        .edges(&graph)
+                        .into_iter()
+                        .filter(|(edge, _)| edge.entry.key() == key)
+                        .map(move |(edge, is_dev)| (i, is_dev, edge.range))
+                })
+                .collect();
+            let transitive: Vec<String> = lockfile
+                .packages
+                .values()
+                .flat_map(|entry| graph.dependencies(entry))
+                .filter(|edge| edge.entry.key() == key)
+                .map(|edge| edge.range)
+                .collect();
+
+            plans.push(FixPlan::new(name, version, &to, &direct, &transitive));
+        }
+
+        Ok(plans)
+    }
+
+    /// `- name@version` and `+ name@version` lines for the lockfile's changed entries
+    fn lockfile_diff(before: &Lockfile, after: &Lockfile) -> Vec<String> {
+        let removed = before
+            .packages
+            .keys()
+            .filter(|key| !after.packages.contains_key(*key))
+            .map(|key| format!("- {}", key));
+        let added = after
+            .packages
+            .keys()
+            .filter(|key| !before.packages.contains_key(*key))
+            .map(|key| format!("+ {}", key));
+        removed.chain(added).collect()
+    }
+
+    async fn fix(
+        &self,
+        cwd: &Path,
+        registry: &str,
+        lockfile: &Lockfile,
+        advisories: &BTreeMap<String, Vec<Advisory>>,
+        importers: &[Importer],
+        findings: &[Finding],
+    ) -> Result<()> {
+        if findings.is_empty() {
+            println!("{}", Self::summary(findings));
+            return Ok(());
+        }
+
+        let plans = Self::plans(registry, lockfile, advisories, importers, findings).await?;
+        for plan in &plans {
+            let ids: BTreeSet<String> = findings
+                .iter()
+                .filter(|f| f.name == plan.name && f.version == plan.from)
+                .map(|f| f.advisory.id())
+                .collect();
+            println!(
+                "{} {} → {} ({})",
+                plan.name,
+                plan.from,
+                plan.to,
+                ids.into_iter().collect::<Vec<_>>().join(", ")
+            );
+            for line in plan.describe(|i| importers[i].name.clone()) {
+                println!("  {}", line);
+            }
+        }
+
+        if self.payload.dry_run {
+            let mut expected = lockfile.clone();
+            for plan in &plans {
+                if let Some(mut entry) = expected
+                    .packages
+                    .remove(&format!("{}@{}", plan.name, plan.from))
+                {
+                    entry.version = plan.to.clone();
+                    expected.add_package(entry);
+                }
+            }
+            println!("\n{} (expected)", LOCKFILE_NAME);
+            for line in Self::lockfile_diff(lockfile, &expected) {
+                println!("  {}", line);
+            }
+            println!("\nDry run, nothing was changed");
+            return Ok(());
+        }
+
+        for (i, importer) in importers.iter().enumerate() {
+            let mut package_json = importer.package_json.clone();
+            let mut changed = false;
+            for plan in &plans {
+                changed |= plan.apply_bumps(i, &mut package_json);
+            }
+            if changed {
+                let path = importer.dir.join("package.json");
+                tokio::fs::write(&path, serde_json::to_string_pretty(&package_json)?).await?;
+                debug::info!("Updated {:?}", path);
+            }
+        }
+
+        // npm only reads overrides from the root package.json
+        if plans.iter().any(|plan| plan.pin) {
+            let root = match Workspace::find(cwd).await? {
+                Some(workspace) => workspace.root,
+                None => cwd.to_path_buf(),
+            };
+            let path = root.join("package.json");
+            let mut package_json = PackageJson::from_path(&path).await?;
+            for plan in plans.iter().filter(|plan| plan.pin) {
+                plan.apply_override(&mut package_json);
+            }
+            tokio::fs::write(&path, serde_json::to_string_pretty(&package_json)?).await?;
+            debug::info!("Updated {:?}", path);
+        }
+
+        InstallActor::with(InstallActorPayload {
+            filter: self.payload.filter.clone(),
+            resolve_only: None,
+        })
+        .run()
+        .await?;
+
+        let after = dependency_versions::load_lockfile(cwd)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("No lockfile was written"))?;
+        println!("\n{}", LOCKFILE_NAME);
+        for line in Self::lockfile_diff(lockfile, &after) {
+            println!("  {}", line);
+        }
+
+        // Audit the new lockfile against the same advisories
+        let importers = importers::load(cwd, &self.payload.filter).await?;
+        let remaining: Vec<Finding> = self
+            .report(&after, advisories, &importers, |finding| {
+                !findings
+                    .iter()
+                    .any(|f| f.advisory.id() == finding.advisory.id())
+            })
+            .0;
+        println!(
+            "\nFixed {} of {}, {} remaining",
+            findings.len().saturating_sub(remaining.len()),
+            findings.len(),
+            remaining.len()
+        );
+
+        if remaining.is_empty() {
+            Ok(())
+        } else {
+            Err(SilentExit { code: 1 }.into())
+        }
+    }
+}
+
+/// `YYYY-MM-DD` of a day counted from the Unix epoch, in the proleptic Gregorian calendar
+fn civil_date(days: i64) -> String {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    civil_date((seconds / 86_400) as i64)
+}
+
+impl Actor<AuditActorPayload> for AuditActor {
+    fn with(payload: AuditActorPayload) -> Self {
+        Self { payload }
+    }
+
+    async fn run(&self) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        let config = Config::load(&cwd).await?;
+        let lockfile = dependency_versions::load_lockfile(&cwd)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("No lockfile found, run craft install first"))?;
+
+        let level = match self.payload.audit_level {
+            Some(level) => level,
+            None => config
+                .get("audit-level")
+                .map_or(Ok(AuditLevel::Info), str::parse)?,
+        };
+        let ignores = config.audit_ignores()?;
+        let today = today();
+        for ignore in &ignores {
+            if let Some(until) = ignore
+                .until
+                .as_deref()
+                .filter(|_| ignore.is_expired(&today))
+            {
+                eprintln!(
+                    "Warning: audit-ignore for {} expired on {}, it is reported again",
+                    ignore.id, until
+                );
+            }
+        }
+
+        let request = Self::request(&lockfile);
+        let advisories = if request.is_empty() {
+            BTreeMap::new()
+        } else {
+            Self::fetch(&config.audit_url(), &request).await?
+        };
+
+        let importers = importers::load(&cwd, &self.payload.filter).await?;
+        let (findings, ignored) = self.report(&lockfile, &advisories, &importers, |finding| {
+            Self::ignored_by(finding, &ignores, &today).is_some()
+        });
+        let findings: Vec<Finding> = findings
+            .into_iter()
+            .filter(|finding| finding.advisory.severity >= level)
+            .collect();
+
+        if self.payload.fix {
+            return self
+                .fix(
+                    &cwd,
+                    config.registry(),
+                    &lockfile,
+                    &advisories,
+                    &importers,
+                    &findings,
+                )
+                .await;
+        }
+
+        if self.payload.sarif {
+            println!("{}", Self::sarif(&findings)?);
+        } else if self.payload.json {
+            println!("{}", Self::json(&findings, ignored)?);
+        } else {
+            println!("{}", Self::table(&findings));
+            if ignored > 0 {
+                println!("{} ignored in {}", ignored, config::CONFIG_FILE_NAME);
+            }
+        }
+
+        // Vulnerabilities at or above the audit level fail the command for CI
+        if findings.is_empty() {
+            Ok(())
+        } else {
+            Err(SilentExit { code: 1 }.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lockfile::PackageEntry;
+
+    fn advisory(severity: AuditLevel) -> Advisory {
+        Advisory {
+            id: serde_json::json!(1_096_366),
+            title: "Prototype Pollution in lodash".to_string(),
+            url: "https://github.com/advisories/GHSA-p6mc-m468-83gw".to_string(),
+            severity,
+            vulnerable_versions: "<4.17.19".to_string(),
+        }
+    }
+
+    fn finding(severity: AuditLevel) -> Finding {
+        Finding {
+            name: "lodash".to_string(),
+            version: "4.17.15".to_string(),
+            advisory: advisory(severity),
+            paths: vec!["app › express@^4.0.0 › lodash@^4.17.0".to_string()],
+        }
+    }
+
+    fn lockfile() -> Lockfile {
+        let mut lockfile = Lockfile::new();
+        lockfile.add_package(PackageEntry::new(
+            "lodash".to_string(),
+            "4.17.15".to_string(),
+            "https://registry.npmjs.org/lodash/-/lodash-4.17.15.tgz".to_string(),
+        ));
+        lockfile.add_package(PackageEntry::new(
+            "lodash".to_string(),
+            "4.17.21".to_string(),
+            "https://registry.npmjs.org/lodash/-/lodash-4.17.21.tgz".to_string(),
+        ));
+        lockfile.add_package(PackageEntry::new(
+            "local".to_string(),
+            "1.0.0".to_string(),
+            "file:../local".to_string(),
+        ));
+        lockfile
+    }
+
+    #[test]
+    fn test_audit_level() {
+        assert_eq!(
+            "moderate".parse::<AuditLevel>().unwrap(),
+            AuditLevel::Moderate
+        );
+        assert!("severe".parse::<AuditLevel>().is_err());
+        assert!(AuditLevel::Critical > AuditLevel::High);
+        assert!(AuditLevel::Low > AuditLevel::Info);
+    }
+
+    #[test]
+    fn test_advisory_ids() {
+        let advisory = advisory(AuditLevel::High);
+        assert_eq!(advisory.id(), "GHSA-p6mc-m468-83gw");
+        assert!(advisory.is_named("GHSA-p6mc-m468-83gw"));
+        assert!(advisory.is_named("1096366"));
+        assert!(!advisory.is_named("GHSA-xxxx-xxxx-xxxx"));
+    }
+
+    #[test]
+    fn test_request_skips_local_packages() {
+        let request = AuditActor::request(&lockfile());
+        assert_eq!(request.len(), 1);
+        assert_eq!(
+            request["lodash"].iter().collect::<Vec<_>>(),
+            ["4.17.15", "4.17.21"]
+        );
+    }
+
+    #[test]
+    fn test_findings() {
+        let advisories = BTreeMap::from([("lodash".to_string(), vec![advisory(AuditLevel::High)])]);
+        let findings = AuditActor::findings(&lockfile(), &advisories);
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].version, "4.17.15");
+    }
+
+    #[test]
+    fn test_ignored_by() {
+        let finding = finding(AuditLevel::High);
+        let ignores = [AuditIgnore::parse("GHSA-p6mc-m468-83gw until 2026-01-31").unwrap()];
+
+        assert!(AuditActor::ignored_by(&finding, &ignores, "2026-01-31").is_some());
+        assert!(AuditActor::ignored_by(&finding, &ignores, "2026-02-01").is_none());
+    }
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(AuditActor::summary(&[]), "0 vulnerabilities");
+        assert_eq!(
+            AuditActor::summary(&[finding(AuditLevel::High)]),
+            "1 vulnerability (1 high)"
+        );
+        assert_eq!(
+            AuditActor::summary(&[
+                finding(AuditLevel::Critical),
+                finding(AuditLevel::Low),
+                finding(AuditLevel::Low)
+            ]),
+            "3 vulnerabilities (2 low, 1 critical)"
+        );
+    }
+
+    #[test]
+    fn test_table() {
+        let table = AuditActor::table(&[finding(AuditLevel::High)]);
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(lines[0], "high      Prototype Pollution in lodash");
+        assert_eq!(lines[1], "          lodash@4.17.15 (vulnerable: <4.17.19)");
+        assert_eq!(lines[3], "          app › express@^4.0.0 › lodash@^4.17.0");
+        assert_eq!(lines.last(), Some(&"1 vulnerability (1 high)"));
+    }
+
+    #[test]
+    fn test_json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&AuditActor::json(&[finding(AuditLevel::High)], 2).unwrap())
+                .unwrap();
+        assert_eq!(json["advisories"][0]["id"], "GHSA-p6mc-m468-83gw");
+        assert_eq!(json["advisories"][0]["severity"], "high");
+        assert_eq!(json["advisories"][0]["vulnerableVersions"], "<4.17.19");
+        assert_eq!(json["ignored"], 2);
+    }
+
+    #[test]
+    fn test_sarif() {
+        let sarif: serde_json::Value = serde_json::from_str(
+            &AuditActor::sarif(&[finding(AuditLevel::Critical), finding(AuditLevel::Critical)])
+                .unwrap(),
+        )
+        .unwrap();
+        let run = &sarif["runs"][0];
+
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(run["tool"]["driver"]["rules"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            run["tool"]["driver"]["rules"][0]["properties"]["security-severity"],
+            "9.0"
+        );
+        assert_eq!(run["results"][0]["ruleId"], "GHSA-p6mc-m468-83gw");
+        assert_eq!(run["results"][0]["level"], "error");
+        assert_eq!(
+            run["results"][0]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "craft.lock"
+        );
+    }
+
+    #[test]
+    fn test_civil_date() {
+        assert_eq!(civil_date(0), "1970-01-01");
+        assert_eq!(civil_date(11_016), "2000-02-29");
+        assert_eq!(civil_date(20_000), "2024-10-04");
+        assert_eq!(today().len(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_from_mock_registry() {
+        let registry = registry::MockRegistry::start().unwrap();
+        registry.advise(
+            "lodash",
+            serde_json::json!({
+                "id": 1_096_366,
+                "title": "Prototype Pollution in lodash",
+                "url": "https://github.com/advisories/GHSA-p6mc-m468-83gw",
+                "severity": "high",
+                "vulnerable_versions": "<4.17.19",
+            }),
+        );
+        let url = format!("{}/-/npm/v1/security/advisories/bulk", registry.url());
+
+        let advisories = AuditActor::fetch(&url, &AuditActor::request(&lockfile()))
+            .await
+            .unwrap();
+
+        let requests = registry.requests();
+        assert_eq!(requests[0].method, "POST");
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({ "lodash": ["4.17.15", "4.17.21"] })
+        );
+        assert_eq!(advisories["lodash"][0].severity, AuditLevel::High);
+    }
+}
@@ -0,0 +1,251 @@
+use node_semver::{Range, Version};
+use package::{PackageJson, range};
+
+/// The lowest published version above `current` outside every `vulnerable` range,
+/// skipping prereleases
+pub(crate) fn minimal_fix<'a>(
+    versions: impl IntoIterator<Item = &'a String>,
+    current: &str,
+    vulnerable: &[&str],
+) -> Option<String> {
+    let current = Version::parse(current).ok()?;
+    let vulnerable: Vec<Range> = vulnerable
+        .iter()
+        .filter_map(|range| Range::parse(range).ok())
+        .collect();
+
+    versions
+        .into_iter()
+        .filter_map(|version| Version::parse(version).ok())
+        .filter(|version| !version.is_prerelease() && *version > current)
+        .filter(|version| !vulnerable.iter().any(|range| range.satisfies(version)))
+        .min()
+        .map(|version| version.to_string())
+}
+
+fn satisfies(range: &str, version: &str) -> bool {
+    match (Range::parse(range), Version::parse(version)) {
+        (Ok(range), Ok(version)) => range.satisfies(&version),
+        // Dist-tags, git and file specs aren't semver, a reinstall decides
+        _ => true,
+    }
+}
+
+/// A direct dependency whose range doesn't allow the fixed version
+#[derive(Debug, PartialEq)]
+pub(crate) struct RangeBump {
+    /// Index of the importer declaring the dependency
+    pub importer: usize,
+    pub is_dev: bool,
+    pub from: String,
+    pub to: String,
+}
+
+/// How a vulnerable locked package moves to the version fixing it: within the
+/// declared ranges a reinstall is enough, otherwise direct dependencies get a new
+/// range and transitive ones an `overrides` entry in the root package.json
+#[derive(Debug, PartialEq)]
+pub(crate) struct FixPlan {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+    pub bumps: Vec<RangeBump>,
+    /// Whether a package depending on it declares a range without the fix
+    pub pin: bool,
+}
+
+impl FixPlan {
+    /// Plan the fix from the ranges declaring the package: `direct` ones as
+    /// `(importer, is_dev, range)` and the `transitive` ones of other packages
+    pub fn new(
+        name: &str,
+        from: &str,
+        to: &str,
+        direct: &[(usize, bool, String)],
+        transitive: &[String],
+    ) -> Self {
+        let bumps = direct
+            .iter()
+            .filter(|(_, _, range)| !satisfies(range, to))
+            .map(|(importer, is_dev, range)| RangeBump {
+                importer: *importer,
+                is_dev: *is_dev,
+                from: range.clone(),
+                to: range::to_latest(range, to).unwrap_or_else(|| format!("^{}", to)),
+            })
+            .collect();
+
+        Self {
+            name: name.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+            bumps,
+            pin: transitive.iter().any(|range| !satisfies(range, to)),
+        }
+    }
+
+    /// The `overrides` selector pinning only the vulnerable version
+    pub fn override_key(&self) -> String {
+        format!("{}@{}", self.name, self.from)
+    }
+
+    /// The planned changes, one line each, with importers named by `importer_name`
+    pub fn describe(&self, importer_name: impl Fn(usize) -> String) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .bumps
+            .iter()
+            .map(|bump| {
+                format!(
+                    "{}: {} {} {} → {}",
+                    importer_name(bump.importer),
+                    if bump.is_dev {
+                        "devDependencies"
+                    } else {
+                        "dependencies"
+                    },
+                    self.name,
+                    bump.from,
+                    bump.to
+                )
+            })
+            .collect();
+
+        if self.pin {
+            lines.push(format!(
+                "overrides: \"{}\": \"{}\"",
+                self.override_key(),
+                self.to
+            ));
+        }
+        if lines.is_empty() {
+            lines.push("within the declared ranges, reinstalling".to_string());
+        }
+        lines
+    }
+
+    /// Raise the ranges of `importer`'s package.json, whether any changed
+    pub fn apply_bumps(&self, importer: usize, package_json: &mut PackageJson) -> bool {
+        let mut changed = false;
+        for bump in self.bumps.iter().filter(|bump| bump.importer == importer) {
+            let deps = if bump.is_dev {
+                &mut package_json.dev_dependencies
+            } else {
+                &mut package_json.dependencies
+            };
+            if let Some(deps) = deps {
+                deps.insert(self.name.clone(), bump.to.clone());
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Add the `overrides` entry to the root package.json
+    pub fn apply_override(&self, package_json: &mut PackageJson) {
+        package_json
+            .overrides
+            .get_or_insert_with(Default::default)
+            .insert(self.override_key(), serde_json::json!(self.to));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions(versions: &[&str]) -> Vec<String> {
+        versions.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_minimal_fix() {
+        let published = versions(&[
+            "4.17.15",
+            "4.17.16",
+            "4.17.19",
+            "4.17.20",
+            "4.17.21",
+            "5.0.0-rc.1",
+        ]);
+
+        assert_eq!(
+            minimal_fix(&published, "4.17.15", &["<4.17.19"]).as_deref(),
+            Some("4.17.19")
+        );
+        // The fix must clear every advisory of the package
+        assert_eq!(
+            minimal_fix(&published, "4.17.15", &["<4.17.19", "<4.17.21"]).as_deref(),
+            Some("4.17.21")
+        );
+        assert_eq!(minimal_fix(&published, "4.17.15", &["*"]), None);
+        assert_eq!(minimal_fix(&published, "4.17.15", &["<5.0.0"]), None);
+    }
+
+    #[test]
+    fn test_plan_within_range() {
+        let plan = FixPlan::new(
+            "lodash",
+            "4.17.15",
+            "4.17.19",
+            &[(0, false, "^4.17.0".to_string())],
+            &["^4.0.0".to_string()],
+        );
+
+        assert!(plan.bumps.is_empty());
+        assert!(!plan.pin);
+        assert_eq!(
+            plan.describe(|_| "app".to_string()),
+            ["within the declared ranges, reinstalling"]
+        );
+    }
+
+    #[test]
+    fn test_plan_outside_range() {
+        let plan = FixPlan::new(
+            "minimist",
+            "0.0.8",
+            "1.2.6",
+            &[(1, true, "~0.0.8".to_string())],
+            &["0.0.8".to_string()],
+        );
+
+        assert_eq!(
+            plan.bumps,
+            [RangeBump {
+                importer: 1,
+                is_dev: true,
+                from: "~0.0.8".to_string(),
+                to: "~1.2.6".to_string(),
+            }]
+        );
+        assert!(plan.pin);
+        assert_eq!(
+            plan.describe(|_| "web".to_string()),
+            [
+                "web: devDependencies minimist ~0.0.8 → ~1.2.6",
+                "overrides: \"minimist@0.0.8\": \"1.2.6\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut package_json: PackageJson =
+            serde_json::from_str(r#"{"devDependencies": {"minimist": "~0.0.8"}}"#).unwrap();
+        let plan = FixPlan::new(
+            "minimist",
+            "0.0.8",
+            "1.2.6",
+            &[(0, true, "~0.0.8".to_string())],
+            &["0.0.8".to_string()],
+        );
+
+        assert!(plan.apply_bumps(0, &mut package_json));
+        assert!(!plan.apply_bumps(1, &mut package_json));
+        plan.apply_override(&mut package_json);
+
+        let json = serde_json::to_value(&package_json).unwrap();
+        assert_eq!(json["devDependencies"]["minimist"], "~1.2.6");
+        assert_eq!(json["overrides"]["minimist@0.0.8"], "1.2.6");
+    }
+}
@@ -0,0 +1,251 @@
+use std::path::Path;
+
+use config::Config;
+use contract::{LOCKFILE_NAME, Result};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use lockfile::Lockfile;
+use node_semver::{Range, Version};
+use package::{InstallPackage, PackageJson, Workspace};
+use resolver::NpmResolver;
+
+/// Registry versions of one dependency of a package.json
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DependencyVersions {
+    pub name: String,
+    pub range: String,
+    pub is_dev: bool,
+    /// Version in the lockfile, or installed in node_modules without one
+    pub current: Option<String>,
+    /// Highest version satisfying `range`
+    pub wanted: Option<String>,
+    /// The `latest` dist-tag
+    pub latest: Option<String>,
+}
+
+/// How far apart two versions are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Severity {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl Severity {
+    /// The kind of bump from `from` to `to`. Below 1.0.0, a minor bump is breaking
+    /// and counts as major, like `^0.x` ranges treat it.
+    pub fn between(from: &str, to: &str) -> Option<Self> {
+        let from = Version::parse(from).ok()?;
+        let to = Version::parse(to).ok()?;
+
+        if from == to {
+            None
+        } else if from.major != to.major || (from.major == 0 && from.minor != to.minor) {
+            Some(Self::Major)
+        } else if from.minor != to.minor {
+            Some(Self::Minor)
+        } else {
+            Some(Self::Patch)
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Patch => "patch",
+            Self::Minor => "minor",
+            Self::Major => "major",
+        }
+    }
+}
+
+/// Package name patterns given on the command line (`react`, `@types/*`)
+#[derive(Debug, Default)]
+pub(crate) struct NamePatterns {
+    patterns: Vec<(String, glob::Pattern)>,
+}
+
+impl NamePatterns {
+    pub fn parse(patterns: &[String]) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|p| Ok((p.clone(), glob::Pattern::new(p)?)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { patterns })
+    }
+
+    /// Every name matches when no pattern was given
+    pub fn matches(&self, name: &str) -> bool {
+        self.patterns.is_empty() || self.patterns.iter().any(|(_, p)| p.matches(name))
+    }
+
+    /// Patterns matching none of `names`
+    pub fn unmatched<'a>(&'a self, names: &[&str]) -> Vec<&'a str> {
+        self.patterns
+            .iter()
+            .filter(|(_, p)| !names.iter().any(|name| p.matches(name)))
+            .map(|(literal, _)| literal.as_str())
+            .collect()
+    }
+}
+
+/// Whether a dependency range is resolved from the registry by version, which excludes
+/// git, path, URL, `workspace:` and `npm:` alias specs
+fn is_versioned(range: &str) -> bool {
+    InstallPackage::is_registry_spec(range)
+        && !range.starts_with("workspace:")
+        && !range.starts_with("npm:")
+}
+
+/// The lockfile of the project (or workspace) containing `cwd`, if it has one
+pub(crate) async fn load_lockfile(cwd: &Path) -> Result<Option<Lockfile>> {
+    let root = Workspace::find(cwd)
+        .await?
+        .map_or(cwd.to_path_buf(), |w| w.root);
+    let path = root.join(LOCKFILE_NAME);
+
+    if path.exists() {
+        Ok(Some(Lockfile::load(&path).await?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// The locked version of `name` for a dependency on `range`. A package locked at
+/// several versions (for other dependents) is matched by range, and one locked
+/// only outside of `range` isn't what the dependency is installed at.
+fn locked_version(lockfile: &Lockfile, name: &str, range: &str) -> Option<String> {
+    let range = Range::parse(range).ok()?;
+    lockfile
+        .packages
+        .values()
+        .filter(|entry| entry.name == name)
+        .filter_map(|entry| Version::parse(&entry.version).ok())
+        .filter(|version| range.satisfies(version))
+        .max()
+        .map(|version| version.to_string())
+}
+
+/// The version of `name` installed in `dir`'s node_modules
+async fn installed_version(dir: &Path, name: &str) -> Option<String> {
+    let path = dir.join("node_modules").join(name).join("package.json");
+    PackageJson::from_path(&path).await.ok()?.version
+}
+
+/// Look up the registry versions of the dependencies of `package_json` (in `dir`)
+/// whose name matches `patterns`, sorted by name
+pub(crate) async fn collect(
+    dir: &Path,
+    package_json: &PackageJson,
+    patterns: &NamePatterns,
+    lockfile: Option<&Lockfile>,
+) -> Result<Vec<DependencyVersions>> {
+    let config = Config::load(dir).await?;
+    let resolver = NpmResolver::new().with_registry(config.registry().to_string());
+
+    let deps: Vec<(String, String, bool)> = [
+        (&package_json.dependencies, false),
+        (&package_json.dev_dependencies, true),
+    ]
+    .into_iter()
+    .flat_map(|(deps, is_dev)| {
+        deps.iter()
+            .flatten()
+            .map(move |(name, range)| (name.clone(), range.clone(), is_dev))
+    })
+    .filter(|(name, range, _)| is_versioned(range) && patterns.matches(name))
+    .collect();
+
+    // A few packuments at a time, CPU cores * 2 like installs
+    let concurrency = std::thread::available_parallelism()
+        .map(|n| n.get() * 2)
+        .unwrap_or(4)
+        .max(2);
+
+    let mut versions: Vec<DependencyVersions> = stream::iter(deps)
+        .map(|(name, range, is_dev)| {
+            let resolver = &resolver;
+            async move {
+                let npm_package = resolver.fetch_package(&name).await?;
+                let current = match lockfile {
+                    Some(lockfile) => locked_version(lockfile, &name, &range),
+                    None => installed_version(dir, &name).await,
+                };
+
+                Ok::<_, anyhow::Error>(DependencyVersions {
+                    current,
+                    wanted: NpmResolver::max_satisfying(&npm_package, &range),
+                    latest: npm_package.dist_tags.get("latest").cloned(),
+                    name,
+                    range,
+                    is_dev,
+                })
+            }
+        })
+        .buffer_unordered(concurrency)
+        .try_collect()
+        .await?;
+
+    versions.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_patterns() {
+        let patterns = NamePatterns::parse(&["@types/*".to_string(), "react".to_string()]).unwrap();
+        assert!(patterns.matches("@types/node"));
+        assert!(patterns.matches("react"));
+        assert!(!patterns.matches("react-dom"));
+        assert_eq!(patterns.unmatched(&["@types/node"]), vec!["react"]);
+
+        assert!(NamePatterns::default().matches("anything"));
+    }
+
+    #[test]
+    fn test_severity() {
+        assert_eq!(Severity::between("1.2.3", "1.2.4"), Some(Severity::Patch));
+        assert_eq!(
+            Severity::between("1.2.3", "1.3.0-beta.1"),
+            Some(Severity::Minor)
+        );
+        assert_eq!(Severity::between("1.2.3", "2.0.0"), Some(Severity::Major));
+        assert_eq!(Severity::between("0.2.3", "0.3.0"), Some(Severity::Major));
+        assert_eq!(Severity::between("1.2.3", "1.2.3"), None);
+        assert_eq!(Severity::between("1.2.3", "next"), None);
+    }
+
+    #[test]
+    fn test_locked_version() {
+        let mut lockfile = Lockfile::new();
+        for version in ["17.0.2", "18.2.0", "18.3.1"] {
+            lockfile.add_package(lockfile::PackageEntry::new(
+                "react".to_string(),
+                version.to_string(),
+                String::new(),
+            ));
+        }
+
+        assert_eq!(
+            locked_version(&lockfile, "react", "^17.0.0").as_deref(),
+            Some("17.0.2")
+        );
+        assert_eq!(
+            locked_version(&lockfile, "react", "^18.0.0").as_deref(),
+            Some("18.3.1")
+        );
+        assert_eq!(locked_version(&lockfile, "react", "^19.0.0"), None);
+        assert_eq!(locked_version(&lockfile, "vue", "^3.0.0"), None);
+    }
+
+    #[test]
+    fn test_is_versioned() {
+        assert!(is_versioned("^1.0.0"));
+        assert!(is_versioned("latest"));
+        assert!(!is_versioned("workspace:^"));
+        assert!(!is_versioned("npm:wrap-ansi@^7.0.0"));
+        assert!(!is_versioned("github:user/repo"));
+        assert!(!is_versioned("file:../lib"));
+    }
+}
@@ -0,0 +1,261 @@
+use std::path::{Path, PathBuf};
+
+use config::Config;
+use contract::{Actor, Pipeline, Result, get_dlx_cache_dir};
+use package::{InstallPackage, PackageBin, PackageJson};
+use pipeline::{InstallPipe, LinkerPipe};
+use process::{LifecycleEnv, Process};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug)]
+pub struct DlxActorPayload {
+    /// Packages to install. When empty, `command` is the package and its binary is run.
+    pub packages: Vec<String>,
+    pub command: String,
+    pub args: Vec<String>,
+    /// Run the binary from the project's `node_modules/.bin` when it's there (`exec`)
+    pub prefer_local: bool,
+}
+
+impl std::fmt::Display for DlxActorPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.command, self.args.join(" "))
+    }
+}
+
+pub struct DlxActor {
+    payload: DlxActorPayload,
+}
+
+impl DlxActor {
+    /// `node_modules/.bin/<command>` in `cwd` or one of its parents
+    fn local_bin(cwd: &Path, command: &str) -> Option<PathBuf> {
+        cwd.ancestors()
+            .map(|dir| dir.join("node_modules").join(".bin").join(command))
+            .find(|bin| bin.exists())
+    }
+
+    /// Install prefix for a set of packages, reused by later runs of the same
+    /// packages: a readable name, which different sets can share, and a hash of
+    /// the exact specs to tell them apart
+    fn prefix_dir(literals: &[String]) -> PathBuf {
+        let readable = literals
+            .iter()
+            .map(|literal| {
+                literal
+                    .chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() || matches!(c, '.' | '-') {
+                            c
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("+");
+        let hash = format!("{:x}", Sha256::digest(literals.join("\0").as_bytes()));
+
+        get_dlx_cache_dir().join(format!("{}-{}", readable, &hash[..16]))
+    }
+
+    /// Install `literals` into a prefix, returning it with the installed package.json
+    /// of the first package
+    async fn install(&self, literals: &[String]) -> Result<(PathBuf, PackageJson)> {
+        let prefix = Self::prefix_dir(literals);
+        // Packages of an earlier run are resolved again, not kept around
+        match tokio::fs::remove_dir_all(prefix.join("node_modules")).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        tokio::fs::create_dir_all(&prefix).await?;
+
+        let pkgs: Vec<InstallPackage> = literals
+            .iter()
+            .map(|literal| InstallPackage::from_literal(literal, false))
+            .collect();
+
+        debug::info!("Installing {:?} into {:?}", literals, prefix);
+
+        let config = Config::load(&std::env::current_dir()?).await?;
+        let artifacts = InstallPipe::new(pkgs.clone())
+            .with_registry(config.registry().to_string())
+            .run()
+            .await?;
+        LinkerPipe::new(artifacts.clone(), pkgs.clone())
+            .with_project_dir(prefix.clone())
+            .run()
+            .await?;
+
+        // Non-registry packages are only named once resolved
+        let first = &pkgs[0];
+        let name = if first.is_registry() {
+            first.name.clone()
+        } else {
+            artifacts
+                .iter()
+                .find(|a| a.matches_spec(first.spec()))
+                .map(|a| a.name.clone())
+                .ok_or_else(|| anyhow::anyhow!("Failed to install {}", first.spec()))?
+        };
+
+        let package_json =
+            PackageJson::from_path(&prefix.join("node_modules").join(&name).join("package.json"))
+                .await?;
+
+        Ok((prefix, package_json))
+    }
+
+    /// The binary to run for a package: its only one, or the one named after it
+    fn select_bin(package_json: &PackageJson) -> Result<String> {
+        let name = package_json.name.clone().unwrap_or_default();
+        let unscoped = name.rsplit('/').next().unwrap_or(&name).to_string();
+
+        match &package_json.bin {
+            Some(PackageBin::String(_)) => Ok(unscoped),
+            Some(PackageBin::Map(bins)) if bins.len() == 1 => {
+                Ok(bins.keys().next().cloned().unwrap_or(unscoped))
+            }
+            Some(PackageBin::Map(bins)) if bins.contains_key(&unscoped) => Ok(unscoped),
+            Some(PackageBin::Map(bins)) => {
+                let mut names: Vec<&String> = bins.keys().collect();
+                names.sort();
+                anyhow::bail!(
+                    "{} has multiple binaries ({}), choose one with: craft dlx --package {} <bin>",
+                    name,
+                    names
+                        .iter()
+                        .map(|n| n.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    name
+                )
+            }
+            None => anyhow::bail!("{} has no binaries", name),
+        }
+    }
+
+    async fn exec(&self, bin: &Path, bin_dir: Option<PathBuf>) -> Result<()> {
+        let mut env = LifecycleEnv::new("npx", &self.payload.command).with_command("exec");
+        if let Some(bin_dir) = bin_dir {
+            env = env.with_bin_dir(bin_dir);
+        }
+
+        let args: Vec<&str> = self.payload.args.iter().map(String::as_str).collect();
+        let cwd = std::env::current_dir()?;
+
+        env.apply(
+            Process::new(&bin.to_string_lossy())
+                .args(&args)
+                .current_dir(&cwd.to_string_lossy()),
+        )
+        .run()
+        .await
+    }
+}
+
+impl Actor<DlxActorPayload> for DlxActor {
+    fn with(payload: DlxActorPayload) -> Self {
+        Self { payload }
+    }
+
+    async fn run(&self) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+
+        if self.payload.prefer_local
+            && self.payload.packages.is_empty()
+            && let Some(bin) = Self::local_bin(&cwd, &self.payload.command)
+        {
+            debug::info!("Running local binary {:?}", bin);
+            return self.exec(&bin, None).await;
+        }
+
+        let (literals, requested_bin) = if self.payload.packages.is_empty() {
+            (vec![self.payload.command.clone()], None)
+        } else {
+            (
+                self.payload.packages.clone(),
+                Some(self.payload.command.clone()),
+            )
+        };
+
+        let (prefix, package_json) = self.install(&literals).await?;
+        let bin_dir = prefix.join("node_modules").join(".bin");
+
+        let bin = match requested_bin {
+            Some(bin) => bin,
+            None => Self::select_bin(&package_json)?,
+        };
+        let bin_path = bin_dir.join(&bin);
+
+        if !bin_path.exists() {
+            anyhow::bail!("{} is not a binary of {}", bin, literals.join(", "));
+        }
+
+        self.exec(&bin_path, Some(bin_dir)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package_json(json: &str) -> PackageJson {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_select_bin() {
+        let single = package_json(r#"{"name": "create-vite", "bin": "index.js"}"#);
+        assert_eq!(DlxActor::select_bin(&single).unwrap(), "create-vite");
+
+        let scoped = package_json(r#"{"name": "@angular/cli", "bin": {"ng": "bin/ng.js"}}"#);
+        assert_eq!(DlxActor::select_bin(&scoped).unwrap(), "ng");
+
+        let named = package_json(
+            r#"{"name": "typescript", "bin": {"tsc": "bin/tsc", "typescript": "bin/ts"}}"#,
+        );
+        assert_eq!(DlxActor::select_bin(&named).unwrap(), "typescript");
+
+        let ambiguous = package_json(
+            r#"{"name": "typescript", "bin": {"tsc": "bin/tsc", "tsserver": "bin/s"}}"#,
+        );
+        let error = DlxActor::select_bin(&ambiguous).unwrap_err().to_string();
+        assert!(error.contains("tsc, tsserver"));
+
+        let none = package_json(r#"{"name": "react"}"#);
+        assert!(DlxActor::select_bin(&none).is_err());
+    }
+
+    #[test]
+    fn test_local_bin() {
+        let root = tempfile::tempdir().unwrap();
+        let bin_dir = root.path().join("node_modules/.bin");
+        std::fs::create_dir_all(&bin_dir).unwrap();
+        std::fs::write(bin_dir.join("eslint"), "").unwrap();
+
+        let nested = root.path().join("packages/app");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        assert_eq!(
+            DlxActor::local_bin(&nested, "eslint"),
+            Some(bin_dir.join("eslint"))
+        );
+        assert_eq!(DlxActor::local_bin(&nested, "prettier"), None);
+    }
+
+    #[test]
+    fn test_prefix_dir() {
+        let dir = DlxActor::prefix_dir(&["@angular/cli@17".to_string(), "typescript".to_string()]);
+        let name = dir.file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with("_angular_cli_17+typescript-"));
+
+        // Specs sharing a readable name get their own prefix
+        assert_ne!(
+            DlxActor::prefix_dir(&["@a/b".to_string()]),
+            DlxActor::prefix_dir(&["_a_b".to_string()])
+        );
+    }
+}
@@ -0,0 +1,457 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use contract::{Actor, Result};
+use lockfile::{DependencyGraph, Edge, PackageEntry};
+use package::WorkspaceFilter;
+use resolver::{ArtifactSource, ResolvedArtifact};
+
+use crate::dependency_versions;
+use crate::importers;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+    Json,
+}
+
+impl FromStr for GraphFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "dot" => Ok(Self::Dot),
+            "mermaid" => Ok(Self::Mermaid),
+            "json" => Ok(Self::Json),
+            _ => anyhow::bail!("Unknown graph format {}, use dot, mermaid or json", format),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GraphActorPayload {
+    pub format: GraphFormat,
+    /// Only the part of the graph below this direct dependency
+    pub root: Option<String>,
+    pub filter: WorkspaceFilter,
+}
+
+impl std::fmt::Display for GraphActorPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.format)
+    }
+}
+
+pub struct GraphActor {
+    payload: GraphActorPayload,
+}
+
+/// A package, or a project (importer) whose dependencies are the graph's roots
+#[derive(Debug, Clone, PartialEq)]
+struct Node {
+    id: String,
+    name: String,
+    version: Option<String>,
+    /// Size on disk, when the package is in the cache
+    size: Option<u64>,
+    /// Only reachable through devDependencies
+    dev: bool,
+    importer: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Link {
+    from: String,
+    to: String,
+    range: String,
+}
+
+#[derive(Debug, Default)]
+struct Export {
+    nodes: Vec<Node>,
+    links: Vec<Link>,
+}
+
+/// `12.3 kB`, like npm's pack output
+pub(crate) fn human_size(bytes: u64) -> String {
+    match bytes {
+        0..1_000 => format!("{} B", bytes),
+        1_000..1_000_000 => format!("{:.1} kB", bytes as f64 / 1_000.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_000_000.0),
+    }
+}
+
+/// Total size of the files under `dir`, symlinked dependencies aren't followed
+async fn dir_size(dir: &Path) -> Option<u64> {
+    let mut size = 0;
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let mut entries = tokio::fs::read_dir(&dir).await.ok()?;
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let Ok(metadata) = tokio::fs::symlink_metadata(entry.path()).await else {
+                continue;
+            };
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+            } else if metadata.is_file() {
+                size += metadata.len();
+            }
+        }
+    }
+
+    Some(size)
+}
+
+/// Where a locked registry or tarball package is extracted in the cache
+fn cache_dir(entry: &PackageEntry) -> Option<PathBuf> {
+    if !entry.resolved.starts_with("http://") && !entry.resolved.starts_with("https://") {
+        return None;
+    }
+
+    let artifact = |source| ResolvedArtifact {
+        name: entry.name.clone(),
+        version: entry.version.clone(),
+        download_url: entry.resolved.clone(),
+        package: None,
+        source,
+        patch: None,
+        cache: None,
+        scope: None,
+        dependency_scope: None,
+    };
+
+    [
+        artifact(ArtifactSource::Registry),
+        artifact(ArtifactSource::Tarball {
+            url: entry.resolved.clone(),
+        }),
+    ]
+    .into_iter()
+    .map(|a| a.package_dir())
+    .find(|dir| dir.exists())
+}
+
+impl Export {
+    fn node_ids(&self) -> BTreeMap<&str, String> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.id.as_str(), format!("n{}", i)))
+            .collect()
+    }
+
+    fn label(node: &Node) -> Vec<String> {
+        let mut label = vec![node.name.clone()];
+        label.extend(node.version.clone());
+        label.extend(node.size.map(human_size));
+        label
+    }
+
+    fn to_dot(&self) -> String {
+        let mut lines = vec![
+            "digraph dependencies {".to_string(),
+            "  rankdir=LR;".to_string(),
+        ];
+
+        for node in &self.nodes {
+            let mut attributes = vec![format!("label=\"{}\"", Self::label(node).join("\\n"))];
+            if node.importer {
+                attributes.push("shape=box".to_string());
+            }
+            if node.dev {
+                attributes.push("style=dashed".to_string());
+            }
+            lines.push(format!("  \"{}\" [{}];", node.id, attributes.join(", ")));
+        }
+        for link in &self.links {
+            lines.push(format!(
+                "  \"{}\" -> \"{}\" [label=\"{}\"];",
+                link.from, link.to, link.range
+            ));
+        }
+
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+
+    fn to_mermaid(&self) -> String {
+        let ids = self.node_ids();
+        let mut lines = vec!["graph LR".to_string()];
+
+        for node in &self.nodes {
+            let label = Self::label(node).join("<br/>");
+            let shape = if node.importer {
+                format!("[\"{}\"]", label)
+            } else {
+                format!("(\"{}\")", label)
+            };
+            lines.push(format!("  {}{}", ids[node.id.as_str()], shape));
+        }
+        for link in &self.links {
+            lines.push(format!(
+                "  {} -->|\"{}\"| {}",
+                ids[link.from.as_str()],
+                link.range,
+                ids[link.to.as_str()]
+            ));
+        }
+
+        let dev: Vec<&str> = self
+            .nodes
+            .iter()
+            .filter(|n| n.dev)
+            .map(|n| ids[n.id.as_str()].as_str())
+            .collect();
+        if !dev.is_empty() {
+            lines.push("  classDef dev stroke-dasharray: 5 5".to_string());
+            lines.push(format!("  class {} dev", dev.join(",")));
+        }
+
+        lines.join("\n")
+    }
+
+    fn to_json(&self) -> Result<String> {
+        let nodes: Vec<serde_json::Value> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                serde_json::json!({
+                    "id": node.id,
+                    "name": node.name,
+                    "version": node.version,
+                    "size": node.size,
+                    "dev": node.dev,
+                    "importer": node.importer,
+                })
+            })
+            .collect();
+        let edges: Vec<serde_json::Value> = self
+            .links
+            .iter()
+            .map(
+                |link| serde_json::json!({ "from": link.from, "to": link.to, "range": link.range }),
+            )
+            .collect();
+
+        Ok(serde_json::to_string_pretty(
+            &serde_json::json!({ "nodes": nodes, "edges": edges }),
+        )?)
+    }
+}
+
+/// An importer's name, version and root edges (with whether they're dev)
+type ImporterRoots<'a> = (String, Option<String>, Vec<(Edge<'a>, bool)>);
+
+impl GraphActor {
+    /// Collect the packages reachable from each importer's roots. A package is
+    /// dev when no production root reaches it.
+    fn export<'a>(
+        graph: &DependencyGraph<'a>,
+        importers: &[ImporterRoots<'a>],
+    ) -> (Export, Vec<&'a PackageEntry>) {
+        let mut export = Export::default();
+        let mut entries: BTreeMap<String, &PackageEntry> = BTreeMap::new();
+        let mut links: BTreeSet<(String, String, String)> = BTreeSet::new();
+        let mut prod: BTreeSet<String> = BTreeSet::new();
+
+        // Production roots first, so the dev flag only sticks to dev-only packages
+        for dev_pass in [false, true] {
+            for (name, version, roots) in importers {
+                let mut queue: VecDeque<&PackageEntry> = VecDeque::new();
+
+                for (edge, _) in roots.iter().filter(|(_, dev)| *dev == dev_pass) {
+                    links.insert((name.clone(), edge.entry.key(), edge.range.clone()));
+                    queue.push_back(edge.entry);
+                }
+
+                if !dev_pass {
+                    export.nodes.push(Node {
+                        id: name.clone(),
+                        name: name.clone(),
+                        version: version.clone(),
+                        size: None,
+                        dev: false,
+                        importer: true,
+                    });
+                }
+
+                while let Some(entry) = queue.pop_front() {
+                    let key = entry.key();
+                    if entries.insert(key.clone(), entry).is_some() {
+                        continue;
+                    }
+                    if !dev_pass {
+                        prod.insert(key.clone());
+                    }
+
+                    for edge in graph.dependencies(entry) {
+                        links.insert((key.clone(), edge.entry.key(), edge.range.clone()));
+                        queue.push_back(edge.entry);
+                    }
+                }
+            }
+        }
+
+        for (key, entry) in &entries {
+            export.nodes.push(Node {
+                id: key.clone(),
+                name: entry.name.clone(),
+                version: Some(entry.version.clone()),
+                size: None,
+                dev: !prod.contains(key),
+                importer: false,
+            });
+        }
+        export.links = links
+            .into_iter()
+            .map(|(from, to, range)| Link { from, to, range })
+            .collect();
+
+        (export, entries.into_values().collect())
+    }
+}
+
+impl Actor<GraphActorPayload> for GraphActor {
+    fn with(payload: GraphActorPayload) -> Self {
+        Self { payload }
+    }
+
+    async fn run(&self) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        let lockfile = dependency_versions::load_lockfile(&cwd)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("No lockfile found, run craft install first"))?;
+        let graph = DependencyGraph::new(&lockfile);
+
+        let mut importers = Vec::new();
+        for importer in importers::load(&cwd, &self.payload.filter).await? {
+            let mut roots = importer.edges(&graph);
+            if let Some(root) = &self.payload.root {
+                roots.retain(|(edge, _)| &edge.name == root);
+            }
+            importers.push((importer.name, importer.package_json.version, roots));
+        }
+
+        if let Some(root) = &self.payload.root
+            && importers.iter().all(|(_, _, roots)| roots.is_empty())
+        {
+            anyhow::bail!("{} is not a direct dependency", root);
+        }
+
+        let (mut export, entries) = Self::export(&graph, &importers);
+        for (node, entry) in export.nodes.iter_mut().filter(|n| !n.importer).zip(entries) {
+            if let Some(dir) = cache_dir(entry) {
+                node.size = dir_size(&dir).await;
+            }
+        }
+
+        let output = match self.payload.format {
+            GraphFormat::Dot => export.to_dot(),
+            GraphFormat::Mermaid => export.to_mermaid(),
+            GraphFormat::Json => export.to_json()?,
+        };
+        println!("{}", output);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lockfile::Lockfile;
+
+    fn lockfile() -> Lockfile {
+        let mut lockfile = Lockfile::new();
+        let entry = |name: &str, version: &str, deps: &[(&str, &str)]| {
+            let entry = PackageEntry::new(name.to_string(), version.to_string(), String::new());
+            if deps.is_empty() {
+                entry
+            } else {
+                entry.with_dependencies(
+                    deps.iter()
+                        .map(|(n, r)| (n.to_string(), r.to_string()))
+                        .collect(),
+                )
+            }
+        };
+        lockfile.add_package(entry("react", "18.3.1", &[("loose-envify", "^1.1.0")]));
+        lockfile.add_package(entry("loose-envify", "1.4.0", &[]));
+        lockfile.add_package(entry("vitest", "1.6.0", &[("loose-envify", "^1.0.0")]));
+        lockfile.add_package(entry("tinyspy", "2.2.1", &[]));
+        lockfile
+    }
+
+    fn export(lockfile: &Lockfile) -> Export {
+        let graph = DependencyGraph::new(lockfile);
+        let prod = BTreeMap::from([("react".to_string(), "^18.0.0".to_string())]);
+        let dev = BTreeMap::from([("vitest".to_string(), "^1.0.0".to_string())]);
+
+        let roots: Vec<(Edge, bool)> = graph
+            .edges(&prod)
+            .into_iter()
+            .map(|e| (e, false))
+            .chain(graph.edges(&dev).into_iter().map(|e| (e, true)))
+            .collect();
+
+        GraphActor::export(&graph, &[("app".to_string(), None, roots)]).0
+    }
+
+    #[test]
+    fn test_export_marks_dev_only_packages() {
+        let lockfile = lockfile();
+        let export = export(&lockfile);
+
+        let flags: Vec<(&str, bool)> = export
+            .nodes
+            .iter()
+            .map(|n| (n.id.as_str(), n.dev))
+            .collect();
+        assert_eq!(
+            flags,
+            vec![
+                ("app", false),
+                ("loose-envify@1.4.0", false),
+                ("react@18.3.1", false),
+                ("vitest@1.6.0", true),
+            ]
+        );
+        assert_eq!(export.links.len(), 4);
+        assert!(export.links.contains(&Link {
+            from: "vitest@1.6.0".to_string(),
+            to: "loose-envify@1.4.0".to_string(),
+            range: "^1.0.0".to_string(),
+        }));
+    }
+
+    #[test]
+    fn test_formats() {
+        let lockfile = lockfile();
+        let export = export(&lockfile);
+
+        let dot = export.to_dot();
+        assert!(dot.starts_with("digraph dependencies {"));
+        assert!(dot.contains("\"app\" [label=\"app\", shape=box];"));
+        assert!(dot.contains("\"vitest@1.6.0\" [label=\"vitest\\n1.6.0\", style=dashed];"));
+        assert!(dot.contains("\"app\" -> \"react@18.3.1\" [label=\"^18.0.0\"];"));
+
+        let mermaid = export.to_mermaid();
+        assert!(mermaid.contains("  n2(\"react<br/>18.3.1\")"));
+        assert!(mermaid.contains("  n0 -->|\"^18.0.0\"| n2"));
+        assert!(mermaid.contains("  class n3 dev"));
+
+        let json: serde_json::Value = serde_json::from_str(&export.to_json().unwrap()).unwrap();
+        assert_eq!(json["nodes"][3]["dev"], true);
+        assert_eq!(json["edges"][0]["range"], "^18.0.0");
+    }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(human_size(512), "512 B");
+        assert_eq!(human_size(12_300), "12.3 kB");
+        assert_eq!(human_size(4_500_000), "4.5 MB");
+    }
+}
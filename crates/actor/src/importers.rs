@@ -0,0 +1,67 @@
+use std::path::{Path, PathBuf};
+
+use contract::Result;
+use lockfile::{DependencyGraph, Edge};
+use package::{PackageJson, Workspace, WorkspaceFilter};
+
+/// A package.json whose dependencies are roots of the lockfile's graph: the
+/// project, or the workspace root and its packages
+#[derive(Debug)]
+pub(crate) struct Importer {
+    pub name: String,
+    pub dir: PathBuf,
+    pub package_json: PackageJson,
+}
+
+impl Importer {
+    pub async fn load(dir: &Path) -> Result<Self> {
+        let package_json = PackageJson::from_path(&dir.join("package.json")).await?;
+        let name = package_json.name.clone().unwrap_or_else(|| {
+            dir.file_name()
+                .map_or_else(|| ".".to_string(), |n| n.to_string_lossy().to_string())
+        });
+
+        Ok(Self {
+            name,
+            dir: dir.to_path_buf(),
+            package_json,
+        })
+    }
+
+    /// Edges from the importer's dependencies, paired with whether they're dev ones
+    pub fn edges<'a>(&self, graph: &DependencyGraph<'a>) -> Vec<(Edge<'a>, bool)> {
+        [
+            (&self.package_json.dependencies, false),
+            (&self.package_json.dev_dependencies, true),
+        ]
+        .into_iter()
+        .flat_map(|(deps, is_dev)| {
+            graph
+                .edges(deps.iter().flatten())
+                .into_iter()
+                .map(move |edge| (edge, is_dev))
+        })
+        .collect()
+    }
+}
+
+/// The importers selected by `filter`; without one, every importer of the
+/// workspace containing `cwd` (or just the project in `cwd`)
+pub(crate) async fn load(cwd: &Path, filter: &WorkspaceFilter) -> Result<Vec<Importer>> {
+    let dirs = if !filter.is_empty() {
+        filter.target_dirs(cwd).await?
+    } else if let Some(workspace) = Workspace::find(cwd).await? {
+        std::iter::once(workspace.root.clone())
+            .chain(workspace.packages.iter().map(|member| member.dir.clone()))
+            .collect()
+    } else {
+        vec![cwd.to_path_buf()]
+    };
+
+    let mut importers = Vec::new();
+    for dir in dirs {
+        importers.push(Importer::load(&dir).await?);
+    }
+
+    Ok(importers)
+}
@@ -1,37 +1,289 @@
-use contract::{Actor, Pipeline, Result};
-use package::{InstallPackage, PackageJson};
-use pipeline::{InstallPipe, LinkerPipe};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 
-pub struct InstallActor;
+use config::Config;
+use contract::{Actor, LOCKFILE_NAME, Pipeline, Result};
+use lockfile::Lockfile;
+use package::{InstallPackage, Overrides, PackageJson, Workspace, WorkspaceFilter};
+use patch::PackagePatch;
+use pipeline::{InstallPipe, LinkerPipe, LockfilePipe};
+use resolver::ResolvedArtifact;
 
-impl Actor<()> for InstallActor {
-    fn with(_: ()) -> Self {
-        Self
-    }
+#[derive(Debug, Default)]
+pub struct InstallActorPayload {
+    pub filter: WorkspaceFilter,
+    /// Names of the packages to re-resolve, the others keep their version in
+    /// craft.lock while their range allows it. Everything is re-resolved when `None`.
+    pub resolve_only: Option<Vec<String>>,
+}
 
-    async fn run(&self) -> Result<()> {
-        let package_json = PackageJson::from_file().await?;
+pub struct InstallActor {
+    payload: InstallActorPayload,
+}
 
-        // Collect all dependencies
+impl InstallActor {
+    /// Collect a package.json's dependencies and devDependencies.
+    ///
+    /// Inside a workspace, dependencies on workspace packages are linked to
+    /// their directory instead of being fetched from the registry.
+    fn collect_packages(
+        package_json: &PackageJson,
+        dir: &Path,
+        workspace: Option<&Workspace>,
+    ) -> Result<Vec<InstallPackage>> {
         let mut pkgs = Vec::new();
 
-        if let Some(deps) = package_json.dependencies {
-            for (name, version) in deps {
-                pkgs.push(InstallPackage::new(name, Some(version), false));
+        for (deps, is_dev) in [
+            (&package_json.dependencies, false),
+            (&package_json.dev_dependencies, true),
+        ] {
+            for (name, version) in deps.iter().flatten() {
+                let member = workspace.and_then(|w| w.resolve_dependency(name, version));
+
+                let version = match member {
+                    Some(member) => format!("link:{}", member.dir.display()),
+                    None if version.starts_with("workspace:") => {
+                        anyhow::bail!("{} uses {} but is not a workspace package", name, version)
+                    }
+                    None if InstallPackage::is_local_spec(version) => {
+                        Self::local_spec(version, dir)
+                    }
+                    None => version.clone(),
+                };
+
+                pkgs.push(InstallPackage::new(name.clone(), Some(version), is_dev));
+            }
+        }
+
+        Ok(pkgs)
+    }
+
+    /// `spec` with a relative path made absolute from `dir`, the directory of
+    /// the package.json declaring it, which isn't where craft runs in a workspace
+    fn local_spec(spec: &str, dir: &Path) -> String {
+        let (protocol, path) = match spec.strip_prefix("link:") {
+            Some(path) => ("link:", path),
+            None => ("file:", spec.strip_prefix("file:").unwrap_or(spec)),
+        };
+        let path = Path::new(path);
+        if path.is_absolute() || path.starts_with("~") {
+            return spec.to_string();
+        }
+        format!("{}{}", protocol, dir.join(path).display())
+    }
+
+    /// Overrides of the root package.json in `root`, and whether they changed
+    /// since its `craft.lock` was written
+    pub(crate) async fn overrides(
+        package_json: &PackageJson,
+        root: &Path,
+    ) -> Result<(Overrides, bool)> {
+        let overrides = Overrides::from_package_json(package_json)?;
+
+        let lockfile_path = root.join(LOCKFILE_NAME);
+        let changed = lockfile_path.exists()
+            && Lockfile::load(&lockfile_path).await?.declared_overrides != overrides.to_map();
+
+        Ok((overrides, changed))
+    }
+
+    /// The install of `pkgs` for the project at `root`, from the `registry` and
+    /// through the `offline-mirror` of its `.craftrc` when there's one, only
+    /// from the mirror with `offline=true`
+    pub(crate) async fn install_pipe(
+        root: &Path,
+        pkgs: Vec<InstallPackage>,
+    ) -> Result<InstallPipe> {
+        let config = Config::load(root).await?;
+        let mut install_pipe = InstallPipe::new(pkgs).with_registry(config.registry().to_string());
+        match config.offline_mirror() {
+            Some(mirror) => {
+                debug::info!("Using the offline mirror at {:?}", mirror);
+                install_pipe = install_pipe
+                    .with_offline_mirror(mirror)
+                    .with_offline(config.offline());
+            }
+            None if config.offline() => {
+                anyhow::bail!("offline=true needs an offline-mirror to install from")
             }
+            None => {}
+        }
+        Ok(install_pipe)
+    }
+
+    /// Versions of the packages in the `craft.lock` of `root` by name, but those
+    /// in [InstallActorPayload::resolve_only], to keep them
+    async fn locked_versions(&self, root: &Path) -> Result<HashMap<String, Vec<String>>> {
+        let mut locked: HashMap<String, Vec<String>> = HashMap::new();
+        let lockfile_path = root.join(LOCKFILE_NAME);
+        let Some(resolve_only) = &self.payload.resolve_only else {
+            return Ok(locked);
+        };
+        if !lockfile_path.exists() {
+            return Ok(locked);
         }
 
-        if let Some(dev_deps) = package_json.dev_dependencies {
-            for (name, version) in dev_deps {
-                pkgs.push(InstallPackage::new(name, Some(version), true));
+        for entry in Lockfile::load(&lockfile_path).await?.packages.into_values() {
+            if !resolve_only.contains(&entry.name) {
+                locked.entry(entry.name).or_default().push(entry.version);
             }
         }
+        Ok(locked)
+    }
+
+    /// Patch hashes by `name@version`, as recorded in the lockfile
+    pub(crate) fn patch_hashes(
+        patches: &BTreeMap<String, PackagePatch>,
+    ) -> BTreeMap<String, String> {
+        patches
+            .iter()
+            .map(|(key, patch)| (key.clone(), patch.hash.clone()))
+            .collect()
+    }
+
+    /// Warn about `patchedDependencies` entries for versions that aren't installed,
+    /// e.g. after an update, since their patch is silently left out
+    fn warn_unused_patches(
+        patches: &BTreeMap<String, PackagePatch>,
+        artifacts: &[ResolvedArtifact],
+    ) {
+        for patch in patches.values() {
+            if !artifacts
+                .iter()
+                .any(|a| a.name == patch.name && a.version == patch.version)
+            {
+                eprintln!(
+                    "Warning: {} isn't installed, its patch wasn't applied",
+                    patch.key()
+                );
+            }
+        }
+    }
+
+    /// Install workspace packages in a single resolution pass, sharing one
+    /// lockfile at the workspace root. With `selected`, only those packages
+    /// (and the root, if selected) are installed.
+    async fn install_workspace(
+        &self,
+        workspace: &Workspace,
+        selected: Option<Vec<PathBuf>>,
+    ) -> Result<()> {
+        let (overrides, overrides_changed) =
+            Self::overrides(&workspace.package_json, &workspace.root).await?;
+        let patches = PackagePatch::load_all(&workspace.package_json, &workspace.root).await?;
+        // Locked entries of unselected packages may depend on the old overrides
+        let selected = if overrides_changed && selected.is_some() {
+            println!("Overrides changed, installing the whole workspace");
+            None
+        } else {
+            selected
+        };
+        let is_selected = |dir: &PathBuf| selected.as_ref().is_none_or(|dirs| dirs.contains(dir));
+
+        let root_pkgs = if is_selected(&workspace.root) {
+            Self::collect_packages(&workspace.package_json, &workspace.root, Some(workspace))?
+        } else {
+            Vec::new()
+        };
+        let mut pkgs = root_pkgs.clone();
+        let mut members = Vec::new();
+
+        for member in workspace.packages.iter().filter(|m| is_selected(&m.dir)) {
+            let member_pkgs =
+                Self::collect_packages(&member.package_json, &member.dir, Some(workspace))?;
+            pkgs.extend(member_pkgs.clone());
+            members.push((member.dir.clone(), member_pkgs));
+        }
+
+        debug::trace!(
+            "Installing {} workspace packages from {:?}: {pkgs:?}",
+            workspace.packages.len(),
+            workspace.root
+        );
+
+        let declared_overrides = overrides.to_map();
+        let install_pipe = Self::install_pipe(&workspace.root, pkgs)
+            .await?
+            .with_locked(self.locked_versions(&workspace.root).await?)
+            .with_overrides(overrides)
+            .with_patches(patches.clone());
+        let artifacts = install_pipe.run().await?;
+        if selected.is_none() {
+            Self::warn_unused_patches(&patches, &artifacts);
+        }
+
+        let member_dirs: Vec<PathBuf> = members.iter().map(|(dir, _)| dir.clone()).collect();
+        let mut linker =
+            LinkerPipe::new(artifacts.clone(), root_pkgs).with_project_dir(workspace.root.clone());
+        for (dir, member_pkgs) in members {
+            linker = linker.with_workspace_package(dir, member_pkgs);
+        }
+        linker.run().await?;
+        crate::lifecycle::run_install_scripts(artifacts.clone(), &workspace.root, &member_dirs)
+            .await?;
+
+        let mut lockfile_pipe = LockfilePipe::new(artifacts)
+            .with_project_dir(workspace.root.clone())
+            .with_overrides(install_pipe.applied_overrides().await)
+            .with_declared_overrides(declared_overrides)
+            .with_patches(Self::patch_hashes(&patches));
+
+        // A partial install keeps the entries of unselected packages
+        let lockfile_path = workspace.root.join(LOCKFILE_NAME);
+        if selected.is_some() && lockfile_path.exists() {
+            lockfile_pipe = lockfile_pipe.with_existing(Lockfile::load(&lockfile_path).await?);
+        }
+        lockfile_pipe.run().await?;
+
+        Ok(())
+    }
+}
+
+impl Actor<InstallActorPayload> for InstallActor {
+    fn with(payload: InstallActorPayload) -> Self {
+        Self { payload }
+    }
+
+    async fn run(&self) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+
+        if let Some(workspace) = Workspace::find(&cwd).await? {
+            let selected = if self.payload.filter.is_empty() {
+                None
+            } else {
+                Some(self.payload.filter.select(&workspace))
+            };
+            return self.install_workspace(&workspace, selected).await;
+        } else if !self.payload.filter.is_empty() {
+            anyhow::bail!("--filter and -w can only be used in a workspace");
+        }
+
+        // Collect all dependencies
+        let package_json = PackageJson::from_file().await?;
+        let pkgs = Self::collect_packages(&package_json, &cwd, None)?;
 
         debug::trace!("Installing packages from package.json: {pkgs:?}");
 
-        // Run install and link pipes
-        let artifacts = InstallPipe::new(pkgs.clone()).run().await?;
-        LinkerPipe::new(artifacts, pkgs).run().await?;
+        let overrides = Overrides::from_package_json(&package_json)?;
+        let patches = PackagePatch::load_all(&package_json, &cwd).await?;
+
+        // Run install, link, lifecycle and lockfile pipes
+        let declared_overrides = overrides.to_map();
+        let install_pipe = Self::install_pipe(&cwd, pkgs.clone())
+            .await?
+            .with_locked(self.locked_versions(&cwd).await?)
+            .with_overrides(overrides)
+            .with_patches(patches.clone());
+        let artifacts = install_pipe.run().await?;
+        Self::warn_unused_patches(&patches, &artifacts);
+        LinkerPipe::new(artifacts.clone(), pkgs).run().await?;
+        crate::lifecycle::run_install_scripts(artifacts.clone(), &cwd, &[]).await?;
+        LockfilePipe::new(artifacts)
+            .with_overrides(install_pipe.applied_overrides().await)
+            .with_declared_overrides(declared_overrides)
+            .with_patches(Self::patch_hashes(&patches))
+            .run()
+            .await?;
 
         Ok(())
     }
@@ -47,7 +299,7 @@ mod tests {
         let temp_dir = tempfile::tempdir().unwrap();
         env::set_current_dir(&temp_dir).unwrap();
 
-        let actor = InstallActor::with(());
+        let actor = InstallActor::with(InstallActorPayload::default());
         let result = actor.run().await;
 
         assert!(result.is_err());
@@ -56,8 +308,130 @@ mod tests {
 
     #[test]
     fn test_install_actor_creation() {
-        let actor = InstallActor::with(());
         // Just verify it can be created
-        drop(actor);
+        let _actor = InstallActor::with(InstallActorPayload::default());
+    }
+
+    #[tokio::test]
+    async fn test_collect_packages_links_workspace_members() {
+        let root = tempfile::tempdir().unwrap();
+        let core = root.path().join("packages/core");
+        std::fs::create_dir_all(&core).unwrap();
+        std::fs::write(
+            root.path().join("package.json"),
+            r#"{"workspaces": ["packages/*"]}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            core.join("package.json"),
+            r#"{"name": "@acme/core", "version": "1.0.0"}"#,
+        )
+        .unwrap();
+
+        let workspace = Workspace::load(root.path()).await.unwrap().unwrap();
+        let package_json: PackageJson = serde_json::from_str(
+            r#"{"dependencies": {
+                "@acme/core": "workspace:^",
+                "react": "^19.0.0",
+                "shared": "file:../shared",
+                "tools": "link:/opt/tools"
+            }}"#,
+        )
+        .unwrap();
+
+        let app = root.path().join("packages/app");
+        let mut pkgs =
+            InstallActor::collect_packages(&package_json, &app, Some(&workspace)).unwrap();
+        pkgs.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(pkgs[0].name, "@acme/core");
+        assert_eq!(pkgs[0].spec(), format!("link:{}", core.display()));
+        assert_eq!(pkgs[1].spec(), "react");
+        assert_eq!(
+            pkgs[2].spec(),
+            format!("file:{}", app.join("../shared").display())
+        );
+        assert_eq!(pkgs[3].spec(), "link:/opt/tools");
+
+        let unknown: PackageJson =
+            serde_json::from_str(r#"{"dependencies": {"missing": "workspace:*"}}"#).unwrap();
+        assert!(InstallActor::collect_packages(&unknown, &app, Some(&workspace)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_overrides_changed() {
+        let root = tempfile::tempdir().unwrap();
+        let package_json: PackageJson =
+            serde_json::from_str(r#"{"overrides": {"dep": "2.0.0", "unused": "1.0.0"}}"#).unwrap();
+        let changed = || async {
+            InstallActor::overrides(&package_json, root.path())
+                .await
+                .unwrap()
+                .1
+        };
+        assert!(!changed().await);
+
+        // Overrides that applied to nothing still count
+        let mut lockfile = Lockfile::new();
+        lockfile
+            .overrides
+            .insert("dep".to_string(), "2.0.0".to_string());
+        lockfile
+            .save(&root.path().join(LOCKFILE_NAME))
+            .await
+            .unwrap();
+        assert!(changed().await);
+
+        lockfile.declared_overrides = Overrides::from_package_json(&package_json)
+            .unwrap()
+            .to_map();
+        lockfile
+            .save(&root.path().join(LOCKFILE_NAME))
+            .await
+            .unwrap();
+        assert!(!changed().await);
+    }
+
+    #[tokio::test]
+    async fn test_locked_versions() {
+        let root = tempfile::tempdir().unwrap();
+        let mut lockfile = Lockfile::new();
+        for (name, version) in [("react", "18.2.0"), ("dep", "1.0.0"), ("dep", "2.0.0")] {
+            lockfile.add_package(lockfile::PackageEntry::new(
+                name.to_string(),
+                version.to_string(),
+                String::new(),
+            ));
+        }
+        lockfile
+            .save(&root.path().join(LOCKFILE_NAME))
+            .await
+            .unwrap();
+
+        let locked = |resolve_only: Option<Vec<String>>| {
+            InstallActor::with(InstallActorPayload {
+                resolve_only,
+                ..Default::default()
+            })
+        };
+        assert!(
+            locked(None)
+                .locked_versions(root.path())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let versions = locked(Some(vec!["react".to_string()]))
+            .locked_versions(root.path())
+            .await
+            .unwrap();
+        assert_eq!(
+            versions,
+            HashMap::from([(
+                "dep".to_string(),
+                vec!["1.0.0".to_string(), "2.0.0".to_string()]
+            )])
+        );
     }
 }
@@ -1,11 +1,39 @@
 mod add_package_actor;
+mod audit_actor;
+mod audit_fix;
 mod clean_cache_actor;
+mod dependency_versions;
+mod dlx_actor;
+mod graph_actor;
+mod importers;
 mod install_actor;
+mod lifecycle;
+mod list_actor;
+mod outdated_actor;
+mod pack_actor;
+mod patch_actor;
+mod publish_actor;
 mod remove_package_actor;
 mod run_script_actor;
+mod serve_actor;
+mod update_actor;
+mod why_actor;
+mod workspace_run;
 
 pub use add_package_actor::{AddActorPayload, AddPackageActor};
+pub use audit_actor::{AuditActor, AuditActorPayload, AuditLevel};
 pub use clean_cache_actor::{CleanCacheActor, CleanCacheActorPayload};
-pub use install_actor::InstallActor;
+pub use dlx_actor::{DlxActor, DlxActorPayload};
+pub use graph_actor::{GraphActor, GraphActorPayload, GraphFormat};
+pub use install_actor::{InstallActor, InstallActorPayload};
+pub use list_actor::{ListActor, ListActorPayload};
+pub use outdated_actor::{OutdatedActor, OutdatedActorPayload};
+pub use pack_actor::{PackActor, PackActorPayload};
+pub use patch_actor::{PatchActor, PatchActorPayload, PatchCommitActor, PatchCommitActorPayload};
+pub use publish_actor::{Access, PublishActor, PublishActorPayload};
 pub use remove_package_actor::{RemoveActorPayload, RemovePackageActor};
 pub use run_script_actor::{RunScriptActor, RunScriptActorPayload};
+pub use serve_actor::{ServeActor, ServeActorPayload};
+pub use update_actor::{UpdateActor, UpdateActorPayload};
+pub use why_actor::{WhyActor, WhyActorPayload};
+pub use workspace_run::RecursiveOptions;
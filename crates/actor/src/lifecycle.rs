@@ -0,0 +1,171 @@
+use std::path::{Path, PathBuf};
+
+use config::Config;
+use contract::{Pipeline, Result};
+use package::PackageJson;
+use pipeline::LifecyclePipe;
+use process::{LifecycleEnv, Process, shell};
+use resolver::ResolvedArtifact;
+
+/// One command of a `craft run` invocation
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ScriptStep {
+    /// Lifecycle event, the script name (`prebuild`, `build`, `postbuild`)
+    pub event: String,
+    pub command: String,
+}
+
+/// What `npm run <script>` runs: `pre<script>`, the script with `args`, then
+/// `post<script>`. `None` if the package has no such script.
+pub(crate) fn script_steps(
+    package_json: &PackageJson,
+    script: &str,
+    args: &[String],
+) -> Option<Vec<ScriptStep>> {
+    let scripts = package_json.scripts.as_ref()?;
+    let main = scripts.get(script)?;
+
+    let mut steps = Vec::new();
+    let pre = format!("pre{}", script);
+    if let Some(command) = scripts.get(&pre) {
+        steps.push(ScriptStep {
+            event: pre,
+            command: command.clone(),
+        });
+    }
+
+    // Arguments only go to the script itself, not its hooks
+    let mut command = main.clone();
+    if !args.is_empty() {
+        command.push(' ');
+        command.push_str(&shell::join(args));
+    }
+    steps.push(ScriptStep {
+        event: script.to_string(),
+        command,
+    });
+
+    let post = format!("post{}", script);
+    if let Some(command) = scripts.get(&post) {
+        steps.push(ScriptStep {
+            event: post,
+            command: command.clone(),
+        });
+    }
+
+    Some(steps)
+}
+
+/// A process running `step` in the package at `dir` the way npm would: with the
+/// `npm_*` environment, `registry` as `npm_config_registry` and every
+/// `node_modules/.bin` from `dir` up on `PATH`
+pub(crate) fn script_process(
+    dir: &Path,
+    package_json: &PackageJson,
+    step: &ScriptStep,
+    registry: &str,
+) -> Process {
+    let init_cwd = std::env::current_dir().unwrap_or_else(|_| dir.to_path_buf());
+
+    let mut env = LifecycleEnv::new(&step.event, &step.command)
+        .with_package(
+            package_json.name.as_deref(),
+            package_json.version.as_deref(),
+        )
+        .with_package_json(dir.join("package.json"))
+        .with_init_cwd(init_cwd)
+        .with_command("run-script")
+        .with_config("registry", registry);
+
+    for ancestor in dir.ancestors() {
+        let bin_dir = ancestor.join("node_modules").join(".bin");
+        if bin_dir.is_dir() {
+            env = env.with_bin_dir(bin_dir);
+        }
+    }
+
+    env.apply(Process::script(&step.command).current_dir(&dir.to_string_lossy()))
+}
+
+/// Run the package's own `event` script in `dir` (e.g. `prepack`), if it has one
+pub(crate) async fn run_hook(dir: &Path, package_json: &PackageJson, event: &str) -> Result<()> {
+    let Some(command) = package_json.scripts.as_ref().and_then(|s| s.get(event)) else {
+        return Ok(());
+    };
+
+    let step = ScriptStep {
+        event: event.to_string(),
+        command: command.clone(),
+    };
+    let config = Config::load(dir).await?;
+    debug::info!("Running {}: {}", step.event, step.command);
+    script_process(dir, package_json, &step, config.registry())
+        .run()
+        .await
+}
+
+/// Run install scripts of the trusted dependencies and report the ones skipped.
+///
+/// The allowlist is the `trustedDependencies` of the package.json in `project_dir`,
+/// the workspace root for workspaces, which also keeps the builds. The links of
+/// `workspace_dirs` are pointed to them as well.
+pub(crate) async fn run_install_scripts(
+    artifacts: Vec<ResolvedArtifact>,
+    project_dir: &Path,
+    workspace_dirs: &[PathBuf],
+) -> Result<()> {
+    let package_json = PackageJson::from_path(&project_dir.join("package.json")).await?;
+    let trusted = package_json.trusted_dependencies.unwrap_or_default();
+    let config = Config::load(project_dir).await?;
+
+    let mut lifecycle = LifecyclePipe::new(artifacts, trusted)
+        .with_project_dir(project_dir.to_path_buf())
+        .with_registry(config.registry().to_string());
+    for dir in workspace_dirs {
+        lifecycle = lifecycle.with_workspace_package(dir.clone());
+    }
+    let skipped = lifecycle.run().await?;
+
+    if !skipped.is_empty() {
+        println!(
+            "Skipped build scripts of {} package(s): {}",
+            skipped.len(),
+            skipped.join(", ")
+        );
+        println!(
+            "Add them to \"trustedDependencies\" in package.json to run their install scripts."
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_steps_with_hooks() {
+        let package_json: PackageJson = serde_json::from_str(
+            r#"{"scripts": {"prebuild": "rimraf dist", "build": "tsc", "postbuild": "cp README.md dist"}}"#,
+        )
+        .unwrap();
+
+        let args = vec!["--watch".to_string(), "src/**/*.ts".to_string()];
+        let steps = script_steps(&package_json, "build", &args).unwrap();
+        let steps: Vec<(&str, &str)> = steps
+            .iter()
+            .map(|s| (s.event.as_str(), s.command.as_str()))
+            .collect();
+
+        assert_eq!(
+            steps,
+            vec![
+                ("prebuild", "rimraf dist"),
+                ("build", "tsc --watch 'src/**/*.ts'"),
+                ("postbuild", "cp README.md dist"),
+            ]
+        );
+        assert!(script_steps(&package_json, "test", &[]).is_none());
+    }
+}
@@ -0,0 +1,410 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use contract::{Actor, Result, SilentExit};
+use lockfile::Lockfile;
+use node_semver::{Range, Version};
+use package::{PackageJson, WorkspaceFilter};
+
+use crate::dependency_versions;
+use crate::importers::{self, Importer};
+
+#[derive(Debug)]
+pub struct ListActorPayload {
+    /// How many levels of transitive dependencies to show, 0 for direct ones only
+    pub depth: usize,
+    /// Only dependencies
+    pub prod: bool,
+    /// Only devDependencies
+    pub dev: bool,
+    pub json: bool,
+    /// Print the installed packages' paths, one per line
+    pub parseable: bool,
+    pub filter: WorkspaceFilter,
+}
+
+impl std::fmt::Display for ListActorPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "depth {}", self.depth)
+    }
+}
+
+pub struct ListActor {
+    payload: ListActorPayload,
+}
+
+/// A dependency as linked in node_modules
+#[derive(Debug, Default, PartialEq)]
+struct Node {
+    name: String,
+    range: String,
+    /// Installed version, `None` when the dependency isn't linked
+    version: Option<String>,
+    path: Option<PathBuf>,
+    is_dev: bool,
+    /// The installed version doesn't satisfy the range
+    invalid: bool,
+    /// Already listed with its dependencies elsewhere in the tree
+    deduped: bool,
+    dependencies: Vec<Node>,
+}
+
+impl Node {
+    fn has_problems(&self) -> bool {
+        self.version.is_none() || self.invalid || self.dependencies.iter().any(Node::has_problems)
+    }
+
+    fn label(&self) -> String {
+        let Some(version) = &self.version else {
+            return format!("{}@{} missing", self.name, self.range);
+        };
+
+        let mut label = format!("{}@{}", self.name, version);
+        if self.is_dev {
+            label.push_str(" (dev)");
+        }
+        if self.invalid {
+            label.push_str(&format!(" invalid: wanted {}", self.range));
+        }
+        if self.deduped {
+            label.push_str(" deduped");
+        }
+        label
+    }
+
+    fn json(&self) -> serde_json::Value {
+        let mut json = serde_json::json!({ "range": self.range });
+        match &self.version {
+            Some(version) => json["version"] = version.clone().into(),
+            None => json["missing"] = true.into(),
+        }
+        if let Some(path) = &self.path {
+            json["path"] = path.to_string_lossy().into();
+        }
+        if self.invalid {
+            json["invalid"] = true.into();
+        }
+        if self.deduped {
+            json["deduped"] = true.into();
+        }
+        if !self.dependencies.is_empty() {
+            json["dependencies"] = dependencies_json(&self.dependencies);
+        }
+        json
+    }
+}
+
+fn dependencies_json<'a>(nodes: impl IntoIterator<Item = &'a Node>) -> serde_json::Value {
+    nodes
+        .into_iter()
+        .map(|node| (node.name.clone(), node.json()))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// Whether `version` satisfies `range`. Specs that aren't semver ranges (git, file,
+/// `workspace:`) are satisfied by any version, `npm:` aliases by their own range.
+fn satisfies(range: &str, version: &str) -> bool {
+    let range = match range.strip_prefix("npm:") {
+        Some(alias) => alias
+            .get(1..)
+            .and_then(|rest| rest.rfind('@'))
+            .map_or("*", |at| &alias[at + 2..]),
+        None => range,
+    };
+
+    match (Range::parse(range), Version::parse(version)) {
+        (Ok(range), Ok(version)) => range.satisfies(&version),
+        _ => true,
+    }
+}
+
+/// Builds the tree of an importer from its node_modules links
+struct TreeBuilder<'a> {
+    lockfile: Option<&'a Lockfile>,
+    depth: usize,
+    seen: HashSet<String>,
+}
+
+impl TreeBuilder<'_> {
+    async fn node(
+        &mut self,
+        node_modules: &Path,
+        name: &str,
+        range: &str,
+        is_dev: bool,
+        level: usize,
+    ) -> Node {
+        let path = node_modules.join(name);
+        let mut node = Node {
+            name: name.to_string(),
+            range: range.to_string(),
+            is_dev,
+            ..Default::default()
+        };
+
+        let Ok(package_json) = PackageJson::from_path(&path.join("package.json")).await else {
+            return node;
+        };
+        let version = package_json.version.clone().unwrap_or_default();
+
+        node.invalid = !satisfies(range, &version);
+        node.version = Some(version.clone());
+        node.path = Some(path.clone());
+
+        if level >= self.depth {
+            return node;
+        }
+        if !self.seen.insert(format!("{}@{}", name, version)) {
+            node.deduped = true;
+            return node;
+        }
+
+        // Ranges as locked, or as declared by the installed package
+        let dependencies: BTreeMap<String, String> = self
+            .lockfile
+            .and_then(|lockfile| lockfile.get_package(&package_json.name.clone()?, &version))
+            .and_then(|entry| entry.dependencies.clone())
+            .unwrap_or_else(|| {
+                package_json
+                    .dependencies
+                    .clone()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect()
+            });
+
+        let child_modules = path.join("node_modules");
+        for (dep, dep_range) in &dependencies {
+            let child = Box::pin(self.node(&child_modules, dep, dep_range, false, level + 1)).await;
+            node.dependencies.push(child);
+        }
+
+        node
+    }
+}
+
+impl ListActor {
+    async fn tree(&self, importer: &Importer, lockfile: Option<&Lockfile>) -> Vec<Node> {
+        let mut builder = TreeBuilder {
+            lockfile,
+            depth: self.payload.depth,
+            seen: HashSet::new(),
+        };
+
+        let mut groups = Vec::new();
+        if !self.payload.dev {
+            groups.push((&importer.package_json.dependencies, false));
+        }
+        if !self.payload.prod {
+            groups.push((&importer.package_json.dev_dependencies, true));
+        }
+
+        let node_modules = importer.dir.join("node_modules");
+        let mut nodes = Vec::new();
+        for (deps, is_dev) in groups {
+            let deps: BTreeMap<&String, &String> = deps.iter().flatten().collect();
+            for (name, range) in deps {
+                nodes.push(builder.node(&node_modules, name, range, is_dev, 0).await);
+            }
+        }
+
+        nodes
+    }
+
+    fn render(importer: &Importer, nodes: &[Node]) -> String {
+        fn render_nodes(nodes: &[Node], prefix: &str, lines: &mut Vec<String>) {
+            for (i, node) in nodes.iter().enumerate() {
+                let last = i == nodes.len() - 1;
+                let (branch, indent) = if last {
+                    ("└── ", "    ")
+                } else {
+                    ("├── ", "│   ")
+                };
+                lines.push(format!("{}{}{}", prefix, branch, node.label()));
+                render_nodes(&node.dependencies, &format!("{}{}", prefix, indent), lines);
+            }
+        }
+
+        let mut lines = vec![format!(
+            "{}@{} {}",
+            importer.name,
+            importer.package_json.version.as_deref().unwrap_or("0.0.0"),
+            importer.dir.display()
+        )];
+        render_nodes(nodes, "", &mut lines);
+        lines.join("\n")
+    }
+
+    /// Paths of the importer and every installed package, each once
+    fn parseable(importer: &Importer, nodes: &[Node]) -> Vec<String> {
+        fn collect(nodes: &[Node], paths: &mut Vec<String>) {
+            for node in nodes {
+                if let Some(path) = &node.path {
+                    let path = path.to_string_lossy().to_string();
+                    if !paths.contains(&path) {
+                        paths.push(path);
+                    }
+                }
+                collect(&node.dependencies, paths);
+            }
+        }
+
+        let mut paths = vec![importer.dir.to_string_lossy().to_string()];
+        collect(nodes, &mut paths);
+        paths
+    }
+
+    fn json(importer: &Importer, nodes: &[Node]) -> serde_json::Value {
+        let (dev, prod): (Vec<&Node>, Vec<&Node>) = nodes.iter().partition(|node| node.is_dev);
+
+        serde_json::json!({
+            "name": importer.name,
+            "version": importer.package_json.version,
+            "path": importer.dir.to_string_lossy(),
+            "dependencies": dependencies_json(prod),
+            "devDependencies": dependencies_json(dev),
+        })
+    }
+}
+
+impl Actor<ListActorPayload> for ListActor {
+    fn with(payload: ListActorPayload) -> Self {
+        Self { payload }
+    }
+
+    async fn run(&self) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        let lockfile = dependency_versions::load_lockfile(&cwd).await?;
+
+        let mut has_problems = false;
+        let mut json = Vec::new();
+
+        for importer in importers::load(&cwd, &self.payload.filter).await? {
+            let nodes = self.tree(&importer, lockfile.as_ref()).await;
+            has_problems |= nodes.iter().any(Node::has_problems);
+
+            if self.payload.json {
+                json.push(Self::json(&importer, &nodes));
+            } else if self.payload.parseable {
+                println!("{}", Self::parseable(&importer, &nodes).join("\n"));
+            } else {
+                println!("{}", Self::render(&importer, &nodes));
+            }
+        }
+
+        if self.payload.json {
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+
+        // Like npm ls, missing or invalid dependencies fail the command
+        if has_problems {
+            return Err(SilentExit { code: 1 }.into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(dir: &Path, name: &str, version: &str, deps: &str) -> PathBuf {
+        let path = dir.join("node_modules").join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(
+            path.join("package.json"),
+            format!(
+                r#"{{"name": "{}", "version": "{}", "dependencies": {}}}"#,
+                name, version, deps
+            ),
+        )
+        .unwrap();
+        path
+    }
+
+    async fn fixture() -> (tempfile::TempDir, Importer) {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(
+            root.path().join("package.json"),
+            r#"{
+                "name": "app",
+                "version": "1.0.0",
+                "dependencies": {"a": "^1.0.0", "b": "^1.0.0", "gone": "^1.0.0"},
+                "devDependencies": {"old": "^2.0.0"}
+            }"#,
+        )
+        .unwrap();
+
+        let a = link(root.path(), "a", "1.1.0", r#"{"c": "^1.0.0"}"#);
+        link(&a, "c", "1.0.0", "{}");
+        let b = link(root.path(), "b", "1.0.0", r#"{"a": "^1.0.0"}"#);
+        link(&b, "a", "1.1.0", r#"{"c": "^1.0.0"}"#);
+        link(root.path(), "old", "1.0.0", "{}");
+
+        let importer = Importer::load(root.path()).await.unwrap();
+        (root, importer)
+    }
+
+    fn actor(depth: usize, prod: bool) -> ListActor {
+        ListActor::with(ListActorPayload {
+            depth,
+            prod,
+            dev: false,
+            json: false,
+            parseable: false,
+            filter: WorkspaceFilter::default(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_tree_marks_problems_and_dedupes() {
+        let (_root, importer) = fixture().await;
+        let nodes = actor(usize::MAX, false).tree(&importer, None).await;
+        let rendered = ListActor::render(&importer, &nodes);
+        let lines: Vec<&str> = rendered.lines().skip(1).collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "├── a@1.1.0",
+                "│   └── c@1.0.0",
+                "├── b@1.0.0",
+                "│   └── a@1.1.0 deduped",
+                "├── gone@^1.0.0 missing",
+                "└── old@1.0.0 (dev) invalid: wanted ^2.0.0",
+            ]
+        );
+        assert!(nodes.iter().any(Node::has_problems));
+    }
+
+    #[tokio::test]
+    async fn test_tree_depth_and_prod() {
+        let (root, importer) = fixture().await;
+        let nodes = actor(0, true).tree(&importer, None).await;
+
+        let names: Vec<&str> = nodes.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b", "gone"]);
+        assert!(nodes.iter().all(|n| n.dependencies.is_empty()));
+
+        let paths = ListActor::parseable(&importer, &nodes);
+        assert_eq!(paths.len(), 3);
+        assert_eq!(paths[0], root.path().to_string_lossy());
+
+        let json = ListActor::json(&importer, &nodes);
+        assert_eq!(json["dependencies"]["a"]["version"], "1.1.0");
+        assert_eq!(json["dependencies"]["gone"]["missing"], true);
+        assert_eq!(json["devDependencies"], serde_json::json!({}));
+    }
+
+    #[test]
+    fn test_satisfies() {
+        assert!(satisfies("^1.0.0", "1.2.0"));
+        assert!(!satisfies("^2.0.0", "1.2.0"));
+        assert!(satisfies("npm:wrap-ansi@^7.0.0", "7.0.0"));
+        assert!(!satisfies("npm:wrap-ansi@^7.0.0", "8.1.0"));
+        assert!(satisfies("workspace:^", "0.1.0"));
+        assert!(satisfies("github:user/repo", "1.0.0"));
+    }
+}
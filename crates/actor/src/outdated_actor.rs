@@ -0,0 +1,223 @@
+use contract::{Actor, Result, SilentExit};
+use package::WorkspaceFilter;
+
+use crate::dependency_versions::{self, DependencyVersions, NamePatterns};
+use crate::importers::Importer;
+
+#[derive(Debug)]
+pub struct OutdatedActorPayload {
+    /// Names or globs of the dependencies to check, all when empty
+    pub packages: Vec<String>,
+    /// Print a JSON array instead of a table
+    pub json: bool,
+    pub filter: WorkspaceFilter,
+}
+
+impl std::fmt::Display for OutdatedActorPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.packages.join(" "))
+    }
+}
+
+pub struct OutdatedActor {
+    payload: OutdatedActorPayload,
+}
+
+/// An outdated dependency of one workspace package
+#[derive(Debug, PartialEq)]
+struct Row {
+    name: String,
+    current: Option<String>,
+    wanted: Option<String>,
+    latest: Option<String>,
+    dependency_type: &'static str,
+    workspace: String,
+}
+
+impl OutdatedActor {
+    /// Whether the locked version differs from the wanted or latest one
+    fn is_outdated(dep: &DependencyVersions) -> bool {
+        [&dep.wanted, &dep.latest]
+            .into_iter()
+            .flatten()
+            .any(|version| dep.current.as_ref() != Some(version))
+    }
+
+    fn table(rows: &[Row]) -> String {
+        let width = |header: &str, value: fn(&Row) -> &str| {
+            rows.iter()
+                .map(|r| value(r).len())
+                .chain([header.len()])
+                .max()
+                .unwrap_or(0)
+        };
+        let version = |v: &Option<String>| v.as_deref().unwrap_or("-").to_string();
+
+        let name = width("Package", |r| &r.name);
+        let kind = width("Type", |r| r.dependency_type);
+
+        let mut lines = vec![format!(
+            "{:name$}  {:10}  {:10}  {:10}  {:kind$}  {}",
+            "Package", "Current", "Wanted", "Latest", "Type", "Workspace"
+        )];
+        for row in rows {
+            lines.push(format!(
+                "{:name$}  {:10}  {:10}  {:10}  {:kind$}  {}",
+                row.name,
+                version(&row.current),
+                version(&row.wanted),
+                version(&row.latest),
+                row.dependency_type,
+                row.workspace
+            ));
+        }
+
+        lines.join("\n")
+    }
+
+    fn json(rows: &[Row]) -> Result<String> {
+        let rows: Vec<serde_json::Value> = rows
+            .iter()
+            .map(|row| {
+                serde_json::json!({
+                    "name": row.name,
+                    "current": row.current,
+                    "wanted": row.wanted,
+                    "latest": row.latest,
+                    "dependencyType": row.dependency_type,
+                    "workspace": row.workspace,
+                })
+            })
+            .collect();
+
+        Ok(serde_json::to_string_pretty(&rows)?)
+    }
+}
+
+impl Actor<OutdatedActorPayload> for OutdatedActor {
+    fn with(payload: OutdatedActorPayload) -> Self {
+        Self { payload }
+    }
+
+    async fn run(&self) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        let dirs = self.payload.filter.target_dirs(&cwd).await?;
+        let patterns = NamePatterns::parse(&self.payload.packages)?;
+        let lockfile = dependency_versions::load_lockfile(&cwd).await?;
+
+        let mut rows = Vec::new();
+        for dir in dirs {
+            let importer = Importer::load(&dir).await?;
+            let deps = dependency_versions::collect(
+                &dir,
+                &importer.package_json,
+                &patterns,
+                lockfile.as_ref(),
+            )
+            .await?;
+            for dep in deps.into_iter().filter(Self::is_outdated) {
+                rows.push(Row {
+                    dependency_type: if dep.is_dev {
+                        "devDependencies"
+                    } else {
+                        "dependencies"
+                    },
+                    name: dep.name,
+                    current: dep.current,
+                    wanted: dep.wanted,
+                    latest: dep.latest,
+                    workspace: importer.name.clone(),
+                });
+            }
+        }
+
+        if self.payload.json {
+            println!("{}", Self::json(&rows)?);
+        } else if !rows.is_empty() {
+            println!("{}", Self::table(&rows));
+        }
+
+        // Like npm, outdated dependencies make the command fail so CI can catch them
+        if rows.is_empty() {
+            Ok(())
+        } else {
+            Err(SilentExit { code: 1 }.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dep(current: Option<&str>, wanted: &str, latest: &str) -> DependencyVersions {
+        DependencyVersions {
+            name: "react".to_string(),
+            range: "^18.0.0".to_string(),
+            is_dev: false,
+            current: current.map(str::to_string),
+            wanted: Some(wanted.to_string()),
+            latest: Some(latest.to_string()),
+        }
+    }
+
+    fn row() -> Row {
+        Row {
+            name: "react".to_string(),
+            current: Some("18.2.0".to_string()),
+            wanted: Some("18.3.1".to_string()),
+            latest: Some("19.0.0".to_string()),
+            dependency_type: "dependencies",
+            workspace: "@acme/web".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_is_outdated() {
+        assert!(!OutdatedActor::is_outdated(&dep(
+            Some("18.3.1"),
+            "18.3.1",
+            "18.3.1"
+        )));
+        assert!(OutdatedActor::is_outdated(&dep(
+            Some("18.2.0"),
+            "18.3.1",
+            "18.3.1"
+        )));
+        assert!(OutdatedActor::is_outdated(&dep(
+            Some("18.3.1"),
+            "18.3.1",
+            "19.0.0"
+        )));
+        assert!(OutdatedActor::is_outdated(&dep(None, "18.3.1", "18.3.1")));
+    }
+
+    #[test]
+    fn test_table() {
+        let table = OutdatedActor::table(&[row()]);
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(
+            lines[0],
+            "Package  Current     Wanted      Latest      Type          Workspace"
+        );
+        assert_eq!(
+            lines[1],
+            "react    18.2.0      18.3.1      19.0.0      dependencies  @acme/web"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let mut missing = row();
+        missing.current = None;
+
+        let json: serde_json::Value =
+            serde_json::from_str(&OutdatedActor::json(&[missing]).unwrap()).unwrap();
+        assert_eq!(json[0]["name"], "react");
+        assert_eq!(json[0]["current"], serde_json::Value::Null);
+        assert_eq!(json[0]["latest"], "19.0.0");
+        assert_eq!(json[0]["dependencyType"], "dependencies");
+        assert_eq!(json[0]["workspace"], "@acme/web");
+    }
+}
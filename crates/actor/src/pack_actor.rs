@@ -0,0 +1,223 @@
+use std::path::Path;
+
+use contract::{Actor, Result};
+use package::{PackageJson, Workspace, WorkspaceFilter, bin_paths, packlist};
+use tarball::pack::{PackFile, pack};
+
+use crate::graph_actor::human_size;
+use crate::lifecycle;
+
+#[derive(Debug, Default)]
+pub struct PackActorPayload {
+    /// Print what would be packed without writing the tarball
+    pub dry_run: bool,
+    pub filter: WorkspaceFilter,
+}
+
+pub struct PackActor {
+    payload: PackActorPayload,
+}
+
+/// A package packed into a tarball
+#[derive(Debug)]
+pub(crate) struct Packed {
+    /// The manifest as packed, `workspace:` ranges replaced by versions
+    pub package_json: PackageJson,
+    pub name: String,
+    pub version: String,
+    /// Paths of the packed files and their sizes
+    pub files: Vec<(String, u64)>,
+    pub tarball: Vec<u8>,
+}
+
+impl Packed {
+    /// `<name>-<version>.tgz`, `@scope/name` becomes `scope-name`
+    pub fn filename(&self) -> String {
+        format!(
+            "{}-{}.tgz",
+            self.name.trim_start_matches('@').replace('/', "-"),
+            self.version
+        )
+    }
+
+    /// The packed files and totals, like `npm pack` prints them
+    pub fn report(&self) -> String {
+        let sizes: Vec<String> = self
+            .files
+            .iter()
+            .map(|(_, size)| human_size(*size))
+            .collect();
+        let width = sizes.iter().map(String::len).max().unwrap_or(0);
+
+        let mut lines = vec![
+            format!("package: {}@{}", self.name, self.version),
+            "Tarball Contents".to_string(),
+        ];
+        for ((path, _), size) in self.files.iter().zip(&sizes) {
+            lines.push(format!("{:>width$} {}", size, path, width = width));
+        }
+
+        let unpacked: u64 = self.files.iter().map(|(_, size)| size).sum();
+        lines.extend([
+            "Tarball Details".to_string(),
+            format!("name:          {}", self.name),
+            format!("version:       {}", self.version),
+            format!("filename:      {}", self.filename()),
+            format!("package size:  {}", human_size(self.tarball.len() as u64)),
+            format!("unpacked size: {}", human_size(unpacked)),
+            format!("total files:   {}", self.files.len()),
+        ]);
+
+        lines.join("\n")
+    }
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+impl PackActor {
+    /// Pack the package in `dir` after running its `prepack` script; running
+    /// `postpack` is up to the caller
+    pub(crate) async fn pack(dir: &Path) -> Result<Packed> {
+        let package_json = PackageJson::from_path(&dir.join("package.json")).await?;
+        lifecycle::run_hook(dir, &package_json, "prepack").await?;
+
+        // prepack may build the files the package.json points to, or the package.json itself
+        let mut package_json = PackageJson::from_path(&dir.join("package.json")).await?;
+        let (Some(name), Some(version)) = (package_json.name.clone(), package_json.version.clone())
+        else {
+            anyhow::bail!("{} needs a name and version to be packed", dir.display());
+        };
+
+        let uses_workspace = [&package_json.dependencies, &package_json.dev_dependencies]
+            .into_iter()
+            .flatten()
+            .flat_map(|deps| deps.values())
+            .any(|range| range.starts_with("workspace:"));
+        let manifest = match Workspace::find(dir).await? {
+            Some(workspace) if uses_workspace => {
+                workspace.rewrite_protocols(&mut package_json)?;
+                format!("{}\n", serde_json::to_string_pretty(&package_json)?).into_bytes()
+            }
+            _ => tokio::fs::read(dir.join("package.json")).await?,
+        };
+
+        let bins: Vec<&str> = bin_paths(&package_json)
+            .into_iter()
+            .map(|path| path.trim_start_matches("./"))
+            .collect();
+        let mut files = Vec::new();
+        for path in packlist(dir, &package_json)? {
+            let contents = if path == "package.json" {
+                manifest.clone()
+            } else {
+                tokio::fs::read(dir.join(&path)).await?
+            };
+            let executable = bins.contains(&path.as_str()) || is_executable(&dir.join(&path));
+            files.push(PackFile::new(path, contents).with_executable(executable));
+        }
+
+        Ok(Packed {
+            tarball: pack(&files)?,
+            files: files
+                .into_iter()
+                .map(|file| (file.path, file.contents.len() as u64))
+                .collect(),
+            package_json,
+            name,
+            version,
+        })
+    }
+}
+
+impl Actor<PackActorPayload> for PackActor {
+    fn with(payload: PackActorPayload) -> Self {
+        Self { payload }
+    }
+
+    async fn run(&self) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+
+        for dir in self.payload.filter.target_dirs(&cwd).await? {
+            let packed = Self::pack(&dir).await?;
+            println!("{}", packed.report());
+
+            if !self.payload.dry_run {
+                tokio::fs::write(cwd.join(packed.filename()), &packed.tarball).await?;
+            }
+            lifecycle::run_hook(&dir, &packed.package_json, "postpack").await?;
+
+            println!("{}", packed.filename());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[tokio::test]
+    async fn test_pack_workspace_package() {
+        let root = tempfile::tempdir().unwrap();
+        let web = root.path().join("packages/web");
+        let core = root.path().join("packages/core");
+        std::fs::create_dir_all(web.join("src")).unwrap();
+        std::fs::create_dir_all(&core).unwrap();
+        std::fs::write(
+            root.path().join("package.json"),
+            r#"{"workspaces": ["packages/*"]}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            core.join("package.json"),
+            r#"{"name": "@acme/core", "version": "1.2.3"}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            web.join("package.json"),
+            r#"{"name": "@acme/web", "version": "0.1.0", "files": ["src"],
+                "dependencies": {"@acme/core": "workspace:^"}}"#,
+        )
+        .unwrap();
+        std::fs::write(web.join("src/index.js"), "export {};\n").unwrap();
+        std::fs::write(web.join("notes.txt"), "not packed").unwrap();
+
+        let packed = PackActor::pack(&web).await.unwrap();
+        assert_eq!(packed.filename(), "acme-web-0.1.0.tgz");
+        assert_eq!(
+            packed
+                .files
+                .iter()
+                .map(|(path, _)| path.as_str())
+                .collect::<Vec<_>>(),
+            ["package.json", "src/index.js"]
+        );
+        assert!(packed.report().contains("total files:   2"));
+
+        let mut archive = tar::Archive::new(GzDecoder::new(packed.tarball.as_slice()));
+        let mut entry = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .find(|entry| entry.path().unwrap().to_str() == Some("package/package.json"))
+            .unwrap();
+        let mut manifest = String::new();
+        entry.read_to_string(&mut manifest).unwrap();
+        let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+        assert_eq!(manifest["dependencies"]["@acme/core"], "^1.2.3");
+    }
+}
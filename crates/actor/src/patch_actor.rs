@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use contract::{Actor, Result};
+use lockfile::PackageEntry;
+use package::{PackageJson, Selector, Workspace};
+use patch::{PackagePatch, patch_path};
+use resolver::{ArtifactSource, ResolvedArtifact, Resolver};
+use tarball::gzip::unpack;
+
+use crate::dependency_versions;
+use crate::{InstallActor, InstallActorPayload};
+
+#[derive(Debug)]
+pub struct PatchActorPayload {
+    /// `name` or `name@range` of the installed package to patch
+    pub package: String,
+}
+
+#[derive(Debug)]
+pub struct PatchCommitActorPayload {
+    /// Directory `craft patch` extracted the package to
+    pub dir: PathBuf,
+}
+
+/// `craft patch`: extract a pristine copy of an installed package to edit
+pub struct PatchActor {
+    payload: PatchActorPayload,
+}
+
+/// `craft patch-commit`: turn the edits into a patch applied on every install
+pub struct PatchCommitActor {
+    payload: PatchCommitActorPayload,
+}
+
+/// The directory of the package.json whose `patchedDependencies` apply
+async fn root(cwd: &Path) -> Result<PathBuf> {
+    Ok(Workspace::find(cwd)
+        .await?
+        .map_or(cwd.to_path_buf(), |w| w.root))
+}
+
+/// Extract the published tarball of a locked package into `dest/package`,
+/// downloading it unless it's in the cache
+async fn extract(entry: &PackageEntry, dest: &Path) -> Result<PathBuf> {
+    if !entry.resolved.starts_with("http://") && !entry.resolved.starts_with("https://") {
+        anyhow::bail!(
+            "{} isn't a registry package, only those can be patched",
+            entry.key()
+        );
+    }
+
+    let artifact = ResolvedArtifact {
+        name: entry.name.clone(),
+        version: entry.version.clone(),
+        download_url: entry.resolved.clone(),
+        package: None,
+        source: ArtifactSource::Registry,
+        patch: None,
+        cache: None,
+        scope: None,
+        dependency_scope: None,
+    };
+    let download = Resolver::new().download(&artifact).await?;
+    unpack(download.path, dest.to_path_buf()).await?;
+
+    Ok(dest.join("package"))
+}
+
+impl Actor<PatchActorPayload> for PatchActor {
+    fn with(payload: PatchActorPayload) -> Self {
+        Self { payload }
+    }
+
+    async fn run(&self) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        let lockfile = dependency_versions::load_lockfile(&cwd)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("No lockfile found, run craft install first"))?;
+
+        let selector = Selector::parse(&self.payload.package);
+        let entries: Vec<&PackageEntry> = lockfile
+            .packages
+            .values()
+            .filter(|entry| selector.matches(&entry.name, &entry.version))
+            .collect();
+        let entry = match entries.as_slice() {
+            [] => anyhow::bail!("{} isn't installed", selector),
+            [entry] => entry,
+            _ => anyhow::bail!(
+                "Several versions of {} are installed ({}), pick one with {}@<version>",
+                selector,
+                entries
+                    .iter()
+                    .map(|e| e.version.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                selector.name
+            ),
+        };
+
+        let dir = tempfile::Builder::new()
+            .prefix("craft-patch-")
+            .tempdir()?
+            .keep();
+        let package_dir = extract(entry, &dir).await?;
+
+        // Keep editing from the existing patch, patch-commit replaces it
+        let root = root(&cwd).await?;
+        let root_package_json = PackageJson::from_path(&root.join("package.json")).await?;
+        if let Some(patch) = PackagePatch::load_all(&root_package_json, &root)
+            .await?
+            .get(&entry.key())
+        {
+            patch::apply(&patch.contents, &package_dir)?;
+            println!("Applied the existing patch of {}", entry.key());
+        }
+
+        println!(
+            "Edit {} in {}, then run:\n  craft patch-commit {}",
+            entry.key(),
+            package_dir.display(),
+            package_dir.display()
+        );
+
+        Ok(())
+    }
+}
+
+impl Actor<PatchCommitActorPayload> for PatchCommitActor {
+    fn with(payload: PatchCommitActorPayload) -> Self {
+        Self { payload }
+    }
+
+    async fn run(&self) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        let dir = self
+            .payload
+            .dir
+            .canonicalize()
+            .map_err(|e| anyhow::anyhow!("Can't open {}: {}", self.payload.dir.display(), e))?;
+
+        let package_json = PackageJson::from_path(&dir.join("package.json")).await?;
+        let (Some(name), Some(version)) = (package_json.name, package_json.version) else {
+            anyhow::bail!("{} has no package name and version", dir.display());
+        };
+
+        let lockfile = dependency_versions::load_lockfile(&cwd)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("No lockfile found, run craft install first"))?;
+        let entry = lockfile
+            .get_package(&name, &version)
+            .ok_or_else(|| anyhow::anyhow!("{}@{} isn't installed", name, version))?;
+
+        let pristine = tempfile::tempdir()?;
+        let original = extract(entry, pristine.path()).await?;
+        let contents = patch::diff(&original, &dir)?;
+        if contents.is_empty() {
+            anyhow::bail!("{} has no changes to {}", dir.display(), entry.key());
+        }
+
+        let root = root(&cwd).await?;
+        let relative = patch_path(&name, &version);
+        let path = root.join(&relative);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, &contents).await?;
+
+        let package_json_path = root.join("package.json");
+        let mut root_package_json = PackageJson::from_path(&package_json_path).await?;
+        root_package_json
+            .patched_dependencies
+            .get_or_insert_with(HashMap::new)
+            .insert(entry.key(), relative.clone());
+        tokio::fs::write(
+            &package_json_path,
+            serde_json::to_string_pretty(&root_package_json)?,
+        )
+        .await?;
+        println!("Wrote {}", relative);
+
+        InstallActor::with(InstallActorPayload::default())
+            .run()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_extract_rejects_local_packages() {
+        let dir = tempfile::tempdir().unwrap();
+        let entry = PackageEntry::new(
+            "lib".to_string(),
+            "1.0.0".to_string(),
+            "file:../lib".to_string(),
+        );
+
+        let error = extract(&entry, dir.path()).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "lib@1.0.0 isn't a registry package, only those can be patched"
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use config::Config;
+use contract::{Actor, Result};
+use network::Network;
+use package::{PackageDist, PackageJson, WorkspaceFilter};
+use registry::{integrity, shasum, tarball_url};
+
+use crate::PackActor;
+use crate::lifecycle;
+use crate::pack_actor::Packed;
+
+/// Who can install a scoped package, unscoped ones are always public
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Public,
+    Restricted,
+}
+
+impl Access {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Public => "public",
+            Self::Restricted => "restricted",
+        }
+    }
+}
+
+impl FromStr for Access {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "public" => Ok(Self::Public),
+            "restricted" => Ok(Self::Restricted),
+            _ => anyhow::bail!("Invalid access {:?}, expected public or restricted", s),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PublishActorPayload {
+    /// Dist-tag pointing to the published version, `latest` when unset
+    pub tag: Option<String>,
+    pub access: Option<Access>,
+    /// One-time password for registries requiring two-factor auth
+    pub otp: Option<String>,
+    pub filter: WorkspaceFilter,
+}
+
+pub struct PublishActor {
+    payload: PublishActorPayload,
+}
+
+/// `publishConfig` of the package.json, which the command line overrides
+fn publish_config<'a>(package_json: &'a PackageJson, key: &str) -> Option<&'a str> {
+    package_json
+        .other
+        .get("publishConfig")
+        .and_then(|config| config.get(key))
+        .and_then(|value| value.as_str())
+}
+
+/// The registry's publish request: the new version's manifest, its dist-tag
+/// and the tarball as a base64 attachment
+fn document(
+    packed: &Packed,
+    registry: &str,
+    tag: &str,
+    access: Option<Access>,
+) -> Result<serde_json::Value> {
+    let mut manifest = packed.package_json.clone();
+    manifest.dist = Some(PackageDist {
+        tarball: tarball_url(registry, &packed.name, &packed.version),
+        integrity: Some(integrity(&packed.tarball)),
+        shasum: Some(shasum(&packed.tarball)),
+    });
+    let mut manifest = serde_json::to_value(manifest)?;
+    manifest["_id"] = format!("{}@{}", packed.name, packed.version).into();
+
+    Ok(serde_json::json!({
+        "_id": packed.name,
+        "name": packed.name,
+        "description": packed.package_json.description,
+        "dist-tags": { tag: packed.version },
+        "versions": { &packed.version: manifest },
+        "access": access.map(|access| access.as_str()),
+        "_attachments": {
+            format!("{}-{}.tgz", packed.name, packed.version): {
+                "content_type": "application/octet-stream",
+                "data": BASE64.encode(&packed.tarball),
+                "length": packed.tarball.len(),
+            },
+        },
+    }))
+}
+
+impl PublishActor {
+    async fn publish(&self, dir: &Path) -> Result<()> {
+        let package_json = PackageJson::from_path(&dir.join("package.json")).await?;
+        let name = package_json.name.as_deref().unwrap_or("the package");
+        if package_json
+            .other
+            .get("private")
+            .is_some_and(|private| private.as_bool() == Some(true))
+        {
+            anyhow::bail!(
+                "{} is private, remove \"private\": true from {} to publish it",
+                name,
+                dir.join("package.json").display()
+            );
+        }
+
+        let config = Config::load(dir).await?;
+        let registry = publish_config(&package_json, "registry")
+            .unwrap_or(config.registry())
+            .trim_end_matches('/')
+            .to_string();
+        let tag = match &self.payload.tag {
+            Some(tag) => tag.clone(),
+            None => publish_config(&package_json, "tag")
+                .unwrap_or("latest")
+                .to_string(),
+        };
+        let access = match self.payload.access {
+            Some(access) => Some(access),
+            None => publish_config(&package_json, "access")
+                .map(str::parse)
+                .transpose()?,
+        };
+
+        lifecycle::run_hook(dir, &package_json, "prepublishOnly").await?;
+        let packed = PackActor::pack(dir).await?;
+        lifecycle::run_hook(dir, &packed.package_json, "postpack").await?;
+
+        let mut headers = Vec::new();
+        if let Some(token) = config.auth_token(&registry) {
+            headers.push(("authorization", format!("Bearer {}", token)));
+        }
+        if let Some(otp) = &self.payload.otp {
+            headers.push(("npm-otp", otp.clone()));
+        }
+
+        let url = format!("{}/{}", registry, packed.name.replace('/', "%2f"));
+        Network::new()
+            .put(&url, &document(&packed, &registry, &tag, access)?, &headers)
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to publish {}@{}: {}",
+                    packed.name,
+                    packed.version,
+                    e
+                )
+            })?;
+
+        lifecycle::run_hook(dir, &packed.package_json, "publish").await?;
+        lifecycle::run_hook(dir, &packed.package_json, "postpublish").await?;
+
+        println!("+ {}@{} ({})", packed.name, packed.version, tag);
+        Ok(())
+    }
+}
+
+impl Actor<PublishActorPayload> for PublishActor {
+    fn with(payload: PublishActorPayload) -> Self {
+        Self { payload }
+    }
+
+    async fn run(&self) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+
+        for dir in self.payload.filter.target_dirs(&cwd).await? {
+            self.publish(&dir).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(package_json: &str) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("package.json"), package_json).unwrap();
+        std::fs::write(dir.path().join("index.js"), "module.exports = 1;\n").unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_publish_to_mock_registry() {
+        let mock = registry::MockRegistry::start().unwrap();
+        let registry = mock.url().to_string();
+        let dir = package(r#"{"name": "@acme/lib", "version": "1.0.0", "description": "A lib"}"#);
+        std::fs::write(
+            dir.path().join(config::CONFIG_FILE_NAME),
+            format!(
+                "registry={}/\n{}/:_authToken={}\n",
+                registry,
+                registry.trim_start_matches("http:"),
+                registry::MockRegistry::TOKEN
+            ),
+        )
+        .unwrap();
+
+        PublishActor::with(PublishActorPayload {
+            tag: Some("next".to_string()),
+            access: Some(Access::Public),
+            otp: Some("123456".to_string()),
+            ..Default::default()
+        })
+        .publish(dir.path())
+        .await
+        .unwrap();
+
+        let requests = mock.requests();
+        let request = &requests[0];
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.path, "/@acme%2flib");
+        assert_eq!(
+            request.headers["authorization"],
+            format!("Bearer {}", registry::MockRegistry::TOKEN)
+        );
+        assert_eq!(request.headers["npm-otp"], "123456");
+
+        let document: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(document["dist-tags"]["next"], "1.0.0");
+        assert_eq!(document["access"], "public");
+        let version = &document["versions"]["1.0.0"];
+        assert_eq!(version["_id"], "@acme/lib@1.0.0");
+        assert_eq!(
+            version["dist"]["tarball"],
+            format!("{}/@acme/lib/-/lib-1.0.0.tgz", registry)
+        );
+        assert!(
+            version["dist"]["integrity"]
+                .as_str()
+                .unwrap()
+                .starts_with("sha512-")
+        );
+
+        let attachment = &document["_attachments"]["@acme/lib-1.0.0.tgz"];
+        let tarball = BASE64.decode(attachment["data"].as_str().unwrap()).unwrap();
+        assert_eq!(attachment["length"], tarball.len());
+        assert_eq!(version["dist"]["shasum"], shasum(&tarball));
+
+        // The registry took the package
+        let packument: serde_json::Value = Network::new()
+            .fetch(&format!("{}/@acme%2flib", registry))
+            .await
+            .unwrap();
+        assert_eq!(packument["dist-tags"]["next"], "1.0.0");
+    }
+
+    #[tokio::test]
+    async fn test_refuses_private_packages() {
+        let dir = package(r#"{"name": "app", "version": "1.0.0", "private": true}"#);
+
+        let error = PublishActor::with(PublishActorPayload::default())
+            .publish(dir.path())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().starts_with("app is private"));
+    }
+}
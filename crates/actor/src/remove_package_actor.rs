@@ -1,28 +1,22 @@
 use contract::{Actor, Result};
-use package::PackageJson;
-use std::path::PathBuf;
+use package::{PackageJson, WorkspaceFilter};
+use std::path::{Path, PathBuf};
 use tokio::fs;
 
 #[derive(Debug)]
 pub struct RemoveActorPayload {
     pub packages: Vec<String>,
     pub working_dir: Option<PathBuf>,
+    pub filter: WorkspaceFilter,
 }
 
 pub struct RemovePackageActor {
     payload: RemoveActorPayload,
 }
 
-impl Actor<RemoveActorPayload> for RemovePackageActor {
-    fn with(payload: RemoveActorPayload) -> Self {
-        Self { payload }
-    }
-
-    async fn run(&self) -> Result<()> {
-        let cwd =
-            self.payload.working_dir.clone().unwrap_or_else(|| {
-                std::env::current_dir().expect("Failed to get current directory")
-            });
+impl RemovePackageActor {
+    /// Remove the packages from the project in `cwd`
+    async fn remove_from(&self, cwd: &Path) -> Result<()> {
         let node_modules = cwd.join("node_modules");
 
         // Remove packages from node_modules
@@ -76,6 +70,25 @@ impl Actor<RemoveActorPayload> for RemovePackageActor {
     }
 }
 
+impl Actor<RemoveActorPayload> for RemovePackageActor {
+    fn with(payload: RemoveActorPayload) -> Self {
+        Self { payload }
+    }
+
+    async fn run(&self) -> Result<()> {
+        let cwd =
+            self.payload.working_dir.clone().unwrap_or_else(|| {
+                std::env::current_dir().expect("Failed to get current directory")
+            });
+
+        for dir in self.payload.filter.target_dirs(&cwd).await? {
+            self.remove_from(&dir).await?;
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,6 +107,7 @@ mod tests {
         let payload = RemoveActorPayload {
             packages: vec!["test-package".to_string()],
             working_dir: Some(temp_dir.path().to_path_buf()),
+            filter: WorkspaceFilter::default(),
         };
 
         let actor = RemovePackageActor::with(payload);
@@ -126,6 +140,7 @@ mod tests {
         let payload = RemoveActorPayload {
             packages: vec!["react".to_string(), "typescript".to_string()],
             working_dir: Some(temp_dir.path().to_path_buf()),
+            filter: WorkspaceFilter::default(),
         };
 
         let actor = RemovePackageActor::with(payload);
@@ -159,6 +174,7 @@ mod tests {
         let payload = RemoveActorPayload {
             packages: vec!["nonexistent-package".to_string()],
             working_dir: Some(temp_dir.path().to_path_buf()),
+            filter: WorkspaceFilter::default(),
         };
 
         let actor = RemovePackageActor::with(payload);
@@ -168,11 +184,53 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_remove_package_actor_with_filter() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::write(
+            root.join("package.json"),
+            r#"{"name": "root", "workspaces": ["packages/*"]}"#,
+        )
+        .await
+        .unwrap();
+
+        for name in ["web", "api"] {
+            let dir = root.join("packages").join(name);
+            fs::create_dir_all(dir.join("node_modules/zod"))
+                .await
+                .unwrap();
+            fs::write(
+                dir.join("package.json"),
+                format!(r#"{{"name": "{name}", "dependencies": {{"zod": "^3.0.0"}}}}"#),
+            )
+            .await
+            .unwrap();
+        }
+
+        let payload = RemoveActorPayload {
+            packages: vec!["zod".to_string()],
+            working_dir: Some(root.to_path_buf()),
+            filter: WorkspaceFilter::parse(&["web".to_string()], false).unwrap(),
+        };
+
+        RemovePackageActor::with(payload).run().await.unwrap();
+
+        assert!(!root.join("packages/web/node_modules/zod").exists());
+        assert!(root.join("packages/api/node_modules/zod").exists());
+
+        let api = fs::read_to_string(root.join("packages/api/package.json"))
+            .await
+            .unwrap();
+        assert!(api.contains("zod"));
+    }
+
     #[test]
     fn test_remove_actor_payload_creation() {
         let payload = RemoveActorPayload {
             packages: vec!["test".to_string()],
             working_dir: None,
+            filter: WorkspaceFilter::default(),
         };
 
         assert_eq!(payload.packages.len(), 1);
@@ -1,11 +1,19 @@
+use std::path::Path;
+
+use crate::lifecycle::{ScriptStep, script_process, script_steps};
+use crate::workspace_run::{RecursiveOptions, WorkspaceRun};
+use config::Config;
 use contract::{Actor, Result};
-use package::PackageJson;
-use process::Process;
+use package::{PackageJson, Workspace, WorkspaceFilter};
+use process::shell;
 
 #[derive(Debug)]
 pub struct RunScriptActorPayload {
     pub script: String,
     pub args: Vec<String>,
+    pub filter: WorkspaceFilter,
+    /// Run in every selected workspace package, in dependency order (`-r`)
+    pub recursive: Option<RecursiveOptions>,
 }
 
 impl std::fmt::Display for RunScriptActorPayload {
@@ -19,25 +27,76 @@ pub struct RunScriptActor {
 }
 
 impl RunScriptActor {
-    async fn lookup_script(&self) -> Result<String> {
-        let mut script = self.payload.script.clone();
-        let package_json = PackageJson::from_file().await?;
-
-        if let Some(scripts) = package_json.scripts
-            && let Some(package_script) = scripts.get(&script)
-        {
-            script = package_script.clone();
-        } else {
-            // Check if it exists in node_modules/.bin
-            let cwd = std::env::current_dir()?;
-            let bin_path = cwd.join("node_modules").join(".bin").join(&script);
+    /// Find what to run in `cwd`: the package.json script with its pre/post hooks,
+    /// or a `node_modules/.bin` entry. `None` if neither exists.
+    async fn lookup_script(&self, cwd: &Path) -> Result<Option<(PackageJson, Vec<ScriptStep>)>> {
+        let script = &self.payload.script;
+        let package_json = PackageJson::from_path(&cwd.join("package.json")).await?;
+
+        if let Some(steps) = script_steps(&package_json, script, &self.payload.args) {
+            return Ok(Some((package_json, steps)));
+        }
+
+        // Check if it exists in node_modules/.bin
+        let bin_path = cwd.join("node_modules").join(".bin").join(script);
+
+        if bin_path.exists() {
+            let step = self.command_step(bin_path.to_string_lossy().to_string());
+            return Ok(Some((package_json, vec![step])));
+        }
+
+        Ok(None)
+    }
 
-            if bin_path.exists() {
-                script = bin_path.to_string_lossy().to_string();
-            }
+    /// Run `command` with the payload's arguments
+    fn command_step(&self, mut command: String) -> ScriptStep {
+        if !self.payload.args.is_empty() {
+            command.push(' ');
+            command.push_str(&shell::join(&self.payload.args));
         }
 
-        Ok(script)
+        ScriptStep {
+            event: self.payload.script.clone(),
+            command,
+        }
+    }
+
+    async fn run_in(
+        &self,
+        cwd: &Path,
+        package_json: &PackageJson,
+        steps: &[ScriptStep],
+    ) -> Result<()> {
+        let config = Config::load(cwd).await?;
+        for step in steps {
+            debug::info!("Running {}: {}", step.event, step.command);
+            script_process(cwd, package_json, step, config.registry())
+                .run()
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn run_recursive(&self, cwd: &Path, options: &RecursiveOptions) -> Result<()> {
+        let workspace = Workspace::find(cwd)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("--recursive can only be used in a workspace"))?;
+
+        let dirs = if self.payload.filter.is_empty() {
+            workspace.packages.iter().map(|p| p.dir.clone()).collect()
+        } else {
+            self.payload.filter.target_dirs(cwd).await?
+        };
+
+        WorkspaceRun::new(
+            &workspace,
+            &self.payload.script,
+            &self.payload.args,
+            options,
+        )
+        .run(&dirs)
+        .await
     }
 }
 
@@ -48,18 +107,46 @@ impl Actor<RunScriptActorPayload> for RunScriptActor {
 
     async fn run(&self) -> contract::Result<()> {
         debug::info!("Running script: {}", self.payload.script);
-        let script = self.lookup_script().await?;
-
         let cwd = std::env::current_dir()?;
 
-        let mut command_with_args = script;
+        if let Some(options) = &self.payload.recursive {
+            return self.run_recursive(&cwd, options).await;
+        }
 
-        if !self.payload.args.is_empty() {
-            command_with_args.push(' ');
-            command_with_args.push_str(&self.payload.args.join(" "));
+        if self.payload.filter.is_empty() {
+            // Unknown names are run as plain commands
+            let (package_json, steps) = match self.lookup_script(&cwd).await? {
+                Some(found) => found,
+                None => (
+                    PackageJson::from_path(&cwd.join("package.json")).await?,
+                    vec![self.command_step(self.payload.script.clone())],
+                ),
+            };
+            self.run_in(&cwd, &package_json, &steps).await?;
+
+            debug::info!("Script completed successfully");
+            return Ok(());
         }
 
-        Process::run_script(&command_with_args, Some(cwd.to_str().unwrap_or("."))).await?;
+        // Selected packages without the script are skipped
+        let mut ran = false;
+        for dir in self.payload.filter.target_dirs(&cwd).await? {
+            let Some((package_json, steps)) = self.lookup_script(&dir).await? else {
+                debug::info!("No {} script in {:?}, skipping", self.payload.script, dir);
+                continue;
+            };
+
+            println!("> {} {}", dir.display(), self.payload.script);
+            self.run_in(&dir, &package_json, &steps).await?;
+            ran = true;
+        }
+
+        if !ran {
+            anyhow::bail!(
+                "None of the selected packages have a {} script",
+                self.payload.script
+            );
+        }
 
         debug::info!("Script completed successfully");
         Ok(())
@@ -75,6 +162,8 @@ mod tests {
         let payload = RunScriptActorPayload {
             script: "echo".to_string(),
             args: vec!["test".to_string()],
+            filter: WorkspaceFilter::default(),
+            recursive: None,
         };
 
         assert_eq!(format!("{}", payload), "echo test");
@@ -85,6 +174,8 @@ mod tests {
         let payload = RunScriptActorPayload {
             script: "craft".to_string(),
             args: vec!["test".to_string()],
+            filter: WorkspaceFilter::default(),
+            recursive: None,
         };
 
         let actor = RunScriptActor::with(payload);
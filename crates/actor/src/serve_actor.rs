@@ -0,0 +1,57 @@
+use contract::{Actor, Result, get_package_cache_dir, get_registry_storage_dir};
+use registry::{Registry, Storage};
+
+#[derive(Debug)]
+pub struct ServeActorPayload {
+    pub host: String,
+    pub port: u16,
+    /// Registry to proxy packages missing from the cache to
+    pub upstream: Option<String>,
+    /// Token publishing takes, publishing is refused without one
+    pub token: Option<String>,
+}
+
+/// `craft serve`: an npm-compatible registry over the package cache, storing
+/// published packages apart from it
+pub struct ServeActor {
+    payload: ServeActorPayload,
+}
+
+impl Actor<ServeActorPayload> for ServeActor {
+    fn with(payload: ServeActorPayload) -> Self {
+        Self { payload }
+    }
+
+    async fn run(&self) -> Result<()> {
+        let cache_dir = get_package_cache_dir();
+        let storage_dir = get_registry_storage_dir();
+        tokio::fs::create_dir_all(&cache_dir).await?;
+        tokio::fs::create_dir_all(&storage_dir).await?;
+
+        let mut registry = Registry::new(Storage::new(storage_dir.clone()))
+            .with_cache(Storage::new(cache_dir.clone()));
+        if let Some(upstream) = &self.payload.upstream {
+            registry = registry.with_upstream(upstream.clone());
+        }
+        if let Some(token) = &self.payload.token {
+            registry = registry.with_token(token.clone());
+        }
+        let server = registry.bind((self.payload.host.as_str(), self.payload.port))?;
+
+        println!(
+            "Serving {} at http://{}",
+            cache_dir.display(),
+            server.local_addr()?
+        );
+        if let Some(upstream) = &self.payload.upstream {
+            println!("Proxying missing packages to {}", upstream);
+        }
+        if self.payload.token.is_some() {
+            println!("Publishing to {}", storage_dir.display());
+        } else {
+            println!("Publishing is disabled, start with --token to enable it");
+        }
+
+        server.run().await
+    }
+}
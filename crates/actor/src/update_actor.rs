@@ -0,0 +1,362 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use contract::{Actor, Result};
+use package::{PackageJson, WorkspaceFilter, range};
+use progress::{Picker, PickerItem};
+
+use crate::dependency_versions::{self, DependencyVersions, NamePatterns, Severity};
+use crate::{InstallActor, InstallActorPayload};
+
+#[derive(Debug)]
+pub struct UpdateActorPayload {
+    /// Names or globs (`@types/*`) of the dependencies to update, all when empty
+    pub packages: Vec<String>,
+    /// Move ranges to the latest version instead of staying within them
+    pub latest: bool,
+    /// Choose the dependencies to update in a picker
+    pub interactive: bool,
+    pub filter: WorkspaceFilter,
+}
+
+impl std::fmt::Display for UpdateActorPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.packages.join(" "))
+    }
+}
+
+pub struct UpdateActor {
+    payload: UpdateActorPayload,
+}
+
+type Project = (PathBuf, PackageJson, Vec<DependencyVersions>);
+
+/// An outdated dependency offered in the picker, by project and dependency index
+struct Candidate<'a> {
+    index: (usize, usize),
+    project: Option<&'a str>,
+    dep: &'a DependencyVersions,
+    severity: Option<Severity>,
+}
+
+impl UpdateActor {
+    /// The range to write for a dependency with `--latest`, `None` when it stays
+    /// as it is. The range follows the latest version (`~1.0.0` -> `~2.1.0`),
+    /// keeping its prefix.
+    fn next_range(dep: &DependencyVersions) -> Option<String> {
+        let next = range::to_latest(&dep.range, dep.latest.as_deref()?)?;
+        (next != dep.range).then_some(next)
+    }
+
+    /// Dependencies whose installed version isn't the one they'd be updated to,
+    /// ordered by dependency type, then severity, then name
+    fn candidates(projects: &[Project], latest: bool) -> Vec<Candidate<'_>> {
+        let mut candidates: Vec<Candidate> = projects
+            .iter()
+            .enumerate()
+            .flat_map(|(p, (_, package_json, deps))| {
+                let project = package_json.name.as_deref().filter(|_| projects.len() > 1);
+
+                deps.iter().enumerate().filter_map(move |(d, dep)| {
+                    let target = if latest { &dep.latest } else { &dep.wanted };
+                    let target = target.as_deref()?;
+                    if dep.current.as_deref() == Some(target) {
+                        return None;
+                    }
+
+                    Some(Candidate {
+                        index: (p, d),
+                        project,
+                        dep,
+                        severity: dep
+                            .current
+                            .as_deref()
+                            .and_then(|current| Severity::between(current, target)),
+                    })
+                })
+            })
+            .collect();
+
+        candidates.sort_by(|a, b| {
+            (a.dep.is_dev, a.severity, &a.dep.name).cmp(&(b.dep.is_dev, b.severity, &b.dep.name))
+        });
+        candidates
+    }
+
+    /// The dependencies to re-resolve, the picked ones or those named, `None` for
+    /// all of them. The others keep their locked versions.
+    fn resolve_only(
+        &self,
+        projects: &[Project],
+        selected: Option<&HashSet<(usize, usize)>>,
+    ) -> Option<Vec<String>> {
+        let names: Vec<String> = match selected {
+            Some(selected) => selected
+                .iter()
+                .map(|&(p, d)| projects[p].2[d].name.clone())
+                .collect(),
+            None if !self.payload.packages.is_empty() => projects
+                .iter()
+                .flat_map(|(_, _, deps)| deps.iter().map(|d| d.name.clone()))
+                .collect(),
+            None => return None,
+        };
+        Some(names)
+    }
+
+    /// Let the user choose among the outdated dependencies, `None` when there are
+    /// none or the picker was cancelled
+    fn pick(&self, projects: &[Project]) -> Result<Option<HashSet<(usize, usize)>>> {
+        let candidates = Self::candidates(projects, self.payload.latest);
+        if candidates.is_empty() {
+            println!("All dependencies are up to date");
+            return Ok(None);
+        }
+
+        let names: Vec<String> = candidates
+            .iter()
+            .map(|c| match c.project {
+                Some(project) => format!("{} ({})", c.dep.name, project),
+                None => c.dep.name.clone(),
+            })
+            .collect();
+        let width = names.iter().map(String::len).max().unwrap_or(0).max(7);
+        let column = |value: Option<&str>| format!("{:<10}", value.unwrap_or("-"));
+
+        let mut picker = Picker::new("Choose the dependencies to update").with_header(&format!(
+            "{:<width$}  {}  {}  {}",
+            "Package",
+            column(Some("Current")),
+            column(Some("Wanted")),
+            "Latest"
+        ));
+        for (candidate, name) in candidates.iter().zip(&names) {
+            let kind = if candidate.dep.is_dev {
+                "devDependencies"
+            } else {
+                "dependencies"
+            };
+            let group = match candidate.severity {
+                Some(severity) => format!("{} · {}", kind, severity.as_str()),
+                None => format!("{} · not installed", kind),
+            };
+
+            picker = picker.with_item(PickerItem::new(
+                &group,
+                &format!(
+                    "{:<width$}  {}  {}  {}",
+                    name,
+                    column(candidate.dep.current.as_deref()),
+                    column(candidate.dep.wanted.as_deref()),
+                    candidate.dep.latest.as_deref().unwrap_or("-")
+                ),
+            ));
+        }
+
+        let Some(selected) = picker.interact()? else {
+            return Ok(None);
+        };
+
+        Ok(Some(
+            selected.into_iter().map(|i| candidates[i].index).collect(),
+        ))
+    }
+}
+
+impl Actor<UpdateActorPayload> for UpdateActor {
+    fn with(payload: UpdateActorPayload) -> Self {
+        Self { payload }
+    }
+
+    async fn run(&self) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        let dirs = self.payload.filter.target_dirs(&cwd).await?;
+        let patterns = NamePatterns::parse(&self.payload.packages)?;
+
+        let lockfile = dependency_versions::load_lockfile(&cwd).await?;
+
+        let mut projects: Vec<Project> = Vec::new();
+        for dir in dirs {
+            let package_json = PackageJson::from_path(&dir.join("package.json")).await?;
+            let deps =
+                dependency_versions::collect(&dir, &package_json, &patterns, lockfile.as_ref())
+                    .await?;
+            projects.push((dir, package_json, deps));
+        }
+
+        let names: Vec<&str> = projects
+            .iter()
+            .flat_map(|(_, _, deps)| deps.iter().map(|d| d.name.as_str()))
+            .collect();
+        let unmatched = patterns.unmatched(&names);
+        if !unmatched.is_empty() {
+            anyhow::bail!("No dependencies match {}", unmatched.join(", "));
+        }
+
+        let selected = if self.payload.interactive {
+            match self.pick(&projects)? {
+                Some(selected) if !selected.is_empty() => Some(selected),
+                _ => return Ok(()),
+            }
+        } else {
+            None
+        };
+
+        let resolve_only = self.resolve_only(&projects, selected.as_ref());
+
+        for (p, (dir, mut package_json, deps)) in projects.into_iter().enumerate() {
+            let mut changed = false;
+
+            for (d, dep) in deps.iter().enumerate() {
+                if selected.as_ref().is_some_and(|s| !s.contains(&(p, d))) {
+                    continue;
+                }
+
+                // Within ranges package.json stays as it is, the install below
+                // picks the highest versions they allow
+                if !self.payload.latest {
+                    if let Some(wanted) = dep.wanted.as_deref()
+                        && dep.current.as_deref() != Some(wanted)
+                    {
+                        let current = dep.current.as_deref().unwrap_or("-");
+                        println!("{} {} → {}", dep.name, current, wanted);
+                    }
+                    continue;
+                }
+
+                let Some(next) = Self::next_range(dep) else {
+                    continue;
+                };
+
+                let target = if dep.is_dev {
+                    &mut package_json.dev_dependencies
+                } else {
+                    &mut package_json.dependencies
+                };
+                if let Some(ranges) = target {
+                    ranges.insert(dep.name.clone(), next.clone());
+                }
+
+                println!("{} {} → {}", dep.name, dep.range, next);
+                changed = true;
+            }
+
+            if changed {
+                let package_json_path = dir.join("package.json");
+                tokio::fs::write(
+                    &package_json_path,
+                    serde_json::to_string_pretty(&package_json)?,
+                )
+                .await?;
+                debug::info!("Updated {:?}", package_json_path);
+            }
+        }
+
+        // Re-resolve within the (new) ranges and refresh node_modules and the lockfile
+        InstallActor::with(InstallActorPayload {
+            filter: self.payload.filter.clone(),
+            resolve_only,
+        })
+        .run()
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dep(range: &str, wanted: &str, latest: &str) -> DependencyVersions {
+        DependencyVersions {
+            name: "react".to_string(),
+            range: range.to_string(),
+            is_dev: false,
+            current: None,
+            wanted: Some(wanted.to_string()),
+            latest: Some(latest.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_candidates() {
+        let mut current = dep("^18.0.0", "18.3.1", "19.0.0");
+        current.current = Some("18.3.1".to_string());
+        let mut patch = dep("^5.0.0", "5.0.2", "5.0.2");
+        patch.name = "typescript".to_string();
+        patch.is_dev = true;
+        patch.current = Some("5.0.1".to_string());
+        let mut missing = dep("^3.0.0", "3.1.0", "3.1.0");
+        missing.name = "zod".to_string();
+
+        let projects = vec![(
+            PathBuf::from("."),
+            PackageJson::default(),
+            vec![current, patch, missing],
+        )];
+
+        // Within range, react is already at the wanted version
+        let names = |latest| -> Vec<(String, Option<Severity>)> {
+            UpdateActor::candidates(&projects, latest)
+                .iter()
+                .map(|c| (c.dep.name.clone(), c.severity))
+                .collect()
+        };
+        assert_eq!(
+            names(false),
+            vec![
+                ("zod".to_string(), None),
+                ("typescript".to_string(), Some(Severity::Patch))
+            ]
+        );
+        assert_eq!(
+            names(true),
+            vec![
+                ("zod".to_string(), None),
+                ("react".to_string(), Some(Severity::Major)),
+                ("typescript".to_string(), Some(Severity::Patch))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_next_range_latest() {
+        let next = |range| UpdateActor::next_range(&dep(range, "18.3.1", "19.0.0"));
+
+        assert_eq!(next("^18.0.0").as_deref(), Some("^19.0.0"));
+        assert_eq!(next("~18.2.0").as_deref(), Some("~19.0.0"));
+        assert_eq!(next("18.2.0").as_deref(), Some("19.0.0"));
+        assert_eq!(next("18.x").as_deref(), Some("^19.0.0"));
+        assert_eq!(next("^19.0.0"), None);
+        assert_eq!(next("*"), None);
+    }
+
+    #[test]
+    fn test_resolve_only() {
+        let mut zod = dep("^3.0.0", "3.1.0", "3.1.0");
+        zod.name = "zod".to_string();
+        let projects = vec![(
+            PathBuf::from("."),
+            PackageJson::default(),
+            vec![dep("^18.0.0", "18.3.1", "19.0.0"), zod],
+        )];
+        let actor = |packages: &[&str]| {
+            UpdateActor::with(UpdateActorPayload {
+                packages: packages.iter().map(|p| p.to_string()).collect(),
+                latest: false,
+                interactive: false,
+                filter: WorkspaceFilter::default(),
+            })
+        };
+
+        assert_eq!(actor(&[]).resolve_only(&projects, None), None);
+        assert_eq!(
+            actor(&["react", "zod"]).resolve_only(&projects, None),
+            Some(vec!["react".to_string(), "zod".to_string()])
+        );
+        // Picked dependencies narrow the ones named
+        assert_eq!(
+            actor(&[]).resolve_only(&projects, Some(&HashSet::from([(0, 1)]))),
+            Some(vec!["zod".to_string()])
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+
+use contract::{Actor, Result};
+use lockfile::{DependencyGraph, Edge};
+use node_semver::{Range, Version};
+use package::{InstallPackage, WorkspaceFilter};
+
+use crate::dependency_versions;
+use crate::importers;
+
+#[derive(Debug)]
+pub struct WhyActorPayload {
+    /// `name` or `name@range` of the package to explain
+    pub package: String,
+    pub filter: WorkspaceFilter,
+}
+
+impl std::fmt::Display for WhyActorPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.package)
+    }
+}
+
+pub struct WhyActor {
+    payload: WhyActorPayload,
+}
+
+impl WhyActor {
+    /// One dependency chain: `web (dev) › react-dom@^18.2.0 › scheduler@^0.23.0`
+    pub(crate) fn chain(importer: &str, is_dev: bool, path: &[Edge]) -> String {
+        let root = if is_dev {
+            format!("{} (dev)", importer)
+        } else {
+            importer.to_string()
+        };
+
+        std::iter::once(root)
+            .chain(
+                path.iter()
+                    .map(|edge| format!("{}@{}", edge.name, edge.range)),
+            )
+            .collect::<Vec<_>>()
+            .join(" › ")
+    }
+}
+
+impl Actor<WhyActorPayload> for WhyActor {
+    fn with(payload: WhyActorPayload) -> Self {
+        Self { payload }
+    }
+
+    async fn run(&self) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        let lockfile = dependency_versions::load_lockfile(&cwd)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("No lockfile found, run craft install first"))?;
+        let graph = DependencyGraph::new(&lockfile);
+
+        let target = InstallPackage::from_literal(&self.payload.package, false);
+        let range = match &target.version {
+            Some(range) => Some(
+                Range::parse(range)
+                    .map_err(|e| anyhow::anyhow!("Invalid range {}: {}", range, e))?,
+            ),
+            None => None,
+        };
+        let matches = |entry: &lockfile::PackageEntry| {
+            entry.name == target.name
+                && range.as_ref().is_none_or(|range| {
+                    Version::parse(&entry.version).is_ok_and(|v| range.satisfies(&v))
+                })
+        };
+
+        // Chains grouped by the version they lead to
+        let mut chains: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for importer in importers::load(&cwd, &self.payload.filter).await? {
+            let edges = importer.edges(&graph);
+
+            for is_dev in [false, true] {
+                let roots: Vec<Edge> = edges
+                    .iter()
+                    .filter(|(_, dev)| *dev == is_dev)
+                    .map(|(edge, _)| edge.clone())
+                    .collect();
+
+                for path in graph.paths(&roots, matches) {
+                    let Some(last) = path.last() else {
+                        continue;
+                    };
+                    chains
+                        .entry(last.entry.key())
+                        .or_default()
+                        .push(Self::chain(&importer.name, is_dev, &path));
+                }
+            }
+        }
+
+        if chains.is_empty() {
+            anyhow::bail!("{} is not a dependency", self.payload.package);
+        }
+
+        for (version, lines) in chains {
+            println!("{}", version);
+            for line in lines {
+                println!("  {}", line);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lockfile::PackageEntry;
+
+    #[test]
+    fn test_chain() {
+        let react_dom =
+            PackageEntry::new("react-dom".to_string(), "18.3.1".to_string(), String::new());
+        let scheduler =
+            PackageEntry::new("scheduler".to_string(), "0.23.2".to_string(), String::new());
+        let path = [
+            Edge {
+                name: "react-dom".to_string(),
+                range: "^18.2.0".to_string(),
+                entry: &react_dom,
+            },
+            Edge {
+                name: "scheduler".to_string(),
+                range: "^0.23.0".to_string(),
+                entry: &scheduler,
+            },
+        ];
+
+        assert_eq!(
+            WhyActor::chain("web", false, &path),
+            "web › react-dom@^18.2.0 › scheduler@^0.23.0"
+        );
+        assert_eq!(
+            WhyActor::chain("web", true, &path[..1]),
+            "web (dev) › react-dom@^18.2.0"
+        );
+    }
+}
@@ -0,0 +1,438 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use config::Config;
+use contract::Result;
+use package::{PackageJson, Workspace, WorkspacePackage};
+use tokio::task::JoinSet;
+
+use crate::lifecycle::{ScriptStep, script_process, script_steps};
+
+/// Options for `craft run -r`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecursiveOptions {
+    /// Maximum number of scripts running at once
+    pub concurrency: usize,
+    /// Stop starting new scripts after the first failure
+    pub bail: bool,
+    /// Print each package's output in one block once it finishes, instead of
+    /// interleaving prefixed lines
+    pub grouped: bool,
+}
+
+impl Default for RecursiveOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            bail: true,
+            grouped: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Outcome {
+    Success,
+    Failed(Option<i32>),
+    /// Not started because an earlier script failed
+    Skipped,
+}
+
+#[derive(Debug)]
+struct Report {
+    name: String,
+    outcome: Outcome,
+    duration: Duration,
+}
+
+/// A package that has the script, with the selected packages it has to wait for
+#[derive(Debug)]
+struct Task {
+    name: String,
+    dir: PathBuf,
+    package_json: PackageJson,
+    steps: Vec<ScriptStep>,
+    deps: BTreeSet<PathBuf>,
+}
+
+/// Runs one script across workspace packages in dependency order.
+///
+/// A package starts once every selected package it depends on (directly or
+/// through other workspace packages) has finished, so independent packages run
+/// in parallel up to the concurrency limit.
+pub(crate) struct WorkspaceRun<'a> {
+    workspace: &'a Workspace,
+    script: &'a str,
+    args: &'a [String],
+    options: &'a RecursiveOptions,
+}
+
+impl<'a> WorkspaceRun<'a> {
+    pub fn new(
+        workspace: &'a Workspace,
+        script: &'a str,
+        args: &'a [String],
+        options: &'a RecursiveOptions,
+    ) -> Self {
+        Self {
+            workspace,
+            script,
+            args,
+            options,
+        }
+    }
+
+    /// Run the script in the packages at `dirs`, skipping those without it
+    pub async fn run(&self, dirs: &[PathBuf]) -> Result<()> {
+        let mut pending = self.tasks(dirs);
+
+        if pending.is_empty() {
+            anyhow::bail!(
+                "None of the selected packages have a {} script",
+                self.script
+            );
+        }
+
+        let registry = Config::load(&self.workspace.root)
+            .await?
+            .registry()
+            .to_string();
+        let width = pending.iter().map(|t| t.name.len()).max().unwrap_or(0);
+        let concurrency = self.options.concurrency.max(1);
+
+        let mut done: BTreeSet<PathBuf> = BTreeSet::new();
+        let mut running = JoinSet::new();
+        let mut reports = Vec::new();
+        let mut failed = false;
+
+        loop {
+            while !(failed && self.options.bail) && running.len() < concurrency {
+                let Some(index) = pending.iter().position(|t| t.deps.is_subset(&done)) else {
+                    break;
+                };
+                let task = pending.remove(index);
+                let prefix = format!("{:width$} |", task.name);
+                let grouped = self.options.grouped;
+                let registry = registry.clone();
+
+                running.spawn(async move {
+                    let started = Instant::now();
+                    let mut outcome = Outcome::Success;
+
+                    // Hooks run in order, stopping at the first failure
+                    for step in &task.steps {
+                        let status = script_process(&task.dir, &task.package_json, step, &registry)
+                            .run_prefixed(&prefix, grouped)
+                            .await;
+
+                        match status {
+                            Ok(status) if status.success() => continue,
+                            Ok(status) => outcome = Outcome::Failed(status.code()),
+                            Err(e) => {
+                                debug::error!(
+                                    "Failed to run {} in {:?}: {}",
+                                    step.event,
+                                    task.dir,
+                                    e
+                                );
+                                outcome = Outcome::Failed(None);
+                            }
+                        }
+                        break;
+                    }
+
+                    (
+                        task.dir,
+                        Report {
+                            name: task.name,
+                            outcome,
+                            duration: started.elapsed(),
+                        },
+                    )
+                });
+            }
+
+            let Some(finished) = running.join_next().await else {
+                if pending.is_empty() || (failed && self.options.bail) {
+                    break;
+                }
+
+                // Nothing running and nothing ready: the remaining packages depend on each other
+                let task = &mut pending[0];
+                debug::warning!(
+                    "Dependency cycle detected, running {} without waiting for its dependencies",
+                    task.name
+                );
+                task.deps.clear();
+                continue;
+            };
+
+            let (dir, report) = finished?;
+            failed |= report.outcome != Outcome::Success;
+            done.insert(dir);
+            reports.push(report);
+        }
+
+        reports.extend(pending.into_iter().map(|task| Report {
+            name: task.name,
+            outcome: Outcome::Skipped,
+            duration: Duration::ZERO,
+        }));
+
+        println!("{}", Self::summary(&reports));
+
+        let failures = reports
+            .iter()
+            .filter(|r| matches!(r.outcome, Outcome::Failed(_)))
+            .count();
+        if failures > 0 {
+            anyhow::bail!(
+                "{} of {} packages failed running {}",
+                failures,
+                reports.len(),
+                self.script
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Tasks for the selected packages that define the script
+    fn tasks(&self, dirs: &[PathBuf]) -> Vec<Task> {
+        let selected: BTreeSet<&Path> = dirs.iter().map(PathBuf::as_path).collect();
+        let mut tasks = Vec::new();
+
+        for dir in dirs {
+            let (name, package_json) = match self.workspace.packages.iter().find(|p| &p.dir == dir)
+            {
+                Some(member) => (member.name.clone(), &member.package_json),
+                None if *dir == self.workspace.root => (
+                    self.workspace
+                        .package_json
+                        .name
+                        .clone()
+                        .unwrap_or_else(|| "<root>".to_string()),
+                    &self.workspace.package_json,
+                ),
+                None => continue,
+            };
+
+            let Some(steps) = script_steps(package_json, self.script, self.args) else {
+                debug::info!("No {} script in {}, skipping", self.script, name);
+                continue;
+            };
+
+            let deps = match self.workspace.packages.iter().find(|p| &p.dir == dir) {
+                Some(member) => self
+                    .transitive_dependencies(member)
+                    .into_iter()
+                    .filter(|dep| selected.contains(dep.as_path()))
+                    .collect(),
+                None => BTreeSet::new(),
+            };
+
+            tasks.push(Task {
+                name,
+                dir: dir.clone(),
+                package_json: package_json.clone(),
+                steps,
+                deps,
+            });
+        }
+
+        // Packages without the script don't run, but those waiting on them
+        // still wait on their dependencies
+        let runnable: BTreeSet<PathBuf> = tasks.iter().map(|t| t.dir.clone()).collect();
+        for task in &mut tasks {
+            task.deps.retain(|dep| runnable.contains(dep));
+        }
+
+        tasks
+    }
+
+    fn transitive_dependencies(&self, member: &WorkspacePackage) -> BTreeSet<PathBuf> {
+        let mut seen = BTreeSet::new();
+        let mut stack = self.workspace.dependencies_of(member);
+
+        while let Some(dep) = stack.pop() {
+            if seen.insert(dep.dir.clone()) {
+                stack.extend(self.workspace.dependencies_of(dep));
+            }
+        }
+
+        seen
+    }
+
+    fn summary(reports: &[Report]) -> String {
+        let width = reports
+            .iter()
+            .map(|r| r.name.len())
+            .chain(["Package".len()])
+            .max()
+            .unwrap_or(0);
+
+        let mut lines = vec![format!(
+            "{:width$}  {:8}  {:>4}  {:>8}",
+            "Package", "Status", "Exit", "Duration"
+        )];
+
+        for report in reports {
+            let (status, code) = match &report.outcome {
+                Outcome::Success => ("ok", "0".to_string()),
+                Outcome::Failed(code) => (
+                    "failed",
+                    code.map_or_else(|| "-".to_string(), |c| c.to_string()),
+                ),
+                Outcome::Skipped => ("skipped", "-".to_string()),
+            };
+            let duration = match report.outcome {
+                Outcome::Skipped => "-".to_string(),
+                _ => format!("{:.2}s", report.duration.as_secs_f64()),
+            };
+
+            lines.push(format!(
+                "{:width$}  {:8}  {:>4}  {:>8}",
+                report.name, status, code, duration
+            ));
+        }
+
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_family = "unix")]
+    fn write_package(dir: &Path, json: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("package.json"), json).unwrap();
+    }
+
+    #[cfg(target_family = "unix")]
+    /// core <- ui <- web, each appending its name to `order` when built
+    async fn fixture(fail_core: bool) -> (tempfile::TempDir, Workspace) {
+        let root = tempfile::tempdir().unwrap();
+        write_package(
+            root.path(),
+            r#"{"name": "root", "workspaces": ["packages/*"]}"#,
+        );
+
+        let core_script = if fail_core {
+            "exit 2"
+        } else {
+            "sleep 0.2 && echo core >> ../../order"
+        };
+        write_package(
+            &root.path().join("packages/core"),
+            &format!(
+                r#"{{"name": "core", "version": "1.0.0", "scripts": {{"build": "{core_script}"}}}}"#
+            ),
+        );
+        write_package(
+            &root.path().join("packages/ui"),
+            r#"{"name": "ui", "version": "1.0.0", "dependencies": {"core": "workspace:*"}, "scripts": {"build": "echo ui >> ../../order"}}"#,
+        );
+        // No build script, web still has to wait for ui through it
+        write_package(
+            &root.path().join("packages/glue"),
+            r#"{"name": "glue", "version": "1.0.0", "dependencies": {"ui": "workspace:*"}}"#,
+        );
+        write_package(
+            &root.path().join("packages/web"),
+            r#"{"name": "web", "version": "1.0.0", "dependencies": {"glue": "workspace:*"}, "scripts": {"build": "echo web >> ../../order"}}"#,
+        );
+
+        let workspace = Workspace::load(root.path()).await.unwrap().unwrap();
+        (root, workspace)
+    }
+
+    #[cfg(target_family = "unix")]
+    fn all_dirs(workspace: &Workspace) -> Vec<PathBuf> {
+        workspace.packages.iter().map(|p| p.dir.clone()).collect()
+    }
+
+    #[cfg(target_family = "unix")]
+    #[tokio::test]
+    async fn test_runs_in_dependency_order() {
+        let (root, workspace) = fixture(false).await;
+        let options = RecursiveOptions {
+            grouped: true,
+            ..Default::default()
+        };
+
+        WorkspaceRun::new(&workspace, "build", &[], &options)
+            .run(&all_dirs(&workspace))
+            .await
+            .unwrap();
+
+        let order = std::fs::read_to_string(root.path().join("order")).unwrap();
+        assert_eq!(order.lines().collect::<Vec<_>>(), vec!["core", "ui", "web"]);
+    }
+
+    #[cfg(target_family = "unix")]
+    #[tokio::test]
+    async fn test_bail_skips_dependents() {
+        let (root, workspace) = fixture(true).await;
+        let options = RecursiveOptions {
+            grouped: true,
+            ..Default::default()
+        };
+
+        let result = WorkspaceRun::new(&workspace, "build", &[], &options)
+            .run(&all_dirs(&workspace))
+            .await;
+
+        assert!(result.is_err());
+        assert!(!root.path().join("order").exists());
+    }
+
+    #[cfg(target_family = "unix")]
+    #[tokio::test]
+    async fn test_no_bail_runs_everything() {
+        let (root, workspace) = fixture(true).await;
+        let options = RecursiveOptions {
+            bail: false,
+            grouped: true,
+            ..Default::default()
+        };
+
+        let result = WorkspaceRun::new(&workspace, "build", &[], &options)
+            .run(&all_dirs(&workspace))
+            .await;
+
+        assert!(result.is_err());
+        let order = std::fs::read_to_string(root.path().join("order")).unwrap();
+        assert_eq!(order.lines().collect::<Vec<_>>(), vec!["ui", "web"]);
+    }
+
+    #[test]
+    fn test_summary_table() {
+        let reports = vec![
+            Report {
+                name: "core".to_string(),
+                outcome: Outcome::Success,
+                duration: Duration::from_millis(1500),
+            },
+            Report {
+                name: "web".to_string(),
+                outcome: Outcome::Failed(Some(1)),
+                duration: Duration::from_millis(250),
+            },
+            Report {
+                name: "docs".to_string(),
+                outcome: Outcome::Skipped,
+                duration: Duration::ZERO,
+            },
+        ];
+
+        let summary = WorkspaceRun::summary(&reports);
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines[0], "Package  Status    Exit  Duration");
+        assert_eq!(lines[1], "core     ok           0     1.50s");
+        assert_eq!(lines[2], "web      failed       1     0.25s");
+        assert_eq!(lines[3], "docs     skipped      -         -");
+    }
+}
@@ -1,4 +1,5 @@
 use std::fmt::Display;
+use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 
@@ -11,6 +12,15 @@ pub struct Cli {
 
     #[arg(short, long, global = true)]
     pub verbose: bool,
+
+    /// Run the command in the workspace packages matching the selector
+    /// (name, `./dir/**` glob, `pkg...` dependencies, `...pkg` dependents)
+    #[arg(short = 'F', long, global = true)]
+    pub filter: Vec<String>,
+
+    /// Include the workspace root in the selected packages
+    #[arg(short = 'w', long, global = true)]
+    pub workspace_root: bool,
 }
 
 impl Display for Cli {
@@ -19,7 +29,7 @@ impl Display for Cli {
     }
 }
 
-#[derive(Subcommand, Debug, PartialEq)]
+#[derive(Subcommand, Debug, PartialEq, Clone)]
 pub enum Commands {
     Add {
         #[arg(required = true)]
@@ -31,11 +41,153 @@ pub enum Commands {
         #[arg(required = true)]
         packages: Vec<String>,
     },
+    /// Update dependencies within their ranges, or to the latest version with --latest
+    #[command(visible_alias = "upgrade", visible_alias = "up")]
+    Update {
+        /// Dependencies to update, globs like `@types/*` work too (all when empty)
+        packages: Vec<String>,
+        /// Move ranges in package.json to the latest version, keeping their ^/~ style
+        #[arg(short = 'L', long)]
+        latest: bool,
+        /// Choose the dependencies to update from a list of outdated ones
+        #[arg(short, long)]
+        interactive: bool,
+    },
+    /// List dependencies with newer versions, exiting with 1 when there are any
+    Outdated {
+        /// Dependencies to check, globs like `@types/*` work too (all when empty)
+        packages: Vec<String>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the tree of installed dependencies
+    #[command(visible_alias = "ls")]
+    List {
+        /// Levels of transitive dependencies to show, 0 for direct dependencies only
+        #[arg(long, default_value_t = 0)]
+        depth: usize,
+        /// Only list dependencies
+        #[arg(short = 'P', long, conflicts_with = "dev")]
+        prod: bool,
+        /// Only list devDependencies
+        #[arg(short = 'D', long)]
+        dev: bool,
+        /// Print the tree as JSON
+        #[arg(long, conflicts_with = "parseable")]
+        json: bool,
+        /// Print the paths of installed packages, one per line
+        #[arg(long)]
+        parseable: bool,
+    },
+    /// Export the resolved dependency graph
+    Graph {
+        #[arg(long, default_value = "dot", value_parser = ["dot", "mermaid", "json"])]
+        format: String,
+        /// Only export the graph below this direct dependency
+        #[arg(long)]
+        root: Option<String>,
+    },
+    /// Show the dependency chains that pull in a package
+    Why {
+        /// Package name, optionally with a range (`scheduler@^0.23.0`)
+        package: String,
+    },
+    /// Check locked packages against the registry's security advisories
+    Audit {
+        #[command(subcommand)]
+        command: Option<AuditCommands>,
+        /// Only report advisories of this severity or higher
+        #[arg(long, value_parser = ["info", "low", "moderate", "high", "critical"])]
+        audit_level: Option<String>,
+        /// Print the report as JSON
+        #[arg(long, conflicts_with = "sarif")]
+        json: bool,
+        /// Print the report as a SARIF log for code scanning
+        #[arg(long)]
+        sarif: bool,
+    },
+    /// Pack the package into a tarball, as it would be published
+    Pack {
+        /// List the files that would be packed without writing the tarball
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Publish the package to the registry
+    Publish {
+        /// Dist-tag pointing to the published version
+        #[arg(long)]
+        tag: Option<String>,
+        /// Who can install a scoped package
+        #[arg(long, value_parser = ["public", "restricted"])]
+        access: Option<String>,
+        /// One-time password from your authenticator
+        #[arg(long)]
+        otp: Option<String>,
+    },
+    /// Serve the package cache as an npm-compatible registry
+    Serve {
+        /// Address to listen on, 0.0.0.0 for other machines
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        #[arg(short, long, default_value_t = 4873)]
+        port: u16,
+        /// Registry to fetch packages missing from the cache from
+        #[arg(long)]
+        upstream: Option<String>,
+        /// Token publishing takes (`_authToken` in the publisher's .craftrc),
+        /// publishing is refused without one
+        #[arg(long, env = "CRAFT_SERVE_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+    },
+    /// Extract an installed package to a temporary directory to edit it
+    Patch {
+        /// Package name, optionally with a version (`lodash@4.17.21`)
+        package: String,
+    },
+    /// Save the edits made after `craft patch` as a patch applied on install
+    PatchCommit {
+        /// Directory printed by `craft patch`
+        dir: PathBuf,
+    },
     Run {
+        /// Run the script in every workspace package, in dependency order
+        #[arg(short, long)]
+        recursive: bool,
+        /// With -r, keep running the remaining packages after a failure
+        #[arg(long, requires = "recursive")]
+        no_bail: bool,
+        /// With -r, how many scripts may run at the same time
+        #[arg(long, default_value_t = 4, requires = "recursive")]
+        workspace_concurrency: usize,
         script: String,
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         args: Vec<String>,
     },
+    /// Run a package binary, installing the package temporarily if it isn't a dependency
+    Exec {
+        /// Package(s) to install, the command is then the binary to run
+        #[arg(short, long)]
+        package: Vec<String>,
+        command: String,
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Run a package binary from a temporary install, ignoring local dependencies
+    Dlx {
+        /// Package(s) to install, the command is then the binary to run
+        #[arg(short, long)]
+        package: Vec<String>,
+        command: String,
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Run a `create-<name>` starter kit, e.g. `craft create vite my-app`
+    Create {
+        name: String,
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
     Cache {
         #[command(subcommand)]
         command: CacheCommands,
@@ -55,12 +207,58 @@ pub enum CacheCommands {
     },
 }
 
+#[derive(Subcommand, Debug, PartialEq, Clone)]
+pub enum AuditCommands {
+    /// Upgrade vulnerable packages to the lowest versions fixing them
+    Fix {
+        /// Print the changes without making them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
 impl Display for Commands {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
     }
 }
 
+impl Commands {
+    /// A plain, non-recursive script run
+    pub fn run(script: &str, args: Vec<String>) -> Self {
+        Self::Run {
+            recursive: false,
+            no_bail: false,
+            workspace_concurrency: 4,
+            script: script.to_string(),
+            args,
+        }
+    }
+
+    /// `craft create <name>` as the `create-<name>` package it runs, like npm init:
+    /// - `vite@5` -> `create-vite@5`
+    /// - `@scope` -> `@scope/create`
+    /// - `@scope/app` -> `@scope/create-app`
+    pub fn create(name: &str, args: Vec<String>) -> Self {
+        let (name, version) = match name.get(1..).and_then(|rest| rest.find('@')) {
+            Some(at) => name.split_at(at + 1),
+            None => (name, ""),
+        };
+
+        let package = match name.split_once('/') {
+            Some((scope, app)) => format!("{}/create-{}", scope, app),
+            None if name.starts_with('@') => format!("{}/create", name),
+            None => format!("create-{}", name),
+        };
+
+        Self::Dlx {
+            package: vec![],
+            command: format!("{}{}", package, version),
+            args,
+        }
+    }
+}
+
 impl Cli {
     pub fn parse_args() -> Self {
         Self::parse()
@@ -76,25 +274,30 @@ impl Cli {
                 Commands::Remove { packages } => Commands::Remove {
                     packages: packages.clone(),
                 },
-                Commands::Run { script, args } => Commands::Run {
-                    script: script.clone(),
-                    args: args.clone(),
-                },
+                Commands::Update { .. }
+                | Commands::Outdated { .. }
+                | Commands::List { .. }
+                | Commands::Graph { .. }
+                | Commands::Why { .. }
+                | Commands::Audit { .. }
+                | Commands::Pack { .. }
+                | Commands::Publish { .. }
+                | Commands::Serve { .. }
+                | Commands::Patch { .. }
+                | Commands::PatchCommit { .. }
+                | Commands::Run { .. }
+                | Commands::Exec { .. }
+                | Commands::Dlx { .. } => cmd.clone(),
+                Commands::Create { name, args } => Commands::create(name, args.clone()),
                 Commands::Cache { command } => Commands::Cache {
                     command: command.clone(),
                 },
                 Commands::Start => Commands::Start,
-                Commands::Test => Commands::Run {
-                    script: "test".to_string(),
-                    args: vec![],
-                },
+                Commands::Test => Commands::run("test", vec![]),
                 Commands::Install => Commands::Install,
                 Commands::External(args) => {
                     if let Some(script) = args.first() {
-                        Commands::Run {
-                            script: script.clone(),
-                            args: args[1..].to_vec(),
-                        }
+                        Commands::run(script, args[1..].to_vec())
                     } else {
                         Commands::Install
                     }
@@ -144,11 +347,194 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_update_packages() {
+        let cli = Cli::parse_from(["craft", "upgrade", "--latest", "@types/*", "react"]);
+        assert_eq!(
+            cli.normalize(),
+            Commands::Update {
+                packages: vec!["@types/*".to_string(), "react".to_string()],
+                latest: true,
+                interactive: false,
+            }
+        );
+
+        let cli = Cli::parse_from(["craft", "up", "-i"]);
+        assert_eq!(
+            cli.command,
+            Some(Commands::Update {
+                packages: vec![],
+                latest: false,
+                interactive: true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_outdated_json() {
+        let cli = Cli::parse_from(["craft", "outdated", "--json", "react"]);
+        assert_eq!(
+            cli.normalize(),
+            Commands::Outdated {
+                packages: vec!["react".to_string()],
+                json: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_list_options() {
+        let cli = Cli::parse_from(["craft", "ls", "--depth", "2", "--prod", "--json"]);
+        assert_eq!(
+            cli.normalize(),
+            Commands::List {
+                depth: 2,
+                prod: true,
+                dev: false,
+                json: true,
+                parseable: false,
+            }
+        );
+
+        assert!(Cli::try_parse_from(["craft", "list", "-P", "-D"]).is_err());
+        assert!(Cli::try_parse_from(["craft", "list", "--json", "--parseable"]).is_err());
+    }
+
+    #[test]
+    fn test_graph_format() {
+        let cli = Cli::parse_from(["craft", "graph", "--format", "mermaid", "--root", "react"]);
+        assert_eq!(
+            cli.normalize(),
+            Commands::Graph {
+                format: "mermaid".to_string(),
+                root: Some("react".to_string()),
+            }
+        );
+
+        assert!(Cli::try_parse_from(["craft", "graph", "--format", "svg"]).is_err());
+    }
+
+    #[test]
+    fn test_why_package() {
+        let cli = Cli::parse_from(["craft", "why", "@babel/core@^7.0.0"]);
+        assert_eq!(
+            cli.normalize(),
+            Commands::Why {
+                package: "@babel/core@^7.0.0".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_audit_options() {
+        let cli = Cli::parse_from(["craft", "audit", "--audit-level", "high", "--sarif"]);
+        assert_eq!(
+            cli.normalize(),
+            Commands::Audit {
+                command: None,
+                audit_level: Some("high".to_string()),
+                json: false,
+                sarif: true,
+            }
+        );
+
+        assert!(Cli::try_parse_from(["craft", "audit", "--audit-level", "severe"]).is_err());
+        assert!(Cli::try_parse_from(["craft", "audit", "--json", "--sarif"]).is_err());
+
+        let cli = Cli::parse_from(["craft", "audit", "fix", "--dry-run"]);
+        assert_eq!(
+            cli.command,
+            Some(Commands::Audit {
+                command: Some(AuditCommands::Fix { dry_run: true }),
+                audit_level: None,
+                json: false,
+                sarif: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_pack_dry_run() {
+        let cli = Cli::parse_from(["craft", "pack", "--dry-run"]);
+        assert_eq!(cli.normalize(), Commands::Pack { dry_run: true });
+    }
+
+    #[test]
+    fn test_publish_options() {
+        let cli = Cli::parse_from([
+            "craft", "publish", "--tag", "next", "--access", "public", "--otp", "123456",
+        ]);
+        assert_eq!(
+            cli.normalize(),
+            Commands::Publish {
+                tag: Some("next".to_string()),
+                access: Some("public".to_string()),
+                otp: Some("123456".to_string()),
+            }
+        );
+
+        assert!(Cli::try_parse_from(["craft", "publish", "--access", "private"]).is_err());
+    }
+
+    #[test]
+    fn test_serve_options() {
+        // The token defaults to CRAFT_SERVE_TOKEN, which the environment may set
+        let cli = Cli::parse_from(["craft", "serve"]);
+        assert!(matches!(
+            cli.normalize(),
+            Commands::Serve {
+                host,
+                port: 4873,
+                upstream: None,
+                ..
+            } if host == "127.0.0.1"
+        ));
+
+        let cli = Cli::parse_from([
+            "craft",
+            "serve",
+            "-p",
+            "8080",
+            "--upstream",
+            "https://registry.npmjs.org",
+            "--token",
+            "secret",
+        ]);
+        assert_eq!(
+            cli.normalize(),
+            Commands::Serve {
+                host: "127.0.0.1".to_string(),
+                port: 8080,
+                upstream: Some("https://registry.npmjs.org".to_string()),
+                token: Some("secret".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_patch_commands() {
+        let cli = Cli::parse_from(["craft", "patch", "lodash@4.17.21"]);
+        assert_eq!(
+            cli.normalize(),
+            Commands::Patch {
+                package: "lodash@4.17.21".to_string(),
+            }
+        );
+
+        let cli = Cli::parse_from(["craft", "patch-commit", "/tmp/craft-patch-1/package"]);
+        assert_eq!(
+            cli.normalize(),
+            Commands::PatchCommit {
+                dir: PathBuf::from("/tmp/craft-patch-1/package"),
+            }
+        );
+    }
+
     #[test]
     fn test_run_script() {
         let cli = Cli::parse_from(["craft", "run", "build"]);
         match cli.command {
-            Some(Commands::Run { script, args }) => {
+            Some(Commands::Run { script, args, .. }) => {
                 assert_eq!(script, "build");
                 assert!(args.is_empty());
             }
@@ -156,6 +542,78 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_run_recursive() {
+        let cli = Cli::parse_from([
+            "craft",
+            "run",
+            "-r",
+            "--no-bail",
+            "--workspace-concurrency",
+            "2",
+            "build",
+            "--watch",
+        ]);
+        assert_eq!(
+            cli.command,
+            Some(Commands::Run {
+                recursive: true,
+                no_bail: true,
+                workspace_concurrency: 2,
+                script: "build".to_string(),
+                args: vec!["--watch".to_string()],
+            })
+        );
+
+        assert!(Cli::try_parse_from(["craft", "run", "--no-bail", "build"]).is_err());
+    }
+
+    #[test]
+    fn test_dlx_with_package() {
+        let cli = Cli::parse_from([
+            "craft",
+            "dlx",
+            "--package",
+            "@angular/cli",
+            "ng",
+            "new",
+            "--skip-git",
+        ]);
+        assert_eq!(
+            cli.command,
+            Some(Commands::Dlx {
+                package: vec!["@angular/cli".to_string()],
+                command: "ng".to_string(),
+                args: vec!["new".to_string(), "--skip-git".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn test_create_normalizes_to_dlx() {
+        let cli = Cli::parse_from(["craft", "create", "vite@5", "my-app", "--template", "react"]);
+        assert_eq!(
+            cli.normalize(),
+            Commands::Dlx {
+                package: vec![],
+                command: "create-vite@5".to_string(),
+                args: vec![
+                    "my-app".to_string(),
+                    "--template".to_string(),
+                    "react".to_string()
+                ],
+            }
+        );
+
+        let command = |name: &str| match Commands::create(name, vec![]) {
+            Commands::Dlx { command, .. } => command,
+            _ => unreachable!(),
+        };
+        assert_eq!(command("vite"), "create-vite");
+        assert_eq!(command("@acme"), "@acme/create");
+        assert_eq!(command("@acme/app@1.0.0"), "@acme/create-app@1.0.0");
+    }
+
     #[test]
     fn test_test_command() {
         let cli = Cli::parse_from(["craft", "test"]);
@@ -164,7 +622,7 @@ mod tests {
         // Verify normalization
         let normalized = cli.normalize();
         match normalized {
-            Commands::Run { script, args } => {
+            Commands::Run { script, args, .. } => {
                 assert_eq!(script, "test");
                 assert!(args.is_empty());
             }
@@ -195,7 +653,7 @@ mod tests {
         // Verify normalization
         let normalized = cli.normalize();
         match normalized {
-            Commands::Run { script, args } => {
+            Commands::Run { script, args, .. } => {
                 assert_eq!(script, "tsc");
                 assert!(args.is_empty());
             }
@@ -220,7 +678,7 @@ mod tests {
         // Verify normalization
         let normalized = cli.normalize();
         match normalized {
-            Commands::Run { script, args } => {
+            Commands::Run { script, args, .. } => {
                 assert_eq!(script, "tsc");
                 assert_eq!(args, vec!["--version", "--help"]);
             }
@@ -228,6 +686,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_filter_flags() {
+        let cli = Cli::parse_from([
+            "craft",
+            "--filter",
+            "@acme/web",
+            "-F",
+            "./apps/**",
+            "add",
+            "zod",
+            "-w",
+        ]);
+        assert_eq!(cli.filter, vec!["@acme/web", "./apps/**"]);
+        assert!(cli.workspace_root);
+        assert_eq!(
+            cli.normalize(),
+            Commands::Add {
+                packages: vec!["zod".to_string()],
+                dev: false,
+            }
+        );
+
+        // Like --verbose, the filter is recognised after the subcommand too
+        let cli = Cli::parse_from(["craft", "run", "build", "--filter", "...^core"]);
+        assert_eq!(cli.filter, vec!["...^core"]);
+        match cli.command {
+            Some(Commands::Run { script, args, .. }) => {
+                assert_eq!(script, "build");
+                assert!(args.is_empty());
+            }
+            _ => panic!("Expected Run command"),
+        }
+    }
+
     #[test]
     fn test_default_command() {
         let cli = Cli::parse_from(["craft"]);
@@ -0,0 +1,395 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use contract::{DEFAULT_REGISTRY, Result};
+
+/// Config file read from the home directory and from the project's directories
+pub const CONFIG_FILE_NAME: &str = ".craftrc";
+
+/// Settings from `.craftrc` files, in the npmrc format:
+///
+/// ```ini
+/// registry=https://registry.example.com
+/// ; lists repeat a key ending in []
+/// audit-ignore[]=GHSA-29mw-wpgm-hmr9 until 2026-12-31
+/// ```
+///
+/// Files closer to the project override the ones above them, and `~/.craftrc`
+/// has the lowest precedence. Lists are combined across files.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Config {
+    values: BTreeMap<String, String>,
+    lists: BTreeMap<String, Vec<String>>,
+    /// Directory of the file each value was read from
+    dirs: BTreeMap<String, PathBuf>,
+}
+
+/// An advisory `craft audit` doesn't report, until an optional `YYYY-MM-DD` date
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditIgnore {
+    pub id: String,
+    pub until: Option<String>,
+}
+
+impl AuditIgnore {
+    /// `GHSA-…` or `GHSA-… until 2026-12-31`
+    pub fn parse(value: &str) -> Result<Self> {
+        let (id, until) = match value.split_once(" until ") {
+            Some((id, until)) => (id.trim(), Some(until.trim())),
+            None => (value.trim(), None),
+        };
+
+        if let Some(until) = until {
+            let is_date = until.len() == 10
+                && until.char_indices().all(|(i, c)| match i {
+                    4 | 7 => c == '-',
+                    _ => c.is_ascii_digit(),
+                });
+            if !is_date {
+                anyhow::bail!(
+                    "Invalid audit-ignore date {:?} for {}, expected YYYY-MM-DD",
+                    until,
+                    id
+                );
+            }
+        }
+
+        Ok(Self {
+            id: id.to_string(),
+            until: until.map(str::to_string),
+        })
+    }
+
+    /// Whether the ignore ran out before `today` (`YYYY-MM-DD`)
+    pub fn is_expired(&self, today: &str) -> bool {
+        self.until.as_deref().is_some_and(|until| until < today)
+    }
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse the contents of one file
+    pub fn parse(contents: &str) -> Self {
+        let mut config = Self::new();
+
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+
+            match key.trim().strip_suffix("[]") {
+                Some(key) => config
+                    .lists
+                    .entry(key.to_string())
+                    .or_default()
+                    .push(value.to_string()),
+                None => {
+                    config
+                        .values
+                        .insert(key.trim().to_string(), value.to_string());
+                }
+            }
+        }
+
+        config
+    }
+
+    /// Values are read from the file in `dir`, so paths in them are relative to it
+    pub fn with_dir(mut self, dir: &Path) -> Self {
+        self.dirs = self
+            .values
+            .keys()
+            .map(|key| (key.clone(), dir.to_path_buf()))
+            .collect();
+        self
+    }
+
+    /// Settings of `other` take precedence over the ones of `self`
+    pub fn merge(mut self, other: Self) -> Self {
+        for key in other.values.keys() {
+            match other.dirs.get(key) {
+                Some(dir) => self.dirs.insert(key.clone(), dir.clone()),
+                None => self.dirs.remove(key),
+            };
+        }
+        self.values.extend(other.values);
+        for (key, values) in other.lists {
+            self.lists.entry(key).or_default().extend(values);
+        }
+        self
+    }
+
+    /// `~/.craftrc` and every `.craftrc` from the filesystem root down to `cwd`
+    pub async fn load(cwd: &Path) -> Result<Self> {
+        let home = std::env::var("HOME")
+            .or_else(|_| std::env::var("USERPROFILE"))
+            .ok()
+            .map(PathBuf::from);
+
+        let mut dirs: Vec<&Path> = cwd.ancestors().collect();
+        dirs.reverse();
+        // The home directory comes first, unless it's one of the project's ancestors
+        if let Some(home) = home.as_deref().filter(|home| !dirs.contains(home)) {
+            dirs.insert(0, home);
+        }
+
+        let mut config = Self::new();
+        for dir in dirs {
+            match tokio::fs::read_to_string(dir.join(CONFIG_FILE_NAME)).await {
+                Ok(contents) => config = config.merge(Self::parse(&contents).with_dir(dir)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    pub fn get_all(&self, key: &str) -> &[String] {
+        self.lists.get(key).map_or(&[], Vec::as_slice)
+    }
+
+    /// Registry packages are resolved from, without a trailing slash
+    pub fn registry(&self) -> &str {
+        self.get("registry")
+            .unwrap_or(DEFAULT_REGISTRY)
+            .trim_end_matches('/')
+    }
+
+    /// Directory downloaded tarballs are copied to, and installs read before
+    /// the network, relative to the `.craftrc` that set it
+    pub fn offline_mirror(&self) -> Option<PathBuf> {
+        let mirror = self.get("offline-mirror").filter(|dir| !dir.is_empty())?;
+        match self.dirs.get("offline-mirror") {
+            Some(dir) => Some(dir.join(mirror)),
+            None => Some(PathBuf::from(mirror)),
+        }
+    }
+
+    /// Whether installs only use the offline mirror, `offline=true`
+    pub fn offline(&self) -> bool {
+        self.get("offline") == Some("true")
+    }
+
+    /// Endpoint `craft audit` posts the locked packages to
+    pub fn audit_url(&self) -> String {
+        match self.get("audit-url") {
+            Some(url) => url.to_string(),
+            None => format!("{}/-/npm/v1/security/advisories/bulk", self.registry()),
+        }
+    }
+
+    /// Token for `registry` from its `//host/path/:_authToken` setting, the
+    /// longest matching path winning, with `${VAR}` replaced by the environment
+    pub fn auth_token(&self, registry: &str) -> Option<String> {
+        let without_scheme = registry
+            .split_once("//")
+            .map_or(registry, |(_, rest)| rest)
+            .trim_end_matches('/');
+
+        let mut path = without_scheme;
+        loop {
+            if let Some(token) = self.get(&format!("//{}/:_authToken", path)) {
+                return Some(expand_env(token, |name| std::env::var(name).ok()));
+            }
+            path = path.rsplit_once('/')?.0;
+        }
+    }
+
+    pub fn audit_ignores(&self) -> Result<Vec<AuditIgnore>> {
+        self.get_all("audit-ignore")
+            .iter()
+            .map(|value| AuditIgnore::parse(value))
+            .collect()
+    }
+}
+
+/// Replace every `${VAR}` in `value` with `lookup(VAR)`, or nothing when it's unset
+fn expand_env(value: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut expanded = String::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&lookup(&rest[start + 2..start + 2 + len]).unwrap_or_default());
+        rest = &rest[start + 3 + len..];
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse(
+            "# comment\n\
+             registry = https://registry.example.com/\n\
+             audit-level=\"high\"\n\
+             audit-ignore[]=GHSA-1\n\
+             audit-ignore[]=GHSA-2 until 2026-01-31\n",
+        );
+
+        assert_eq!(config.registry(), "https://registry.example.com");
+        assert_eq!(config.get("audit-level"), Some("high"));
+        assert_eq!(
+            config.audit_url(),
+            "https://registry.example.com/-/npm/v1/security/advisories/bulk"
+        );
+        assert_eq!(
+            config.audit_ignores().unwrap(),
+            vec![
+                AuditIgnore {
+                    id: "GHSA-1".to_string(),
+                    until: None
+                },
+                AuditIgnore {
+                    id: "GHSA-2".to_string(),
+                    until: Some("2026-01-31".to_string())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_offline_mirror() {
+        let root = Path::new("/project");
+        assert_eq!(Config::new().offline_mirror(), None);
+        assert_eq!(
+            Config::parse("offline-mirror = ./vendor/npm")
+                .with_dir(root)
+                .offline_mirror(),
+            Some(PathBuf::from("/project/./vendor/npm"))
+        );
+        assert_eq!(
+            Config::parse("offline-mirror=/srv/mirror")
+                .with_dir(root)
+                .offline_mirror(),
+            Some(PathBuf::from("/srv/mirror"))
+        );
+        assert!(!Config::new().offline());
+        assert!(Config::parse("offline=true").offline());
+    }
+
+    #[test]
+    fn test_auth_token() {
+        let config = Config::parse(
+            "//registry.example.com/:_authToken=host-token\n\
+             //registry.example.com/team/:_authToken=team-token\n",
+        );
+
+        assert_eq!(
+            config.auth_token("https://registry.example.com").as_deref(),
+            Some("host-token")
+        );
+        assert_eq!(
+            config
+                .auth_token("https://registry.example.com/team/npm/")
+                .as_deref(),
+            Some("team-token")
+        );
+        assert_eq!(config.auth_token("https://other.example.com"), None);
+    }
+
+    #[test]
+    fn test_expand_env() {
+        let lookup = |name: &str| (name == "NPM_TOKEN").then(|| "secret".to_string());
+        assert_eq!(expand_env("${NPM_TOKEN}", lookup), "secret");
+        assert_eq!(expand_env("a-${UNSET}-b", lookup), "a--b");
+        assert_eq!(expand_env("${NPM_TOKEN", lookup), "${NPM_TOKEN");
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = Config::new();
+        assert_eq!(config.registry(), DEFAULT_REGISTRY);
+        assert!(config.get_all("audit-ignore").is_empty());
+    }
+
+    #[test]
+    fn test_merge() {
+        let user = Config::parse("registry=https://a.example\naudit-ignore[]=GHSA-1");
+        let project = Config::parse("registry=https://b.example\naudit-ignore[]=GHSA-2");
+        let config = user.merge(project);
+
+        assert_eq!(config.registry(), "https://b.example");
+        assert_eq!(config.get_all("audit-ignore"), ["GHSA-1", "GHSA-2"]);
+    }
+
+    #[test]
+    fn test_audit_ignore() {
+        let ignore = AuditIgnore::parse("GHSA-1 until 2026-01-31").unwrap();
+        assert!(!ignore.is_expired("2026-01-31"));
+        assert!(ignore.is_expired("2026-02-01"));
+        assert!(
+            !AuditIgnore::parse("GHSA-1")
+                .unwrap()
+                .is_expired("2099-01-01")
+        );
+        assert!(AuditIgnore::parse("GHSA-1 until next year").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_load_nearest_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = dir.path().join("apps/web");
+        std::fs::create_dir_all(&app).unwrap();
+        std::fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "registry=https://root.example\naudit-level=low",
+        )
+        .unwrap();
+        std::fs::write(app.join(CONFIG_FILE_NAME), "registry=https://web.example").unwrap();
+
+        let config = Config::load(&app).await.unwrap();
+        assert_eq!(config.registry(), "https://web.example");
+        assert_eq!(config.get("audit-level"), Some("low"));
+    }
+
+    #[tokio::test]
+    async fn test_load_offline_mirror_from_its_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = dir.path().join("apps/web");
+        std::fs::create_dir_all(&app).unwrap();
+        std::fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "offline-mirror=./vendor/npm",
+        )
+        .unwrap();
+        std::fs::write(app.join(CONFIG_FILE_NAME), "audit-level=low").unwrap();
+
+        let config = Config::load(&app).await.unwrap();
+        assert_eq!(
+            config.offline_mirror(),
+            Some(dir.path().join("./vendor/npm"))
+        );
+
+        // A closer file setting it again takes over, with its own directory
+        std::fs::write(app.join(CONFIG_FILE_NAME), "offline-mirror=mirror").unwrap();
+        let config = Config::load(&app).await.unwrap();
+        assert_eq!(config.offline_mirror(), Some(app.join("mirror")));
+    }
+}
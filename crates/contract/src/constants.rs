@@ -2,6 +2,12 @@ use std::path::PathBuf;
 
 pub const CRAFT_VERBOSE: &str = "CRAFT_VERBOSE";
 
+/// Registry packages are resolved from
+pub const DEFAULT_REGISTRY: &str = "https://registry.npmjs.org";
+
+/// Lockfile written next to package.json
+pub const LOCKFILE_NAME: &str = "craft.lock";
+
 /// Get the package cache directory path
 /// - macOS/Linux: ~/.craft/packages
 /// - Windows: %USERPROFILE%\.craft\packages
@@ -13,6 +19,16 @@ pub fn get_package_cache_dir() -> PathBuf {
     PathBuf::from(home).join(".craft").join("packages")
 }
 
+/// Packages published to `craft serve`, next to the package cache
+pub fn get_registry_storage_dir() -> PathBuf {
+    get_package_cache_dir().with_file_name("registry")
+}
+
+/// Install prefixes of packages run with `craft dlx`, next to the package cache
+pub fn get_dlx_cache_dir() -> PathBuf {
+    get_package_cache_dir().with_file_name("dlx")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
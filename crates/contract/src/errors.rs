@@ -16,6 +16,12 @@ pub enum PipelineError {
 
     #[error("Download error: {message}")]
     DownloadError { message: String },
+
+    #[error("Script error: {message}")]
+    ScriptError { message: String },
+
+    #[error("Patch error: {message}")]
+    PatchError { message: String },
 }
 
 #[derive(Error, Debug)]
@@ -23,3 +29,80 @@ pub enum PackageError {
     #[error("no package.json found")]
     NoPackageJson,
 }
+
+/// Exit with `code` without an error message, the command already printed its result
+/// (e.g. `craft outdated` finding outdated dependencies)
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("exit code {code}")]
+pub struct SilentExit {
+    pub code: i32,
+}
+
+/// A child process exited unsuccessfully; craft exits with the same status
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("{}", self.describe())]
+pub struct ProcessError {
+    pub command: String,
+    pub code: Option<i32>,
+    pub signal: Option<i32>,
+}
+
+impl ProcessError {
+    pub fn from_status(command: &str, status: &std::process::ExitStatus) -> Self {
+        #[cfg(unix)]
+        let signal = std::os::unix::process::ExitStatusExt::signal(status);
+        #[cfg(not(unix))]
+        let signal = None;
+
+        Self {
+            command: command.to_string(),
+            code: status.code(),
+            signal,
+        }
+    }
+
+    /// Exit code for craft itself, `128 + signal` for killed children like a shell
+    pub fn exit_code(&self) -> i32 {
+        match (self.code, self.signal) {
+            (Some(code), _) => code,
+            (None, Some(signal)) => 128 + signal,
+            (None, None) => 1,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match (self.code, self.signal) {
+            (Some(code), _) => format!("Command failed with exit code {}: {}", code, self.command),
+            (None, Some(signal)) => {
+                format!("Command terminated by signal {}: {}", signal, self.command)
+            }
+            (None, None) => format!("Command failed: {}", self.command),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_error_exit_code() {
+        let exited = ProcessError {
+            command: "eslint".to_string(),
+            code: Some(2),
+            signal: None,
+        };
+        assert_eq!(exited.exit_code(), 2);
+        assert_eq!(
+            exited.to_string(),
+            "Command failed with exit code 2: eslint"
+        );
+
+        let killed = ProcessError {
+            command: "node server.js".to_string(),
+            code: None,
+            signal: Some(2),
+        };
+        assert_eq!(killed.exit_code(), 130);
+    }
+}
@@ -5,6 +5,9 @@ mod pipeline;
 
 pub use actor::Actor;
 pub use anyhow::Result;
-pub use constants::{CRAFT_VERBOSE, get_package_cache_dir};
-pub use errors::{PackageError, PipelineError};
+pub use constants::{
+    CRAFT_VERBOSE, DEFAULT_REGISTRY, LOCKFILE_NAME, get_dlx_cache_dir, get_package_cache_dir,
+    get_registry_storage_dir,
+};
+pub use errors::{PackageError, PipelineError, ProcessError, SilentExit};
 pub use pipeline::Pipeline;
@@ -0,0 +1,239 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use node_semver::{Range, Version};
+
+use crate::{Lockfile, PackageEntry};
+
+/// A dependency declared as `name: range`, and the locked package it resolves to
+#[derive(Debug, Clone)]
+pub struct Edge<'a> {
+    pub name: String,
+    pub range: String,
+    pub entry: &'a PackageEntry,
+}
+
+/// The resolved dependency graph recorded in a lockfile.
+///
+/// The lockfile stores each package's declared dependency ranges, so edges are
+/// recovered by resolving every range to the highest locked version satisfying it.
+pub struct DependencyGraph<'a> {
+    /// Entries by package name, highest version first
+    by_name: HashMap<&'a str, Vec<&'a PackageEntry>>,
+}
+
+impl<'a> DependencyGraph<'a> {
+    pub fn new(lockfile: &'a Lockfile) -> Self {
+        let mut by_name: HashMap<&str, Vec<&PackageEntry>> = HashMap::new();
+        for entry in lockfile.packages.values() {
+            by_name.entry(entry.name.as_str()).or_default().push(entry);
+        }
+
+        for entries in by_name.values_mut() {
+            entries.sort_by(|a, b| {
+                match (Version::parse(&a.version), Version::parse(&b.version)) {
+                    (Ok(a), Ok(b)) => b.cmp(&a),
+                    _ => b.version.cmp(&a.version),
+                }
+            });
+        }
+
+        Self { by_name }
+    }
+
+    /// The locked package a dependency on `name` with `range` resolves to.
+    ///
+    /// `npm:` aliases resolve to the aliased package. Ranges that aren't semver
+    /// (git, file, `workspace:` or a dist-tag) resolve to the package's only (or
+    /// highest) locked version, semver ranges no locked version satisfies to `None`.
+    pub fn resolve(&self, name: &str, range: &str) -> Option<&'a PackageEntry> {
+        let (name, range) = match range.strip_prefix("npm:") {
+            Some(alias) => match alias.get(1..).and_then(|rest| rest.rfind('@')) {
+                Some(at) => (&alias[..at + 1], &alias[at + 2..]),
+                None => (alias, "*"),
+            },
+            None => (name, range),
+        };
+
+        let entries = self.by_name.get(name)?;
+        let Ok(range) = Range::parse(range) else {
+            return entries.first().copied();
+        };
+
+        entries
+            .iter()
+            .find(|entry| Version::parse(&entry.version).is_ok_and(|v| range.satisfies(&v)))
+            .copied()
+    }
+
+    /// Edges from `dependencies` (a package.json or lockfile dependency map) to the
+    /// locked packages they resolve to, skipping ones missing from the lockfile
+    pub fn edges<'d>(
+        &self,
+        dependencies: impl IntoIterator<Item = (&'d String, &'d String)>,
+    ) -> Vec<Edge<'a>> {
+        let mut edges: Vec<Edge> = dependencies
+            .into_iter()
+            .filter_map(|(name, range)| {
+                Some(Edge {
+                    name: name.clone(),
+                    range: range.clone(),
+                    entry: self.resolve(name, range)?,
+                })
+            })
+            .collect();
+        edges.sort_by(|a, b| a.name.cmp(&b.name));
+        edges
+    }
+
+    /// The dependencies of a locked package
+    pub fn dependencies(&self, entry: &PackageEntry) -> Vec<Edge<'a>> {
+        self.edges(entry.dependencies.iter().flatten())
+    }
+
+    /// Every chain of edges from one of `roots` down to a package matching `target`.
+    /// A package appears at most once in a chain, so cycles end there.
+    pub fn paths(
+        &self,
+        roots: &[Edge<'a>],
+        target: impl Fn(&PackageEntry) -> bool,
+    ) -> Vec<Vec<Edge<'a>>> {
+        let leads_to_target = self.ancestors(&target);
+        let mut paths = Vec::new();
+
+        for root in roots {
+            let mut chain = vec![root.clone()];
+            self.walk(&mut chain, &target, &leads_to_target, &mut paths);
+        }
+
+        paths
+    }
+
+    fn walk(
+        &self,
+        chain: &mut Vec<Edge<'a>>,
+        target: &impl Fn(&PackageEntry) -> bool,
+        leads_to_target: &HashSet<String>,
+        paths: &mut Vec<Vec<Edge<'a>>>,
+    ) {
+        let Some(entry) = chain.last().map(|edge| edge.entry) else {
+            return;
+        };
+
+        if target(entry) {
+            paths.push(chain.clone());
+        }
+        if !leads_to_target.contains(&entry.key()) {
+            return;
+        }
+
+        for edge in self.dependencies(entry) {
+            if chain.iter().any(|e| e.entry.key() == edge.entry.key()) {
+                continue;
+            }
+            chain.push(edge);
+            self.walk(chain, target, leads_to_target, paths);
+            chain.pop();
+        }
+    }
+
+    /// Keys of the packages depending, directly or transitively, on one matching `target`
+    fn ancestors(&self, target: &impl Fn(&PackageEntry) -> bool) -> HashSet<String> {
+        let mut dependents: HashMap<String, Vec<String>> = HashMap::new();
+        let mut queue: VecDeque<String> = VecDeque::new();
+
+        for entry in self.by_name.values().flatten() {
+            for edge in self.dependencies(entry) {
+                dependents
+                    .entry(edge.entry.key())
+                    .or_default()
+                    .push(entry.key());
+            }
+            if target(entry) {
+                queue.push_back(entry.key());
+            }
+        }
+
+        let mut ancestors = HashSet::new();
+        while let Some(key) = queue.pop_front() {
+            for dependent in dependents.get(&key).into_iter().flatten() {
+                if ancestors.insert(dependent.clone()) {
+                    queue.push_back(dependent.clone());
+                }
+            }
+        }
+
+        ancestors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn entry(name: &str, version: &str, deps: &[(&str, &str)]) -> PackageEntry {
+        let entry = PackageEntry::new(name.to_string(), version.to_string(), String::new());
+        if deps.is_empty() {
+            return entry;
+        }
+        entry.with_dependencies(
+            deps.iter()
+                .map(|(n, r)| (n.to_string(), r.to_string()))
+                .collect(),
+        )
+    }
+
+    fn lockfile() -> Lockfile {
+        let mut lockfile = Lockfile::new();
+        lockfile.add_package(entry("react-dom", "18.3.1", &[("scheduler", "^0.23.0")]));
+        lockfile.add_package(entry("scheduler", "0.23.2", &[("loose-envify", "^1.1.0")]));
+        lockfile.add_package(entry("scheduler", "0.20.2", &[]));
+        lockfile.add_package(entry("loose-envify", "1.4.0", &[("scheduler", "^0.23.0")]));
+        lockfile.add_package(entry("wrap-ansi", "7.0.0", &[]));
+        lockfile.add_package(entry("wrap-ansi", "8.1.0", &[]));
+        lockfile
+    }
+
+    #[test]
+    fn test_resolve() {
+        let lockfile = lockfile();
+        let graph = DependencyGraph::new(&lockfile);
+
+        let version = |name, range| graph.resolve(name, range).map(|e| e.version.as_str());
+        assert_eq!(version("scheduler", "^0.23.0"), Some("0.23.2"));
+        assert_eq!(version("scheduler", "^0.20.0"), Some("0.20.2"));
+        assert_eq!(version("scheduler", "latest"), Some("0.23.2"));
+        assert_eq!(version("scheduler", "^1.0.0"), None);
+        assert_eq!(
+            version("wrap-ansi-cjs", "npm:wrap-ansi@^7.0.0"),
+            Some("7.0.0")
+        );
+        assert_eq!(version("missing", "^1.0.0"), None);
+    }
+
+    #[test]
+    fn test_paths_stop_at_cycles() {
+        let lockfile = lockfile();
+        let graph = DependencyGraph::new(&lockfile);
+        let deps = BTreeMap::from([("react-dom".to_string(), "^18.0.0".to_string())]);
+        let roots = graph.edges(&deps);
+
+        let paths = graph.paths(&roots, |e| e.name == "loose-envify");
+        let chains: Vec<Vec<String>> = paths
+            .iter()
+            .map(|path| path.iter().map(|e| e.entry.key()).collect())
+            .collect();
+
+        assert_eq!(
+            chains,
+            vec![vec![
+                "react-dom@18.3.1".to_string(),
+                "scheduler@0.23.2".to_string(),
+                "loose-envify@1.4.0".to_string()
+            ]]
+        );
+        assert_eq!(paths[0][1].range, "^0.23.0");
+
+        assert!(graph.paths(&roots, |e| e.name == "wrap-ansi").is_empty());
+    }
+}
@@ -1,5 +1,7 @@
+pub mod graph;
 pub mod lockfile;
 pub mod package_entry;
 
+pub use graph::{DependencyGraph, Edge};
 pub use lockfile::Lockfile;
 pub use package_entry::PackageEntry;
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::path::Path;
 
 use contract::Result;
@@ -10,14 +10,26 @@ use crate::package_entry::PackageEntry;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Lockfile {
     pub version: String,
-    pub packages: HashMap<String, PackageEntry>,
+    pub packages: BTreeMap<String, PackageEntry>,
+    /// Overrides applied while resolving, by key; entries are re-resolved when they change
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub overrides: BTreeMap<String, String>,
+    /// Every override of the root package.json, by key, to tell when they changed
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub declared_overrides: BTreeMap<String, String>,
+    /// Hashes of the patches applied to packages, by `name@version`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub patches: BTreeMap<String, String>,
 }
 
 impl Lockfile {
     pub fn new() -> Self {
         Self {
             version: "1.0.0".to_string(),
-            packages: HashMap::new(),
+            packages: BTreeMap::new(),
+            overrides: BTreeMap::new(),
+            declared_overrides: BTreeMap::new(),
+            patches: BTreeMap::new(),
         }
     }
 
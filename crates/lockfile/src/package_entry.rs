@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use serde::{Deserialize, Serialize};
 
@@ -10,7 +10,7 @@ pub struct PackageEntry {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub integrity: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub dependencies: Option<HashMap<String, String>>,
+    pub dependencies: Option<BTreeMap<String, String>>,
 }
 
 impl PackageEntry {
@@ -29,7 +29,7 @@ impl PackageEntry {
         self
     }
 
-    pub fn with_dependencies(mut self, dependencies: HashMap<String, String>) -> Self {
+    pub fn with_dependencies(mut self, dependencies: BTreeMap<String, String>) -> Self {
         self.dependencies = Some(dependencies);
         self
     }
@@ -1,3 +1,5 @@
 mod network;
+mod transport;
 
 pub use network::Network;
+pub use transport::{ByteStream, Conditional, Transport};
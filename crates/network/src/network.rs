@@ -1,11 +1,14 @@
+use crate::transport::{ByteStream, Conditional, Transport, save};
+use async_trait::async_trait;
 use contract::Result;
-use reqwest::Client;
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use reqwest::{Client, StatusCode};
+use serde::Serialize;
 use serde::de::DeserializeOwned;
 use std::path::PathBuf;
-use tokio::fs::File;
-use tokio::io::AsyncWriteExt;
 
-#[derive(Debug)]
+/// HTTP [Transport]. Clones share the connection pool.
+#[derive(Debug, Clone)]
 pub struct Network {
     client: Client,
 }
@@ -19,21 +22,91 @@ impl Network {
 
     /// Fetch JSON data from a URL and deserialize it into type [T]
     pub async fn fetch<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
-        let response = self.client.get(url).send().await?;
+        let response = self.client.get(url).send().await?.error_for_status()?;
         let data = response.json::<T>().await?;
         Ok(data)
     }
 
+    /// Post [B] as JSON to a URL and deserialize the response into type [T]
+    pub async fn post<B: Serialize, T: DeserializeOwned>(&self, url: &str, body: &B) -> Result<T> {
+        let response = self
+            .client
+            .post(url)
+            .json(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        let data = response.json::<T>().await?;
+        Ok(data)
+    }
+
+    /// Put [B] as JSON to a URL with extra headers, failing with the response
+    /// body when the server rejects it
+    pub async fn put<B: Serialize>(
+        &self,
+        url: &str,
+        body: &B,
+        headers: &[(&str, String)],
+    ) -> Result<()> {
+        let mut request = self.client.put(url).json(body);
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            anyhow::bail!("{} {}: {}", status, url, text.trim());
+        }
+
+        Ok(())
+    }
+
     /// Download a file from a URL and save it to the specified path
     pub async fn download(&self, from_url: &str, store_path: PathBuf) -> Result<()> {
-        let response = self.client.get(from_url).send().await?;
-        let bytes = response.bytes().await?;
+        save(self.fetch_stream(from_url).await?, store_path).await
+    }
+}
 
-        let mut file = File::create(store_path).await?;
-        file.write_all(&bytes).await?;
-        file.flush().await?;
+#[async_trait]
+impl Transport for Network {
+    async fn fetch_json(&self, url: &str) -> Result<serde_json::Value> {
+        self.fetch(url).await
+    }
 
-        Ok(())
+    async fn fetch_stream(&self, url: &str) -> Result<ByteStream> {
+        let response = self.client.get(url).send().await?.error_for_status()?;
+        Ok(Box::pin(futures::stream::try_unfold(
+            response,
+            |mut response| async move {
+                let chunk = response.chunk().await?;
+                Ok(chunk.map(|chunk| (chunk, response)))
+            },
+        )))
+    }
+
+    async fn fetch_if_modified(&self, url: &str, etag: Option<&str>) -> Result<Conditional> {
+        let mut request = self.client.get(url);
+        if let Some(etag) = etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(Conditional::NotModified);
+        }
+
+        let response = response.error_for_status()?;
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(str::to_string);
+        Ok(Conditional::Modified {
+            body: response.bytes().await?,
+            etag,
+        })
     }
 }
 
@@ -46,6 +119,9 @@ impl Default for Network {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use bytes::Bytes;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
     #[test]
     fn test_network_new() {
@@ -58,4 +134,63 @@ mod tests {
         let network = Network::default();
         assert!(std::mem::size_of_val(&network.client) > 0);
     }
+
+    /// Answer each request with `respond(request)`
+    async fn serve(respond: fn(&str) -> String) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0; 4096];
+                let n = socket.read(&mut buf).await.unwrap();
+                let response = respond(&String::from_utf8_lossy(&buf[..n]).to_lowercase());
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_fetch_if_modified() {
+        let url = serve(|request| {
+            if request.contains("if-none-match: \"v1\"") {
+                "HTTP/1.1 304 Not Modified\r\ncontent-length: 0\r\n\r\n".to_string()
+            } else {
+                "HTTP/1.1 200 OK\r\netag: \"v1\"\r\ncontent-length: 2\r\n\r\n{}".to_string()
+            }
+        })
+        .await;
+        let network = Network::new();
+
+        assert_eq!(
+            network.fetch_if_modified(&url, None).await.unwrap(),
+            Conditional::Modified {
+                body: Bytes::from_static(b"{}"),
+                etag: Some("\"v1\"".to_string()),
+            }
+        );
+        assert_eq!(
+            network
+                .fetch_if_modified(&url, Some("\"v1\""))
+                .await
+                .unwrap(),
+            Conditional::NotModified
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_through_transport() {
+        let url = serve(|_| {
+            "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n3\r\nabc\r\n3\r\ndef\r\n0\r\n\r\n"
+                .to_string()
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+
+        let transport: Arc<dyn Transport> = Arc::new(Network::new());
+        transport.download(&url, path.clone()).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "abcdef");
+    }
 }
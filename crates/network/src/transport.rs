@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+use std::pin::Pin;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use contract::Result;
+use futures::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+
+/// A response body read chunk by chunk
+pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes>> + Send>>;
+
+/// What a conditional GET got back
+#[derive(Debug, Clone, PartialEq)]
+pub enum Conditional {
+    /// The resource changed, or there was no `etag` to compare with
+    Modified { body: Bytes, etag: Option<String> },
+    /// The resource still has the `etag` it was requested with
+    NotModified,
+}
+
+/// How packages and their documents are fetched. [crate::Network] does it over
+/// HTTP; resolvers accept any transport, so an install can share one
+/// connection pool, and tests or mirrors can answer without a registry.
+#[async_trait]
+pub trait Transport: std::fmt::Debug + Send + Sync {
+    /// GET `url` and parse the body as JSON
+    async fn fetch_json(&self, url: &str) -> Result<serde_json::Value>;
+
+    /// GET `url` and read the body as it arrives
+    async fn fetch_stream(&self, url: &str) -> Result<ByteStream>;
+
+    /// GET `url` unless it still has `etag`, the ETag of an earlier response
+    async fn fetch_if_modified(&self, url: &str, etag: Option<&str>) -> Result<Conditional>;
+}
+
+impl dyn Transport {
+    /// Fetch JSON data from a URL and deserialize it into type [T]
+    pub async fn fetch<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        Ok(serde_json::from_value(self.fetch_json(url).await?)?)
+    }
+
+    /// Download a file from a URL and save it to the specified path
+    pub async fn download(&self, from_url: &str, store_path: PathBuf) -> Result<()> {
+        save(self.fetch_stream(from_url).await?, store_path).await
+    }
+}
+
+/// Write `stream` to `path` chunk by chunk
+pub(crate) async fn save(mut stream: ByteStream, path: PathBuf) -> Result<()> {
+    let mut file = File::create(path).await?;
+    while let Some(chunk) = stream.next().await {
+        file.write_all(&chunk?).await?;
+    }
+    file.flush().await?;
+
+    Ok(())
+}
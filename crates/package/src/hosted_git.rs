@@ -0,0 +1,244 @@
+use std::fmt::Display;
+
+/// Git hosting providers that expose downloadable archives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitHost {
+    GitHub,
+    GitLab,
+    Bitbucket,
+}
+
+impl GitHost {
+    fn from_shortcut(shortcut: &str) -> Option<Self> {
+        match shortcut {
+            "github" => Some(Self::GitHub),
+            "gitlab" => Some(Self::GitLab),
+            "bitbucket" => Some(Self::Bitbucket),
+            _ => None,
+        }
+    }
+
+    fn from_domain(domain: &str) -> Option<Self> {
+        match domain.trim_start_matches("www.") {
+            "github.com" => Some(Self::GitHub),
+            "gitlab.com" => Some(Self::GitLab),
+            "bitbucket.org" => Some(Self::Bitbucket),
+            _ => None,
+        }
+    }
+
+    pub fn domain(&self) -> &'static str {
+        match self {
+            Self::GitHub => "github.com",
+            Self::GitLab => "gitlab.com",
+            Self::Bitbucket => "bitbucket.org",
+        }
+    }
+
+    pub fn shortcut(&self) -> &'static str {
+        match self {
+            Self::GitHub => "github",
+            Self::GitLab => "gitlab",
+            Self::Bitbucket => "bitbucket",
+        }
+    }
+}
+
+/// A repository on a known git host, parsed from a dependency specification
+///
+/// Handles the same shapes as npm's hosted-git-info:
+/// - Shortcuts: `github:user/repo`, `gitlab:user/repo`, `bitbucket:user/repo`
+/// - Bare GitHub shorthand: `user/repo`
+/// - URLs: `git+https://github.com/user/repo.git`, `git@github.com:user/repo.git`, ...
+///
+/// Everything after `#` is kept as the committish (branch, tag or commit).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostedGit {
+    pub host: GitHost,
+    pub user: String,
+    pub project: String,
+    pub committish: Option<String>,
+}
+
+impl HostedGit {
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (location, committish) = match spec.split_once('#') {
+            Some((location, committish)) if !committish.is_empty() => {
+                (location, Some(committish.to_string()))
+            }
+            Some((location, _)) => (location, None),
+            None => (spec, None),
+        };
+
+        let (host, path) = if let Some((shortcut, path)) = location.split_once(':')
+            && let Some(host) = GitHost::from_shortcut(shortcut)
+        {
+            (host, path)
+        } else if Self::is_shorthand(location) {
+            (GitHost::GitHub, location)
+        } else {
+            Self::parse_url(location)?
+        };
+
+        let (user, project) = path.trim_matches('/').split_once('/')?;
+        let project = project.trim_end_matches(".git");
+
+        if !Self::is_valid_segment(user) || !Self::is_valid_segment(project) {
+            return None;
+        }
+
+        Some(Self {
+            host,
+            user: user.to_string(),
+            project: project.to_string(),
+            committish,
+        })
+    }
+
+    /// Bare `user/repo` shorthand, which npm maps to GitHub
+    fn is_shorthand(location: &str) -> bool {
+        !location.starts_with(['@', '.', '/', '~'])
+            && !location.contains(':')
+            && location.matches('/').count() == 1
+    }
+
+    /// Split a git URL into its host and repository path
+    fn parse_url(location: &str) -> Option<(GitHost, &str)> {
+        let url = location.trim_start_matches("git+");
+        let url = ["https://", "http://", "git://", "ssh://"]
+            .iter()
+            .find_map(|scheme| url.strip_prefix(scheme))
+            .unwrap_or(url);
+
+        // Drop credentials such as `git@`
+        let url = url.split_once('@').map_or(url, |(_, rest)| rest);
+
+        // Host is separated from the path by `/` in URLs and by `:` in scp-like syntax
+        let split_at = url.find(['/', ':'])?;
+        let (domain, path) = url.split_at(split_at);
+        let host = GitHost::from_domain(domain)?;
+
+        Some((host, &path[1..]))
+    }
+
+    fn is_valid_segment(segment: &str) -> bool {
+        !segment.is_empty()
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    }
+
+    fn committish_or_head(&self) -> &str {
+        self.committish.as_deref().unwrap_or("HEAD")
+    }
+
+    /// URL of a gzipped archive of the repository at the requested committish
+    pub fn tarball_url(&self) -> String {
+        let committish = self.committish_or_head();
+        match self.host {
+            GitHost::GitHub => format!(
+                "https://codeload.github.com/{}/{}/tar.gz/{}",
+                self.user, self.project, committish
+            ),
+            GitHost::GitLab => format!(
+                "https://gitlab.com/{}/{}/-/archive/{}/{}-{}.tar.gz",
+                self.user, self.project, committish, self.project, committish
+            ),
+            GitHost::Bitbucket => format!(
+                "https://bitbucket.org/{}/{}/get/{}.tar.gz",
+                self.user, self.project, committish
+            ),
+        }
+    }
+
+    /// URL used for a full `git clone` when the archive is unavailable
+    pub fn clone_url(&self) -> String {
+        format!(
+            "https://{}/{}/{}.git",
+            self.host.domain(),
+            self.user,
+            self.project
+        )
+    }
+}
+
+impl Display for HostedGit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}/{}", self.host.shortcut(), self.user, self.project)?;
+        if let Some(committish) = &self.committish {
+            write!(f, "#{}", committish)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shortcuts() {
+        let git = HostedGit::parse("github:user/repo#v1.2.0").expect("should parse");
+        assert_eq!(git.host, GitHost::GitHub);
+        assert_eq!(git.user, "user");
+        assert_eq!(git.project, "repo");
+        assert_eq!(git.committish, Some("v1.2.0".to_string()));
+
+        let git = HostedGit::parse("gitlab:group/project").expect("should parse");
+        assert_eq!(git.host, GitHost::GitLab);
+        assert_eq!(git.committish, None);
+
+        let git = HostedGit::parse("bitbucket:team/lib").expect("should parse");
+        assert_eq!(git.host, GitHost::Bitbucket);
+    }
+
+    #[test]
+    fn test_parse_bare_shorthand() {
+        let git = HostedGit::parse("user/repo").expect("should parse");
+        assert_eq!(git.host, GitHost::GitHub);
+        assert_eq!(git.to_string(), "github:user/repo");
+
+        assert!(HostedGit::parse("@scope/package").is_none());
+        assert!(HostedGit::parse("./local/dir").is_none());
+        assert!(HostedGit::parse("a/b/c").is_none());
+        assert!(HostedGit::parse("^1.0.0").is_none());
+    }
+
+    #[test]
+    fn test_parse_urls() {
+        let git = HostedGit::parse("git+https://github.com/user/repo.git#main").expect("https");
+        assert_eq!(git.project, "repo");
+        assert_eq!(git.committish, Some("main".to_string()));
+
+        let git = HostedGit::parse("git+ssh://git@gitlab.com/user/repo.git").expect("ssh");
+        assert_eq!(git.host, GitHost::GitLab);
+
+        let git = HostedGit::parse("git@bitbucket.org:user/repo.git").expect("scp");
+        assert_eq!(git.host, GitHost::Bitbucket);
+        assert_eq!(git.user, "user");
+
+        assert!(HostedGit::parse("git+https://example.com/user/repo.git").is_none());
+    }
+
+    #[test]
+    fn test_tarball_urls() {
+        let git = HostedGit::parse("github:user/repo#v1.2.0").expect("should parse");
+        assert_eq!(
+            git.tarball_url(),
+            "https://codeload.github.com/user/repo/tar.gz/v1.2.0"
+        );
+        assert_eq!(git.clone_url(), "https://github.com/user/repo.git");
+
+        let git = HostedGit::parse("gitlab:user/repo").expect("should parse");
+        assert_eq!(
+            git.tarball_url(),
+            "https://gitlab.com/user/repo/-/archive/HEAD/repo-HEAD.tar.gz"
+        );
+
+        let git = HostedGit::parse("bitbucket:user/repo#abc123").expect("should parse");
+        assert_eq!(
+            git.tarball_url(),
+            "https://bitbucket.org/user/repo/get/abc123.tar.gz"
+        );
+    }
+}
@@ -1,3 +1,6 @@
+use std::path::PathBuf;
+
+use crate::HostedGit;
 pub use crate::{NpmPackage, PackageJson};
 
 #[derive(Debug, Clone)]
@@ -5,6 +8,9 @@ pub struct InstallPackage {
     pub name: String,
     pub version: Option<String>,
     pub is_dev: bool,
+    /// Directory of the package.json declaring the dependency, which relative
+    /// `file:` and `link:` specs point from. The working directory when unset.
+    pub base_dir: Option<PathBuf>,
 }
 
 impl InstallPackage {
@@ -13,18 +19,42 @@ impl InstallPackage {
             name,
             version,
             is_dev,
+            base_dir: None,
         }
     }
 
+    /// Resolve relative local specs against `dir` instead of the working directory
+    pub fn with_base_dir(mut self, dir: PathBuf) -> Self {
+        self.base_dir = Some(dir);
+        self
+    }
+
     pub fn to_cache_key(&self) -> String {
-        format!(
+        let key = format!(
             "{}-{}",
             self.name,
             self.version.clone().unwrap_or("latest".to_string())
-        )
+        );
+        // The same relative path means different packages in different directories
+        match &self.base_dir {
+            Some(dir) if Self::is_local_spec(self.spec()) => {
+                format!("{}@{}", key, dir.display())
+            }
+            _ => key,
+        }
     }
 
     pub fn from_literal(package: &str, is_dev: bool) -> Self {
+        // Git, path and URL specs may contain @ (e.g. git@github.com:user/repo.git), keep them whole
+        if !Self::is_registry_spec(package) {
+            return Self {
+                name: package.to_string(),
+                version: None,
+                is_dev,
+                base_dir: None,
+            };
+        }
+
         // Handle scoped packages like @scope/package@version
         // Find the last @ symbol to split name and version
         if let Some(last_at) = package.rfind('@') {
@@ -35,6 +65,7 @@ impl InstallPackage {
                     name: package.to_string(),
                     version: None,
                     is_dev,
+                    base_dir: None,
                 }
             } else {
                 // Split into name and version
@@ -43,6 +74,7 @@ impl InstallPackage {
                     name: name.to_string(),
                     version: Some(version[1..].to_string()), // Skip the @ symbol
                     is_dev,
+                    base_dir: None,
                 }
             }
         } else {
@@ -51,6 +83,7 @@ impl InstallPackage {
                 name: package.to_string(),
                 version: None,
                 is_dev,
+                base_dir: None,
             }
         }
     }
@@ -76,13 +109,52 @@ impl InstallPackage {
         req.matches(&ver)
     }
 
+    /// The specification to resolve: the version for `"name": "<spec>"` entries
+    /// that point somewhere other than the registry, otherwise the name itself
+    /// (e.g. `craft add github:user/repo`).
+    pub fn spec(&self) -> &str {
+        match self.version.as_deref() {
+            Some(version) if !Self::is_registry_spec(version) => version,
+            _ => &self.name,
+        }
+    }
+
     pub fn is_git(&self) -> bool {
-        self.name.starts_with("git:")
-            || self.name.starts_with("git+ssh:")
-            || self.name.starts_with("git+http:")
-            || self.name.starts_with("git+https:")
-            || self.name.starts_with("ssh:")
-            || self.name.ends_with(".git")
+        Self::is_git_spec(self.spec())
+    }
+
+    /// Whether the package comes from the registry rather than git, a path or a URL
+    pub fn is_registry(&self) -> bool {
+        Self::is_registry_spec(self.spec())
+    }
+
+    pub fn is_registry_spec(spec: &str) -> bool {
+        !Self::is_git_spec(spec) && !Self::is_local_spec(spec) && !Self::is_tarball_spec(spec)
+    }
+
+    /// `file:` and `link:` protocols, or a bare relative/absolute path
+    pub fn is_local_spec(spec: &str) -> bool {
+        spec.starts_with("file:")
+            || spec.starts_with("link:")
+            || spec.starts_with("./")
+            || spec.starts_with("../")
+            || spec.starts_with('/')
+            || spec.starts_with("~/")
+    }
+
+    /// Plain `http(s)://` URLs that don't point at a git repository
+    pub fn is_tarball_spec(spec: &str) -> bool {
+        (spec.starts_with("https://") || spec.starts_with("http://")) && !Self::is_git_spec(spec)
+    }
+
+    pub fn is_git_spec(spec: &str) -> bool {
+        spec.starts_with("git:")
+            || spec.starts_with("git+ssh:")
+            || spec.starts_with("git+http:")
+            || spec.starts_with("git+https:")
+            || spec.starts_with("ssh:")
+            || spec.ends_with(".git")
+            || HostedGit::parse(spec).is_some()
     }
 }
 
@@ -140,6 +212,64 @@ mod tests {
         assert!(!pkg.is_git());
     }
 
+    #[test]
+    fn test_is_git_hosted_shorthand() {
+        let pkg = InstallPackage::new(
+            "foo".to_string(),
+            Some("github:user/repo#v1.2.0".to_string()),
+            false,
+        );
+        assert!(pkg.is_git());
+        assert_eq!(pkg.spec(), "github:user/repo#v1.2.0");
+
+        let pkg = InstallPackage::new("foo".to_string(), Some("user/repo".to_string()), false);
+        assert!(pkg.is_git());
+
+        let pkg = InstallPackage::new("foo".to_string(), Some("^1.0.0".to_string()), false);
+        assert!(!pkg.is_git());
+        assert_eq!(pkg.spec(), "foo");
+    }
+
+    #[test]
+    fn test_local_and_tarball_specs() {
+        let pkg = InstallPackage::new("lib".to_string(), Some("file:../lib".to_string()), false);
+        assert!(!pkg.is_registry());
+        assert!(!pkg.is_git());
+        assert_eq!(pkg.spec(), "file:../lib");
+
+        assert!(InstallPackage::is_local_spec("link:../lib"));
+        assert!(InstallPackage::is_local_spec("./vendor/pkg-1.0.0.tgz"));
+        assert!(InstallPackage::is_tarball_spec(
+            "https://example.com/pkg/-/pkg-1.0.0.tgz"
+        ));
+        assert!(!InstallPackage::is_tarball_spec(
+            "https://github.com/user/repo.git"
+        ));
+        assert!(InstallPackage::is_registry_spec("^1.0.0"));
+        assert!(InstallPackage::is_registry_spec("npm:wrap-ansi@^7.0.0"));
+    }
+
+    #[test]
+    fn test_from_literal_url() {
+        let pkg = InstallPackage::from_literal(
+            "https://registry.example.com/@scope/pkg/-/pkg-1.0.0.tgz",
+            false,
+        );
+        assert_eq!(pkg.version, None);
+        assert!(!pkg.is_registry());
+    }
+
+    #[test]
+    fn test_from_literal_git() {
+        let pkg = InstallPackage::from_literal("git+ssh://git@github.com:user/repo.git", false);
+        assert_eq!(pkg.name, "git+ssh://git@github.com:user/repo.git");
+        assert_eq!(pkg.version, None);
+
+        let pkg = InstallPackage::from_literal("gitlab:user/repo#main", true);
+        assert_eq!(pkg.name, "gitlab:user/repo#main");
+        assert!(pkg.is_git());
+    }
+
     #[test]
     fn test_from_literal_simple() {
         let pkg = InstallPackage::from_literal("react", false);
@@ -1,5 +1,16 @@
+pub mod hosted_git;
 pub mod install_package;
 pub mod npm;
+pub mod overrides;
+pub mod packlist;
+pub mod range;
+pub mod workspace;
+pub mod workspace_filter;
 
+pub use hosted_git::{GitHost, HostedGit};
 pub use install_package::InstallPackage;
-pub use npm::{NpmPackage, PackageBin, PackageJson};
+pub use npm::{NpmPackage, PackageBin, PackageDist, PackageJson};
+pub use overrides::{Override, Overrides, Selector};
+pub use packlist::{bin_paths, packlist};
+pub use workspace::{Workspace, WorkspacePackage, Workspaces};
+pub use workspace_filter::WorkspaceFilter;
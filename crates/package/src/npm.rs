@@ -1,9 +1,13 @@
+use crate::Workspaces;
 use contract::{PackageError, Result};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 use tokio::fs;
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct PackageJson {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -25,6 +29,20 @@ pub struct PackageJson {
     pub dist: Option<PackageDist>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bin: Option<PackageBin>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspaces: Option<Workspaces>,
+    /// Dependencies allowed to run install scripts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trusted_dependencies: Option<Vec<String>>,
+    /// npm overrides of transitive dependency ranges, see [crate::Overrides]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overrides: Option<serde_json::Map<String, serde_json::Value>>,
+    /// yarn resolutions, the `foo/bar` keyed equivalent of `overrides`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolutions: Option<HashMap<String, String>>,
+    /// Patch files applied to dependencies, by `name@version`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patched_dependencies: Option<HashMap<String, String>>,
     #[serde(flatten)]
     pub other: HashMap<String, serde_json::Value>,
 }
@@ -42,13 +60,16 @@ impl PackageJson {
         Ok(package_json_path)
     }
     pub async fn from_file() -> Result<Self> {
-        let package_json_path = Self::file_path()?;
+        Self::from_path(&Self::file_path()?).await
+    }
 
+    /// Read a package.json from an explicit path
+    pub async fn from_path(package_json_path: &Path) -> Result<Self> {
         if !package_json_path.exists() {
             return Err(PackageError::NoPackageJson.into());
         }
 
-        let content = fs::read_to_string(&package_json_path).await?;
+        let content = fs::read_to_string(package_json_path).await?;
         let package_json: PackageJson = serde_json::from_str(&content)?;
         Ok(package_json)
     }
@@ -57,6 +78,10 @@ impl PackageJson {
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PackageDist {
     pub tarball: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shasum: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -0,0 +1,418 @@
+use std::collections::BTreeMap;
+
+use contract::Result;
+use node_semver::{Range, Version};
+
+use crate::PackageJson;
+
+/// A package in an override's path, `name` or `name@range`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    pub name: String,
+    pub range: Option<String>,
+}
+
+impl Selector {
+    pub fn parse(selector: &str) -> Self {
+        match selector.get(1..).and_then(|rest| rest.find('@')) {
+            Some(at) => Self {
+                name: selector[..at + 1].to_string(),
+                range: Some(selector[at + 2..].to_string()),
+            },
+            None => Self {
+                name: selector.to_string(),
+                range: None,
+            },
+        }
+    }
+
+    /// Whether the package `name` at `version` is selected
+    pub fn matches(&self, name: &str, version: &str) -> bool {
+        self.name == name && self.matches_version(version)
+    }
+
+    pub fn matches_version(&self, version: &str) -> bool {
+        match &self.range {
+            None => true,
+            Some(range) => match (Range::parse(range), Version::parse(version)) {
+                (Ok(range), Ok(version)) => range.satisfies(&version),
+                _ => range == version,
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Selector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.range {
+            Some(range) => write!(f, "{}@{}", self.name, range),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// Replaces the range `target` is declared with, wherever it's a dependency of a
+/// package below `parents`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Override {
+    pub parents: Vec<Selector>,
+    pub target: Selector,
+    /// Whether the last parent must depend on `target` itself rather than through
+    /// other packages
+    pub direct: bool,
+    pub spec: String,
+}
+
+impl Override {
+    /// Whether the override applies to a dependency on `name` declared by the last
+    /// package of `chain`, the `(name, version)` path from a direct dependency.
+    /// The target's own range is checked against the resolved version separately.
+    pub fn applies_to(&self, name: &str, chain: &[(String, String)]) -> bool {
+        if self.target.name != name {
+            return false;
+        }
+
+        let mut above = chain;
+        for (i, selector) in self.parents.iter().rev().enumerate() {
+            let position = if i == 0 && self.direct {
+                match above.last() {
+                    Some((name, version)) if selector.matches(name, version) => above.len() - 1,
+                    _ => return false,
+                }
+            } else {
+                match above
+                    .iter()
+                    .rposition(|(name, version)| selector.matches(name, version))
+                {
+                    Some(position) => position,
+                    None => return false,
+                }
+            };
+            above = &above[..position];
+        }
+
+        true
+    }
+
+    /// The override in yarn's `resolutions` notation: `foo/bar` for a direct
+    /// dependency of foo, `foo/**/bar` for one anywhere below it
+    pub fn key(&self) -> String {
+        let mut key = self
+            .parents
+            .iter()
+            .map(Selector::to_string)
+            .collect::<Vec<_>>()
+            .join("/**/");
+        if !key.is_empty() {
+            key.push_str(if self.direct { "/" } else { "/**/" });
+        }
+        key.push_str(&self.target.to_string());
+        key
+    }
+}
+
+/// npm `overrides`, pnpm-style `foo>bar` keys and yarn `resolutions` of a root package.json
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overrides {
+    overrides: Vec<Override>,
+}
+
+impl Overrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_package_json(package_json: &PackageJson) -> Result<Self> {
+        let mut overrides = Vec::new();
+
+        if let Some(map) = &package_json.overrides {
+            Self::parse_npm(map, &[], package_json, &mut overrides)?;
+        }
+        for (key, spec) in package_json.resolutions.iter().flatten() {
+            let (parents, target, direct) = Self::parse_yarn(key);
+            overrides.push(Override {
+                parents,
+                target,
+                direct,
+                spec: Self::reference(spec, package_json)?,
+            });
+        }
+
+        // The most specific override wins, earlier ones first among equals
+        overrides.sort_by_key(|o| std::cmp::Reverse(o.parents.len()));
+
+        Ok(Self { overrides })
+    }
+
+    /// npm's nested objects, where `"."` overrides the enclosing package itself
+    fn parse_npm(
+        map: &serde_json::Map<String, serde_json::Value>,
+        parents: &[Selector],
+        package_json: &PackageJson,
+        overrides: &mut Vec<Override>,
+    ) -> Result<()> {
+        for (key, value) in map {
+            let (mut path, direct) = match key.as_str() {
+                "." => (parents.to_vec(), false),
+                // pnpm's `foo>bar` selects direct dependencies
+                _ if key.contains('>') => {
+                    let mut path = parents.to_vec();
+                    path.extend(key.split('>').map(|s| Selector::parse(s.trim())));
+                    (path, true)
+                }
+                _ => {
+                    let mut path = parents.to_vec();
+                    path.push(Selector::parse(key));
+                    (path, false)
+                }
+            };
+
+            match value {
+                serde_json::Value::String(spec) => {
+                    let Some(target) = path.pop() else {
+                        anyhow::bail!("The \".\" override must be nested in a package");
+                    };
+                    overrides.push(Override {
+                        parents: path,
+                        target,
+                        direct,
+                        spec: Self::reference(spec, package_json)?,
+                    });
+                }
+                serde_json::Value::Object(nested) if key != "." => {
+                    Self::parse_npm(nested, &path, package_json, overrides)?;
+                }
+                _ => anyhow::bail!("Invalid override for {:?}", key),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// yarn's `foo/bar`, `foo/**/bar` and `**/bar` keys
+    fn parse_yarn(key: &str) -> (Vec<Selector>, Selector, bool) {
+        let mut segments: Vec<String> = Vec::new();
+        let mut parts = key.split('/');
+        while let Some(part) = parts.next() {
+            // A scope belongs to the name after it
+            if part.starts_with('@')
+                && let Some(name) = parts.next()
+            {
+                segments.push(format!("{}/{}", part, name));
+            } else {
+                segments.push(part.to_string());
+            }
+        }
+
+        let direct = segments.len() < 2 || segments[segments.len() - 2] != "**";
+        let mut selectors: Vec<Selector> = segments
+            .iter()
+            .filter(|segment| *segment != "**")
+            .map(|segment| Selector::parse(segment))
+            .collect();
+        let target = selectors.pop().unwrap_or_else(|| Selector::parse(key));
+
+        (selectors, target, direct)
+    }
+
+    /// `$name` refers to the version the root package.json declares for `name`
+    fn reference(spec: &str, package_json: &PackageJson) -> Result<String> {
+        let Some(name) = spec.strip_prefix('$') else {
+            return Ok(spec.to_string());
+        };
+
+        [&package_json.dependencies, &package_json.dev_dependencies]
+            .into_iter()
+            .flatten()
+            .find_map(|deps| deps.get(name))
+            .cloned()
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Override {} refers to {}, which isn't a dependency",
+                    spec,
+                    name
+                )
+            })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+
+    /// Overrides for a dependency on `name` of the last package in `chain`, most
+    /// specific first
+    pub fn matching<'a>(
+        &'a self,
+        name: &'a str,
+        chain: &'a [(String, String)],
+    ) -> impl Iterator<Item = &'a Override> + 'a {
+        self.overrides
+            .iter()
+            .filter(move |o| o.applies_to(name, chain))
+    }
+
+    /// How far the overrides scoped to parent packages are matched by `chain`, as a
+    /// key. Packages below chains with the same scope get the same overrides, so
+    /// a package reached with different scopes is resolved once for each.
+    pub fn scope(&self, chain: &[(String, String)]) -> Option<String> {
+        let progress: Vec<String> =
+            self.overrides
+                .iter()
+                .filter_map(|o| {
+                    // The last parent of a direct override is checked against the
+                    // package declaring the dependency itself
+                    let max = if o.direct {
+                        o.parents.len().saturating_sub(1)
+                    } else {
+                        o.parents.len()
+                    };
+                    let matched = chain.iter().fold(0, |matched, (name, version)| {
+                        match o.parents.get(matched) {
+                            Some(selector) if matched < max && selector.matches(name, version) => {
+                                matched + 1
+                            }
+                            _ => matched,
+                        }
+                    });
+                    (matched > 0).then(|| format!("{}={}:{}", o.key(), o.spec, matched))
+                })
+                .collect();
+
+        (!progress.is_empty()).then(|| progress.join(","))
+    }
+
+    /// Overrides by key, as recorded in the lockfile
+    pub fn to_map(&self) -> BTreeMap<String, String> {
+        self.overrides
+            .iter()
+            .map(|o| (o.key(), o.spec.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(json: &str) -> Overrides {
+        let package_json: PackageJson = serde_json::from_str(json).unwrap();
+        Overrides::from_package_json(&package_json).unwrap()
+    }
+
+    fn chain(packages: &[(&str, &str)]) -> Vec<(String, String)> {
+        packages
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_selector() {
+        let selector = Selector::parse("@babel/core@^7.0.0");
+        assert_eq!(selector.name, "@babel/core");
+        assert_eq!(selector.range.as_deref(), Some("^7.0.0"));
+        assert!(selector.matches("@babel/core", "7.24.0"));
+        assert!(!selector.matches("@babel/core", "6.0.0"));
+        assert_eq!(Selector::parse("semver").range, None);
+    }
+
+    #[test]
+    fn test_npm_overrides() {
+        let overrides = overrides(
+            r#"{
+                "dependencies": { "semver": "^7.5.4" },
+                "overrides": {
+                    "semver": "$semver",
+                    "foo": { ".": "2.0.0", "bar": "1.0.0" },
+                    "baz>qux": "3.0.0"
+                }
+            }"#,
+        );
+
+        assert_eq!(
+            overrides.to_map(),
+            BTreeMap::from([
+                ("baz/qux".to_string(), "3.0.0".to_string()),
+                ("foo".to_string(), "2.0.0".to_string()),
+                ("foo/**/bar".to_string(), "1.0.0".to_string()),
+                ("semver".to_string(), "^7.5.4".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_yarn_resolutions() {
+        let overrides = overrides(
+            r#"{
+                "resolutions": {
+                    "**/minimist": "1.2.6",
+                    "@scope/foo/bar": "1.0.0",
+                    "foo/**/@scope/bar": "2.0.0"
+                }
+            }"#,
+        );
+
+        let keys: Vec<String> = overrides.overrides.iter().map(Override::key).collect();
+        assert!(keys.contains(&"minimist".to_string()));
+        assert!(keys.contains(&"@scope/foo/bar".to_string()));
+        assert!(keys.contains(&"foo/**/@scope/bar".to_string()));
+    }
+
+    #[test]
+    fn test_matching() {
+        let overrides = overrides(
+            r#"{ "overrides": { "bar": "1.0.0", "foo>bar": "2.0.0", "qux": { "bar": "3.0.0" } } }"#,
+        );
+        let spec = |name: &str, packages: &[(&str, &str)]| {
+            let chain = chain(packages);
+            overrides
+                .matching(name, &chain)
+                .next()
+                .map(|o| o.spec.clone())
+        };
+
+        assert_eq!(spec("bar", &[("a", "1.0.0")]).as_deref(), Some("1.0.0"));
+        assert_eq!(spec("bar", &[("foo", "1.0.0")]).as_deref(), Some("2.0.0"));
+        // foo>bar only applies to foo's own dependency
+        assert_eq!(
+            spec("bar", &[("foo", "1.0.0"), ("a", "1.0.0")]).as_deref(),
+            Some("1.0.0")
+        );
+        // npm's nested overrides apply anywhere below the package
+        assert_eq!(
+            spec("bar", &[("qux", "1.0.0"), ("a", "1.0.0")]).as_deref(),
+            Some("3.0.0")
+        );
+        assert_eq!(spec("baz", &[("foo", "1.0.0")]), None);
+    }
+
+    #[test]
+    fn test_scope() {
+        let overrides = overrides(
+            r#"{ "overrides": { "bar": "1.0.0", "foo>bar": "2.0.0", "qux": { "baz": { "bar": "3.0.0" } } } }"#,
+        );
+        let scope = |packages: &[(&str, &str)]| overrides.scope(&chain(packages));
+
+        // Direct parents and unscoped overrides don't depend on the path
+        assert_eq!(scope(&[("a", "1.0.0"), ("foo", "1.0.0")]), None);
+        assert_eq!(
+            scope(&[("qux", "1.0.0")]).as_deref(),
+            Some("qux/**/baz/**/bar=3.0.0:1")
+        );
+        assert_eq!(
+            scope(&[("qux", "1.0.0"), ("a", "1.0.0"), ("baz", "1.0.0")]),
+            scope(&[("qux", "1.0.0"), ("baz", "1.0.0")])
+        );
+        assert_ne!(
+            scope(&[("qux", "1.0.0"), ("baz", "1.0.0")]),
+            scope(&[("qux", "1.0.0")])
+        );
+    }
+
+    #[test]
+    fn test_invalid_reference() {
+        let package_json: PackageJson =
+            serde_json::from_str(r#"{ "overrides": { "semver": "$semver" } }"#).unwrap();
+        assert!(Overrides::from_package_json(&package_json).is_err());
+    }
+}
@@ -0,0 +1,341 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use contract::{LOCKFILE_NAME, Result};
+use glob::{MatchOptions, Pattern};
+
+use crate::{PackageBin, PackageJson};
+
+/// Never packed, at any depth
+const ALWAYS_IGNORED: &[&str] = &[
+    ".git",
+    ".svn",
+    ".hg",
+    "CVS",
+    "node_modules",
+    ".npmrc",
+    ".craftrc",
+    ".npmignore",
+    ".gitignore",
+    ".DS_Store",
+    "npm-debug.log",
+    "._*",
+    ".*.swp",
+    "*.orig",
+];
+
+/// Never packed from the package root
+const ROOT_IGNORED: &[&str] = &[
+    LOCKFILE_NAME,
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+];
+
+/// Always packed from the package root, case-insensitively, even when ignored
+const ALWAYS_INCLUDED: &[&str] = &["package.json", "readme*", "license*", "licence*"];
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// A line of an ignore file or a `files` entry, relative to the directory `base`
+#[derive(Debug)]
+struct Rule {
+    base: String,
+    pattern: Pattern,
+    negated: bool,
+    /// Only matches directories (`dist/`)
+    dir_only: bool,
+    /// Matches the path below `base` rather than any file name (`/dist`, `lib/*.js`)
+    anchored: bool,
+}
+
+impl Rule {
+    fn parse(line: &str, base: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let line = line.strip_prefix("./").unwrap_or(line);
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+
+        Some(Self {
+            base: base.to_string(),
+            pattern: Pattern::new(line).ok()?,
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    /// Whether the rule matches `path`, relative to the package root
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let relative = if self.base.is_empty() {
+            path
+        } else {
+            match path
+                .strip_prefix(self.base.as_str())
+                .and_then(|p| p.strip_prefix('/'))
+            {
+                Some(relative) => relative,
+                None => return false,
+            }
+        };
+
+        if self.anchored {
+            self.pattern.matches_with(relative, MATCH_OPTIONS)
+        } else {
+            let name = relative.rsplit('/').next().unwrap_or(relative);
+            self.pattern.matches_with(name, MATCH_OPTIONS)
+        }
+    }
+}
+
+/// Whether the last of `rules` matching `path` excludes it
+fn ignored(rules: &[Rule], path: &str, is_dir: bool) -> bool {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| rule.matches(path, is_dir))
+        .is_some_and(|rule| !rule.negated)
+}
+
+fn matches_any(patterns: &[&str], name: &str) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| Pattern::new(pattern).is_ok_and(|p| p.matches_with(name, MATCH_OPTIONS)))
+}
+
+/// The rules of the `.npmignore` in `dir`, or of its `.gitignore` without one
+fn ignore_rules(dir: &Path, base: &str) -> Result<Vec<Rule>> {
+    for name in [".npmignore", ".gitignore"] {
+        let path = dir.join(name);
+        if path.is_file() {
+            let contents = std::fs::read_to_string(path)?;
+            return Ok(contents
+                .lines()
+                .filter_map(|line| Rule::parse(line, base))
+                .collect());
+        }
+    }
+
+    Ok(Vec::new())
+}
+
+/// Whether a `files` entry includes `path`, itself or one of its directories
+fn listed(files: &[Rule], path: &str) -> bool {
+    let mut included = false;
+    for rule in files {
+        let matched = std::iter::successors(Some(path), |p| p.rsplit_once('/').map(|(dir, _)| dir))
+            .enumerate()
+            .any(|(i, p)| rule.matches(p, i > 0));
+        if matched {
+            included = !rule.negated;
+        }
+    }
+    included
+}
+
+fn walk(
+    root: &Path,
+    relative: &str,
+    files: Option<&[Rule]>,
+    rules: &mut Vec<Rule>,
+    packed: &mut BTreeSet<String>,
+) -> Result<()> {
+    let dir = root.join(relative);
+    // `files` takes precedence over the root's ignore file, not over nested ones
+    let own_rules = if relative.is_empty() && files.is_some() {
+        Vec::new()
+    } else {
+        ignore_rules(&dir, relative)?
+    };
+    let inherited = rules.len();
+    rules.extend(own_rules);
+
+    let mut entries: Vec<_> = std::fs::read_dir(&dir)?.collect::<std::io::Result<_>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = if relative.is_empty() {
+            name.clone()
+        } else {
+            format!("{}/{}", relative, name)
+        };
+        let file_type = entry.file_type()?;
+        let is_dir = file_type.is_dir();
+
+        if matches_any(ALWAYS_IGNORED, &name)
+            || (relative.is_empty() && ROOT_IGNORED.contains(&name.as_str()))
+            || ignored(rules, &path, is_dir)
+        {
+            continue;
+        }
+
+        if is_dir {
+            walk(root, &path, files, rules, packed)?;
+        } else if file_type.is_file() && files.is_none_or(|files| listed(files, &path)) {
+            packed.insert(path);
+        }
+    }
+
+    rules.truncate(inherited);
+    Ok(())
+}
+
+/// Files of the package in `dir` that go into its tarball, as sorted `/`
+/// separated paths relative to `dir`.
+///
+/// Like npm, `files` lists what to include and `.npmignore` (or `.gitignore`)
+/// files what to leave out. package.json, the README and LICENSE, `main` and
+/// the `bin` files are always included.
+pub fn packlist(dir: &Path, package_json: &PackageJson) -> Result<Vec<String>> {
+    let files: Option<Vec<Rule>> = package_json
+        .other
+        .get("files")
+        .and_then(|files| files.as_array())
+        .map(|files| {
+            files
+                .iter()
+                .filter_map(|file| file.as_str())
+                .filter_map(|file| Rule::parse(file, ""))
+                .map(|rule| Rule {
+                    anchored: true,
+                    ..rule
+                })
+                .collect()
+        });
+
+    let mut packed = BTreeSet::new();
+    walk(dir, "", files.as_deref(), &mut Vec::new(), &mut packed)?;
+
+    for entry in std::fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if matches_any(ALWAYS_INCLUDED, &name.to_lowercase()) && dir.join(&name).is_file() {
+            packed.insert(name);
+        }
+    }
+
+    let main = package_json
+        .other
+        .get("main")
+        .and_then(|main| main.as_str());
+    for path in main.into_iter().chain(bin_paths(package_json)) {
+        let path = path.strip_prefix("./").unwrap_or(path);
+        if dir.join(path).is_file() {
+            packed.insert(path.to_string());
+        }
+    }
+
+    Ok(packed.into_iter().collect())
+}
+
+/// Paths of the package's executables, relative to its root
+pub fn bin_paths(package_json: &PackageJson) -> Vec<&str> {
+    match &package_json.bin {
+        Some(PackageBin::String(path)) => vec![path.as_str()],
+        Some(PackageBin::Map(bins)) => bins.values().map(String::as_str).collect(),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(files: &[(&str, &str)], package_json: &str) -> (tempfile::TempDir, PackageJson) {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, contents) in files {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+        std::fs::write(dir.path().join("package.json"), package_json).unwrap();
+        (dir, serde_json::from_str(package_json).unwrap())
+    }
+
+    #[test]
+    fn test_ignore_files() {
+        let (dir, package_json) = package(
+            &[
+                (".gitignore", "dist\n"),
+                (".craftrc", "//registry.npmjs.org/:_authToken=secret\n"),
+                (".npmignore", "*.test.js\n/coverage\ndocs/\n!keep.test.js\n"),
+                ("index.js", ""),
+                ("index.test.js", ""),
+                ("keep.test.js", ""),
+                ("lib/util.js", ""),
+                ("lib/util.test.js", ""),
+                ("lib/coverage/report.js", ""),
+                ("coverage/lcov.info", ""),
+                ("docs/api.md", ""),
+                ("dist/index.js", ""),
+                ("node_modules/dep/index.js", ""),
+                ("craft.lock", ""),
+                ("README.md", ""),
+            ],
+            r#"{"name": "lib", "version": "1.0.0"}"#,
+        );
+
+        // .npmignore replaces .gitignore, so dist is packed
+        assert_eq!(
+            packlist(dir.path(), &package_json).unwrap(),
+            [
+                "README.md",
+                "dist/index.js",
+                "index.js",
+                "keep.test.js",
+                "lib/coverage/report.js",
+                "lib/util.js",
+                "package.json",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_files_field() {
+        let (dir, package_json) = package(
+            &[
+                (".npmignore", "dist\n"),
+                ("src/index.ts", ""),
+                ("dist/index.js", ""),
+                ("dist/index.d.ts", ""),
+                ("dist/internal/.npmignore", "*.map\n"),
+                ("dist/internal/util.js", ""),
+                ("dist/internal/util.js.map", ""),
+                ("bin/cli.js", ""),
+                ("LICENSE", ""),
+            ],
+            r#"{"name": "lib", "version": "1.0.0", "main": "./dist/index.js",
+                "files": ["dist", "!dist/*.d.ts"], "bin": {"lib": "bin/cli.js"}}"#,
+        );
+
+        assert_eq!(
+            packlist(dir.path(), &package_json).unwrap(),
+            [
+                "LICENSE",
+                "bin/cli.js",
+                "dist/index.js",
+                "dist/internal/util.js",
+                "package.json",
+            ]
+        );
+    }
+}
@@ -0,0 +1,78 @@
+//! Rewriting version ranges in package.json while keeping the user's style
+
+/// Operators kept when a range is moved to another version, longest first
+const OPERATORS: [&str; 6] = [">=", "<=", "^", "~", "=", ">"];
+
+/// Split a simple range into its operator and version (`^1.2.3` -> `^`, `1.2.3`),
+/// `None` for ranges that aren't a single comparator (`1.x`, `^1 || ^2`, `*`)
+fn split(range: &str) -> Option<(&str, &str)> {
+    let range = range.trim();
+    let operator = OPERATORS
+        .iter()
+        .find(|op| range.starts_with(**op))
+        .copied()
+        .unwrap_or("");
+    let version = range[operator.len()..].trim_start();
+
+    let is_version = !version.is_empty()
+        && version.chars().next().is_some_and(|c| c.is_ascii_digit())
+        && version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'))
+        && !version.contains(['x', 'X']);
+
+    is_version.then_some((operator, version))
+}
+
+/// Point `range` at `version`, keeping its operator: `^1.0.0` -> `^1.4.2`,
+/// `~1.0.0` -> `~1.0.5`, `1.0.0` -> `1.0.5`.
+///
+/// Returns `None` for ranges that can't be moved without changing their meaning.
+pub fn with_version(range: &str, version: &str) -> Option<String> {
+    let (operator, _) = split(range)?;
+    Some(format!("{}{}", operator, version))
+}
+
+/// Like [`with_version`], but ranges without a single version (`1.x`, `>=1 <2`)
+/// become `^version`. Ranges that already follow every release (`*`, `latest`) are kept.
+pub fn to_latest(range: &str, version: &str) -> Option<String> {
+    match range.trim() {
+        "" | "*" | "x" | "latest" => None,
+        _ => with_version(range, version).or_else(|| Some(format!("^{}", version))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_version_keeps_operator() {
+        assert_eq!(with_version("^1.0.0", "1.4.2").as_deref(), Some("^1.4.2"));
+        assert_eq!(with_version("~1.0.0", "1.0.5").as_deref(), Some("~1.0.5"));
+        assert_eq!(with_version("1.0.0", "1.0.5").as_deref(), Some("1.0.5"));
+        assert_eq!(with_version(">=1.0.0", "2.0.0").as_deref(), Some(">=2.0.0"));
+        assert_eq!(with_version("^1.2", "1.4.0").as_deref(), Some("^1.4.0"));
+        assert_eq!(
+            with_version("^1.0.0-beta.1", "1.0.0").as_deref(),
+            Some("^1.0.0")
+        );
+    }
+
+    #[test]
+    fn test_with_version_skips_complex_ranges() {
+        assert_eq!(with_version("1.x", "1.4.2"), None);
+        assert_eq!(with_version("^1.0.0 || ^2.0.0", "2.1.0"), None);
+        assert_eq!(with_version(">=1.0.0 <2.0.0", "1.5.0"), None);
+        assert_eq!(with_version("*", "1.0.0"), None);
+        assert_eq!(with_version("latest", "1.0.0"), None);
+    }
+
+    #[test]
+    fn test_to_latest() {
+        assert_eq!(to_latest("~1.0.0", "3.1.0").as_deref(), Some("~3.1.0"));
+        assert_eq!(to_latest("1.x", "3.1.0").as_deref(), Some("^3.1.0"));
+        assert_eq!(to_latest("*", "3.1.0"), None);
+        assert_eq!(to_latest("latest", "3.1.0"), None);
+    }
+}
@@ -0,0 +1,354 @@
+use contract::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::PackageJson;
+
+/// The `workspaces` field, either a list of globs or yarn's `{ "packages": [...] }`
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Workspaces {
+    Globs(Vec<String>),
+    Config {
+        packages: Vec<String>,
+        #[serde(flatten)]
+        other: std::collections::HashMap<String, serde_json::Value>,
+    },
+}
+
+impl Workspaces {
+    pub fn globs(&self) -> &[String] {
+        match self {
+            Self::Globs(globs) => globs,
+            Self::Config { packages, .. } => packages,
+        }
+    }
+}
+
+/// A package that is part of a workspace
+#[derive(Debug, Clone)]
+pub struct WorkspacePackage {
+    pub name: String,
+    pub version: String,
+    pub dir: PathBuf,
+    pub package_json: PackageJson,
+}
+
+/// A monorepo root together with every package matched by its `workspaces` globs
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    pub root: PathBuf,
+    pub package_json: PackageJson,
+    pub packages: Vec<WorkspacePackage>,
+}
+
+impl Workspace {
+    /// Load the workspace rooted at `root`, `None` if it declares no workspaces
+    pub async fn load(root: &Path) -> Result<Option<Self>> {
+        let package_json = PackageJson::from_path(&root.join("package.json")).await?;
+
+        let Some(workspaces) = &package_json.workspaces else {
+            return Ok(None);
+        };
+
+        let mut packages = Vec::new();
+        for dir in Self::match_dirs(root, workspaces.globs())? {
+            let member_json = PackageJson::from_path(&dir.join("package.json")).await?;
+            let Some(name) = member_json.name.clone() else {
+                debug::warning!("Skipping workspace package without a name: {:?}", dir);
+                continue;
+            };
+
+            packages.push(WorkspacePackage {
+                name,
+                version: member_json
+                    .version
+                    .clone()
+                    .unwrap_or_else(|| "0.0.0".to_string()),
+                dir,
+                package_json: member_json,
+            });
+        }
+
+        debug::trace!("Discovered {} workspace packages", packages.len());
+
+        Ok(Some(Self {
+            root: root.to_path_buf(),
+            package_json,
+            packages,
+        }))
+    }
+
+    /// Find the workspace containing `dir`, looking in `dir` and its ancestors.
+    ///
+    /// `dir` belongs to the package whose package.json is closest to it, and is
+    /// in a workspace when that package is the workspace root or one of its members.
+    pub async fn find(dir: &Path) -> Result<Option<Self>> {
+        let mut project: Option<&Path> = None;
+
+        for candidate in dir.ancestors() {
+            if !candidate.join("package.json").exists() {
+                continue;
+            }
+            let project = *project.get_or_insert(candidate);
+
+            if let Some(workspace) = Self::load(candidate).await?
+                && (candidate == project || workspace.packages.iter().any(|p| p.dir == project))
+            {
+                return Ok(Some(workspace));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Directories with a package.json matched by the globs, `!` globs exclude
+    fn match_dirs(root: &Path, globs: &[String]) -> Result<Vec<PathBuf>> {
+        let mut included = Vec::new();
+        let mut excluded = Vec::new();
+
+        for pattern in globs {
+            let (negated, pattern) = match pattern.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, pattern.as_str()),
+            };
+            let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+            let full_pattern = root.join(pattern).to_string_lossy().to_string();
+
+            for dir in glob::glob(&full_pattern)?.filter_map(|entry| entry.ok()) {
+                if negated {
+                    excluded.push(dir);
+                } else if dir.join("package.json").is_file()
+                    && !dir.components().any(|c| c.as_os_str() == "node_modules")
+                {
+                    included.push(dir);
+                }
+            }
+        }
+
+        included.retain(|dir| !excluded.contains(dir));
+        included.sort();
+        included.dedup();
+
+        Ok(included)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&WorkspacePackage> {
+        self.packages.iter().find(|p| p.name == name)
+    }
+
+    /// Whether a dependency should be linked to a workspace package instead of fetched.
+    ///
+    /// `workspace:` ranges always refer to the workspace, plain ranges only when
+    /// the workspace package's version satisfies them.
+    pub fn resolve_dependency(&self, name: &str, range: &str) -> Option<&WorkspacePackage> {
+        let member = self.get(name)?;
+
+        if range.starts_with("workspace:") {
+            return Some(member);
+        }
+
+        let range = node_semver::Range::parse(range).ok()?;
+        let version = node_semver::Version::parse(&member.version).ok()?;
+        range.satisfies(&version).then_some(member)
+    }
+
+    /// Workspace packages that `member` depends on directly
+    pub fn dependencies_of(&self, member: &WorkspacePackage) -> Vec<&WorkspacePackage> {
+        let json = &member.package_json;
+        let mut deps: Vec<&WorkspacePackage> = [&json.dependencies, &json.dev_dependencies]
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|(name, range)| self.resolve_dependency(name, range))
+            .filter(|dep| dep.dir != member.dir)
+            .collect();
+
+        deps.sort_by(|a, b| a.name.cmp(&b.name));
+        deps.dedup_by(|a, b| a.dir == b.dir);
+        deps
+    }
+
+    /// Workspace packages that depend directly on `member`
+    pub fn dependents_of(&self, member: &WorkspacePackage) -> Vec<&WorkspacePackage> {
+        self.packages
+            .iter()
+            .filter(|p| self.dependencies_of(p).iter().any(|d| d.dir == member.dir))
+            .collect()
+    }
+
+    /// Replace `workspace:` ranges with the versions they point to, as done when packing.
+    ///
+    /// - `workspace:*` -> `1.2.3`
+    /// - `workspace:^` -> `^1.2.3`
+    /// - `workspace:~` -> `~1.2.3`
+    /// - `workspace:^1.0.0` -> `^1.0.0`
+    pub fn rewrite_protocols(&self, package_json: &mut PackageJson) -> Result<()> {
+        for deps in [
+            package_json.dependencies.as_mut(),
+            package_json.dev_dependencies.as_mut(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            for (name, range) in deps.iter_mut() {
+                let Some(protocol) = range.strip_prefix("workspace:") else {
+                    continue;
+                };
+
+                let member = self.get(name).ok_or_else(|| {
+                    anyhow::anyhow!("{} uses {} but is not a workspace package", name, range)
+                })?;
+
+                *range = match protocol {
+                    "*" | "" => member.version.clone(),
+                    "^" | "~" => format!("{}{}", protocol, member.version),
+                    explicit => explicit.to_string(),
+                };
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_package(dir: &Path, json: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("package.json"), json).unwrap();
+    }
+
+    fn fixture() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        write_package(
+            root.path(),
+            r#"{"name": "root", "workspaces": ["packages/*", "!packages/ignored"]}"#,
+        );
+        write_package(
+            &root.path().join("packages/core"),
+            r#"{"name": "@acme/core", "version": "1.2.3"}"#,
+        );
+        write_package(
+            &root.path().join("packages/web"),
+            r#"{"name": "@acme/web", "version": "0.1.0", "dependencies": {"@acme/core": "workspace:^"}}"#,
+        );
+        write_package(
+            &root.path().join("packages/ignored"),
+            r#"{"name": "ignored", "version": "1.0.0"}"#,
+        );
+        root
+    }
+
+    #[tokio::test]
+    async fn test_load_workspace() {
+        let root = fixture();
+        let workspace = Workspace::load(root.path()).await.unwrap().unwrap();
+
+        let names: Vec<&str> = workspace.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["@acme/core", "@acme/web"]);
+        assert_eq!(workspace.get("@acme/core").unwrap().version, "1.2.3");
+    }
+
+    #[tokio::test]
+    async fn test_find_from_member_dir() {
+        let root = fixture();
+        let member = root.path().join("packages/web");
+        let workspace = Workspace::find(&member).await.unwrap().unwrap();
+        assert_eq!(workspace.root, root.path());
+
+        // Directories inside a member or the root belong to it too
+        let src = member.join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        let workspace = Workspace::find(&src).await.unwrap().unwrap();
+        assert_eq!(workspace.root, root.path());
+        let scripts = root.path().join("scripts");
+        std::fs::create_dir_all(&scripts).unwrap();
+        assert!(Workspace::find(&scripts).await.unwrap().is_some());
+
+        // A package nested in a member isn't part of the workspace
+        let nested = member.join("fixtures/app");
+        write_package(&nested, r#"{"name": "nested"}"#);
+        assert!(Workspace::find(&nested).await.unwrap().is_none());
+
+        let standalone = tempfile::tempdir().unwrap();
+        write_package(standalone.path(), r#"{"name": "app"}"#);
+        assert!(Workspace::find(standalone.path()).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_dependency() {
+        let root = fixture();
+        let workspace = Workspace::load(root.path()).await.unwrap().unwrap();
+
+        assert!(
+            workspace
+                .resolve_dependency("@acme/core", "workspace:*")
+                .is_some()
+        );
+        assert!(
+            workspace
+                .resolve_dependency("@acme/core", "^1.0.0")
+                .is_some()
+        );
+        assert!(
+            workspace
+                .resolve_dependency("@acme/core", "^2.0.0")
+                .is_none()
+        );
+        assert!(
+            workspace
+                .resolve_dependency("react", "workspace:*")
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dependency_graph() {
+        let root = fixture();
+        let workspace = Workspace::load(root.path()).await.unwrap().unwrap();
+        let core = workspace.get("@acme/core").unwrap();
+        let web = workspace.get("@acme/web").unwrap();
+
+        let deps: Vec<&str> = workspace
+            .dependencies_of(web)
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(deps, vec!["@acme/core"]);
+        assert!(workspace.dependencies_of(core).is_empty());
+
+        let dependents: Vec<&str> = workspace
+            .dependents_of(core)
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(dependents, vec!["@acme/web"]);
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_protocols() {
+        let root = fixture();
+        let workspace = Workspace::load(root.path()).await.unwrap().unwrap();
+
+        for (protocol, expected) in [
+            ("workspace:*", "1.2.3"),
+            ("workspace:^", "^1.2.3"),
+            ("workspace:~", "~1.2.3"),
+            ("workspace:>=1.0.0", ">=1.0.0"),
+        ] {
+            let mut package_json: PackageJson = serde_json::from_str(&format!(
+                r#"{{"dependencies": {{"@acme/core": "{protocol}", "react": "^19.0.0"}}}}"#
+            ))
+            .unwrap();
+
+            workspace.rewrite_protocols(&mut package_json).unwrap();
+
+            let deps = package_json.dependencies.unwrap();
+            assert_eq!(deps["@acme/core"], expected);
+            assert_eq!(deps["react"], "^19.0.0");
+        }
+    }
+}
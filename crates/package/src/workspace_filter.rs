@@ -0,0 +1,359 @@
+use contract::Result;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::{Workspace, WorkspacePackage};
+
+/// What a single selector matches before graph expansion
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    /// Package name, `*` globs allowed (`@acme/*`)
+    Name(glob::Pattern),
+    /// Directory relative to the workspace root (`./apps/**`)
+    Dir(glob::Pattern),
+}
+
+/// One `--filter` value, using pnpm's selector syntax:
+/// - `@acme/web`, `@acme/*`: packages by name
+/// - `./apps/**`, `{apps/*}`: packages by directory
+/// - `foo...` / `foo^...`: with / only its dependencies
+/// - `...foo` / `...^foo`: with / only its dependents
+/// - `!foo`: exclude the matched packages
+#[derive(Debug, Clone, PartialEq)]
+struct Selector {
+    target: Target,
+    exclude: bool,
+    dependencies: bool,
+    dependents: bool,
+    // Drop the matched packages themselves, keeping only their graph neighbours
+    skip_self: bool,
+}
+
+impl Selector {
+    fn parse(raw: &str) -> Result<Self> {
+        let (exclude, mut selector) = match raw.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, raw),
+        };
+        let mut skip_self = false;
+
+        let dependents = if let Some(rest) = selector.strip_prefix("...") {
+            selector = rest;
+            if let Some(rest) = selector.strip_prefix('^') {
+                selector = rest;
+                skip_self = true;
+            }
+            true
+        } else {
+            false
+        };
+
+        let dependencies = if let Some(rest) = selector.strip_suffix("...") {
+            selector = rest;
+            if let Some(rest) = selector.strip_suffix('^') {
+                selector = rest;
+                skip_self = true;
+            }
+            true
+        } else {
+            false
+        };
+
+        if selector.is_empty() {
+            anyhow::bail!("Invalid filter: {}", raw);
+        }
+
+        let target = if let Some(dir) = selector.strip_prefix('{').and_then(|s| s.strip_suffix('}'))
+        {
+            Target::Dir(Self::dir_pattern(dir)?)
+        } else if selector.starts_with('.') || selector.starts_with('/') {
+            Target::Dir(Self::dir_pattern(selector)?)
+        } else {
+            Target::Name(glob::Pattern::new(selector)?)
+        };
+
+        Ok(Self {
+            target,
+            exclude,
+            dependencies,
+            dependents,
+            skip_self,
+        })
+    }
+
+    fn dir_pattern(dir: &str) -> Result<glob::Pattern> {
+        let dir = dir.trim_start_matches("./").trim_end_matches('/');
+        Ok(glob::Pattern::new(if dir == "." { "" } else { dir })?)
+    }
+
+    fn matches(&self, workspace: &Workspace, member: &WorkspacePackage) -> bool {
+        match &self.target {
+            Target::Name(pattern) => pattern.matches(&member.name),
+            Target::Dir(pattern) => member
+                .dir
+                .strip_prefix(&workspace.root)
+                .is_ok_and(|relative| pattern.matches_path(relative)),
+        }
+    }
+
+    /// Directories selected by this selector, including graph expansion
+    fn select(&self, workspace: &Workspace) -> BTreeSet<PathBuf> {
+        let matched: Vec<&WorkspacePackage> = workspace
+            .packages
+            .iter()
+            .filter(|member| self.matches(workspace, member))
+            .collect();
+
+        let mut selected = BTreeSet::new();
+
+        if self.dependencies {
+            Self::walk(&matched, &mut selected, |m| workspace.dependencies_of(m));
+        }
+        if self.dependents {
+            Self::walk(&matched, &mut selected, |m| workspace.dependents_of(m));
+        }
+
+        if self.skip_self {
+            for member in &matched {
+                selected.remove(&member.dir);
+            }
+        } else {
+            selected.extend(matched.iter().map(|m| m.dir.clone()));
+        }
+
+        selected
+    }
+
+    /// Collect everything reachable from `start` through `next`, excluding `start`
+    fn walk<'a>(
+        start: &[&'a WorkspacePackage],
+        selected: &mut BTreeSet<PathBuf>,
+        next: impl Fn(&'a WorkspacePackage) -> Vec<&'a WorkspacePackage>,
+    ) {
+        let mut seen: BTreeSet<PathBuf> = BTreeSet::new();
+        let mut stack: Vec<&WorkspacePackage> = start.iter().flat_map(|m| next(m)).collect();
+
+        while let Some(member) = stack.pop() {
+            if seen.insert(member.dir.clone()) {
+                stack.extend(next(member));
+            }
+        }
+
+        selected.extend(seen);
+    }
+}
+
+/// Selects the workspace packages a command acts on (`--filter`, `-w`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkspaceFilter {
+    selectors: Vec<Selector>,
+    include_root: bool,
+}
+
+impl WorkspaceFilter {
+    pub fn parse(filters: &[String], include_root: bool) -> Result<Self> {
+        let selectors = filters
+            .iter()
+            .map(|f| Selector::parse(f))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            selectors,
+            include_root,
+        })
+    }
+
+    /// No selectors: commands act on the current directory only
+    pub fn is_empty(&self) -> bool {
+        self.selectors.is_empty() && !self.include_root
+    }
+
+    /// Directories of the selected packages, the workspace root first when `-w` is set
+    pub fn select(&self, workspace: &Workspace) -> Vec<PathBuf> {
+        let mut selected = BTreeSet::new();
+
+        let (excludes, includes): (Vec<&Selector>, Vec<&Selector>) =
+            self.selectors.iter().partition(|s| s.exclude);
+
+        // Only exclusions means "everything except"
+        if includes.is_empty() && !excludes.is_empty() {
+            selected.extend(workspace.packages.iter().map(|p| p.dir.clone()));
+        }
+        for selector in includes {
+            selected.extend(selector.select(workspace));
+        }
+        for selector in excludes {
+            for dir in selector.select(workspace) {
+                selected.remove(&dir);
+            }
+        }
+
+        let mut dirs = Vec::new();
+        if self.include_root {
+            dirs.push(workspace.root.clone());
+        }
+        dirs.extend(selected.into_iter().filter(|dir| *dir != workspace.root));
+        dirs
+    }
+
+    /// Directories a command should act on when run from `cwd`.
+    ///
+    /// Without a filter this is `cwd` itself; with one, the workspace containing
+    /// `cwd` is loaded and the filter applied to it.
+    pub async fn target_dirs(&self, cwd: &Path) -> Result<Vec<PathBuf>> {
+        if self.is_empty() {
+            return Ok(vec![cwd.to_path_buf()]);
+        }
+
+        let workspace = Workspace::find(cwd)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("--filter and -w can only be used in a workspace"))?;
+
+        let dirs = self.select(&workspace);
+        if dirs.is_empty() {
+            anyhow::bail!("No workspace packages matched the filter");
+        }
+
+        Ok(dirs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_package(dir: &Path, json: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("package.json"), json).unwrap();
+    }
+
+    /// core <- ui <- web, plus an unrelated docs app
+    async fn fixture() -> (tempfile::TempDir, Workspace) {
+        let root = tempfile::tempdir().unwrap();
+        write_package(
+            root.path(),
+            r#"{"name": "root", "workspaces": ["packages/*", "apps/*"]}"#,
+        );
+        write_package(
+            &root.path().join("packages/core"),
+            r#"{"name": "@acme/core", "version": "1.0.0"}"#,
+        );
+        write_package(
+            &root.path().join("packages/ui"),
+            r#"{"name": "@acme/ui", "version": "1.0.0", "dependencies": {"@acme/core": "workspace:*"}}"#,
+        );
+        write_package(
+            &root.path().join("apps/web"),
+            r#"{"name": "@acme/web", "version": "1.0.0", "dependencies": {"@acme/ui": "workspace:^"}}"#,
+        );
+        write_package(
+            &root.path().join("apps/docs"),
+            r#"{"name": "docs", "version": "1.0.0"}"#,
+        );
+
+        let workspace = Workspace::load(root.path()).await.unwrap().unwrap();
+        (root, workspace)
+    }
+
+    fn names(workspace: &Workspace, dirs: Vec<PathBuf>) -> Vec<String> {
+        dirs.iter()
+            .map(|dir| {
+                workspace
+                    .packages
+                    .iter()
+                    .find(|p| &p.dir == dir)
+                    .map_or("<root>".to_string(), |p| p.name.clone())
+            })
+            .collect()
+    }
+
+    fn select(workspace: &Workspace, filters: &[&str], include_root: bool) -> Vec<String> {
+        let filters: Vec<String> = filters.iter().map(|f| f.to_string()).collect();
+        let filter = WorkspaceFilter::parse(&filters, include_root).unwrap();
+        let mut selected = names(workspace, filter.select(workspace));
+        selected.sort();
+        selected
+    }
+
+    #[tokio::test]
+    async fn test_select_by_name_and_dir() {
+        let (_root, workspace) = fixture().await;
+
+        assert_eq!(select(&workspace, &["@acme/web"], false), vec!["@acme/web"]);
+        assert_eq!(
+            select(&workspace, &["@acme/*"], false),
+            vec!["@acme/core", "@acme/ui", "@acme/web"]
+        );
+        assert_eq!(
+            select(&workspace, &["./apps/**"], false),
+            vec!["@acme/web", "docs"]
+        );
+        assert_eq!(
+            select(&workspace, &["{packages/core}"], false),
+            vec!["@acme/core"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_select_graph() {
+        let (_root, workspace) = fixture().await;
+
+        assert_eq!(
+            select(&workspace, &["@acme/web..."], false),
+            vec!["@acme/core", "@acme/ui", "@acme/web"]
+        );
+        assert_eq!(
+            select(&workspace, &["@acme/web^..."], false),
+            vec!["@acme/core", "@acme/ui"]
+        );
+        assert_eq!(
+            select(&workspace, &["...@acme/core"], false),
+            vec!["@acme/core", "@acme/ui", "@acme/web"]
+        );
+        assert_eq!(
+            select(&workspace, &["...^@acme/ui"], false),
+            vec!["@acme/web"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exclude_and_root() {
+        let (_root, workspace) = fixture().await;
+
+        assert_eq!(
+            select(&workspace, &["!docs"], false),
+            vec!["@acme/core", "@acme/ui", "@acme/web"]
+        );
+        assert_eq!(
+            select(&workspace, &["@acme/*", "!@acme/ui"], false),
+            vec!["@acme/core", "@acme/web"]
+        );
+        assert_eq!(select(&workspace, &[], true), vec!["<root>"]);
+    }
+
+    #[tokio::test]
+    async fn test_target_dirs() {
+        let (root, _workspace) = fixture().await;
+
+        let empty = WorkspaceFilter::default();
+        assert!(empty.is_empty());
+        assert_eq!(
+            empty.target_dirs(root.path()).await.unwrap(),
+            vec![root.path().to_path_buf()]
+        );
+
+        let none = WorkspaceFilter::parse(&["missing".to_string()], false).unwrap();
+        assert!(none.target_dirs(root.path()).await.is_err());
+
+        let standalone = tempfile::tempdir().unwrap();
+        write_package(standalone.path(), r#"{"name": "app"}"#);
+        let filter = WorkspaceFilter::parse(&["app".to_string()], false).unwrap();
+        assert!(filter.target_dirs(standalone.path()).await.is_err());
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(WorkspaceFilter::parse(&["...".to_string()], false).is_err());
+        assert!(WorkspaceFilter::parse(&["!".to_string()], false).is_err());
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+
+use contract::Result;
+use diffy::{DiffOptions, Patch};
+use sha2::{Digest, Sha256};
+
+/// Directories inside a package that aren't part of its contents
+const IGNORED_DIRS: &[&str] = &["node_modules", ".git"];
+
+/// The file name of the missing side of an added or deleted file
+const DEV_NULL: &str = "/dev/null";
+
+/// Paths of the regular files below `dir`, relative to it
+fn files(dir: &Path) -> Result<BTreeSet<PathBuf>> {
+    let mut files = BTreeSet::new();
+    let mut pending = vec![PathBuf::new()];
+
+    while let Some(relative) = pending.pop() {
+        for entry in std::fs::read_dir(dir.join(&relative))? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            let path = relative.join(entry.file_name());
+
+            if file_type.is_dir() {
+                if !IGNORED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()) {
+                    pending.push(path);
+                }
+            } else if file_type.is_file() {
+                files.insert(path);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// A relative path with `/` separators, as it appears in a patch
+fn patch_name(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn read(path: &Path) -> Result<Option<Vec<u8>>> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// A `git diff` style patch of every file that differs between the `original`
+/// and `modified` copies of a package, empty when they're the same
+pub fn diff(original: &Path, modified: &Path) -> Result<String> {
+    let mut paths = files(original)?;
+    paths.extend(files(modified)?);
+
+    let mut patch = String::new();
+    for path in paths {
+        let before = read(&original.join(&path))?;
+        let after = read(&modified.join(&path))?;
+        if before == after {
+            continue;
+        }
+
+        let name = patch_name(&path);
+        let text = |bytes: Option<Vec<u8>>| {
+            String::from_utf8(bytes.unwrap_or_default())
+                .map_err(|_| anyhow::anyhow!("{} is a binary file, only text can be patched", name))
+        };
+
+        let mut options = DiffOptions::new();
+        options.set_original_filename(match before {
+            Some(_) => format!("a/{}", name),
+            None => DEV_NULL.to_string(),
+        });
+        options.set_modified_filename(match after {
+            Some(_) => format!("b/{}", name),
+            None => DEV_NULL.to_string(),
+        });
+
+        patch.push_str(&format!("diff --git a/{} b/{}\n", name, name));
+        if before.is_none() {
+            patch.push_str("new file mode 100644\n");
+        } else if after.is_none() {
+            patch.push_str("deleted file mode 100644\n");
+        }
+        let (before, after) = (text(before)?, text(after)?);
+        patch.push_str(&options.create_patch(&before, &after).to_string());
+    }
+
+    Ok(patch)
+}
+
+/// The parts of a patch changing one file each, split at their `diff --git` lines
+fn sections(patch: &str) -> Vec<&str> {
+    let mut starts: Vec<usize> = patch
+        .match_indices("diff --git ")
+        .map(|(i, _)| i)
+        .filter(|&i| i == 0 || patch.as_bytes()[i - 1] == b'\n')
+        .collect();
+    if starts.is_empty() && !patch.trim().is_empty() {
+        starts.push(0);
+    }
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(n, &start)| &patch[start..starts.get(n + 1).copied().unwrap_or(patch.len())])
+        .collect()
+}
+
+/// The path a patch's `a/…` or `b/…` file name refers to, `None` for `/dev/null`
+fn target(name: &str, prefix: &str) -> Result<Option<PathBuf>> {
+    if name == DEV_NULL {
+        return Ok(None);
+    }
+
+    let path = PathBuf::from(name.strip_prefix(prefix).unwrap_or(name));
+    if path.as_os_str().is_empty()
+        || path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        anyhow::bail!("Invalid path {} in patch", name);
+    }
+
+    Ok(Some(path))
+}
+
+/// Apply a patch made by [diff] to the package in `dir`
+pub fn apply(patch: &str, dir: &Path) -> Result<()> {
+    for section in sections(patch) {
+        let file_patch = Patch::from_str(section)?;
+        let (Some(original), Some(modified)) = (file_patch.original(), file_patch.modified())
+        else {
+            anyhow::bail!("Patch is missing the name of the file it changes");
+        };
+        let original = target(original, "a/")?;
+        let modified = target(modified, "b/")?;
+
+        let base = match &original {
+            Some(path) => std::fs::read_to_string(dir.join(path))
+                .map_err(|e| anyhow::anyhow!("Can't patch {}: {}", patch_name(path), e))?,
+            None => String::new(),
+        };
+        let name = modified
+            .as_ref()
+            .or(original.as_ref())
+            .map(|p| patch_name(p));
+        let patched = diffy::apply(&base, &file_patch).map_err(|e| {
+            anyhow::anyhow!("Patch doesn't apply to {}: {}", name.unwrap_or_default(), e)
+        })?;
+
+        match (&original, &modified) {
+            (_, Some(path)) => {
+                let path = dir.join(path);
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(path, patched)?;
+            }
+            (Some(path), None) => std::fs::remove_file(dir.join(path))?,
+            (None, None) => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Hex SHA-256 of a patch, identifying it in the lockfile and the store
+pub fn hash(patch: &str) -> String {
+    format!("{:x}", Sha256::digest(patch.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, files: &[(&str, &str)]) {
+        for (path, contents) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_diff_and_apply() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = dir.path().join("original");
+        let modified = dir.path().join("modified");
+        let target = dir.path().join("target");
+        for package in [&original, &modified, &target] {
+            write(
+                package,
+                &[
+                    ("index.js", "module.exports = 1;\n"),
+                    ("lib/util.js", "exports.a = 1;\nexports.b = 2;\n"),
+                    ("README.md", "# lib\n"),
+                ],
+            );
+        }
+        write(
+            &modified,
+            &[
+                ("lib/util.js", "exports.a = 1;\nexports.b = 3;\n"),
+                ("lib/new.js", "exports.c = 4;\n"),
+                ("node_modules/dep/index.js", "ignored\n"),
+            ],
+        );
+        std::fs::remove_file(modified.join("README.md")).unwrap();
+
+        let patch = diff(&original, &modified).unwrap();
+        assert!(patch.contains("diff --git a/lib/util.js b/lib/util.js\n"));
+        assert!(patch.contains("-exports.b = 2;\n+exports.b = 3;\n"));
+        assert!(patch.contains("new file mode 100644\n--- /dev/null\n+++ b/lib/new.js\n"));
+        assert!(patch.contains("deleted file mode 100644\n--- a/README.md\n+++ /dev/null\n"));
+        assert!(!patch.contains("index.js"));
+
+        apply(&patch, &target).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(target.join("lib/util.js")).unwrap(),
+            "exports.a = 1;\nexports.b = 3;\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(target.join("lib/new.js")).unwrap(),
+            "exports.c = 4;\n"
+        );
+        assert!(!target.join("README.md").exists());
+        assert_eq!(diff(&modified, &modified).unwrap(), "");
+    }
+
+    #[test]
+    fn test_apply_rejects_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), &[("index.js", "one\n")]);
+
+        let patch = "diff --git a/index.js b/index.js\n--- a/index.js\n+++ b/index.js\n@@ -1 +1 @@\n-two\n+three\n";
+        assert!(apply(patch, dir.path()).is_err());
+
+        let escape = "--- a/../index.js\n+++ b/../index.js\n@@ -1 +1 @@\n-one\n+two\n";
+        assert!(apply(escape, dir.path()).is_err());
+    }
+}
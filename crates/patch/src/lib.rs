@@ -0,0 +1,5 @@
+mod diff;
+mod patched_dependencies;
+
+pub use diff::{apply, diff, hash};
+pub use patched_dependencies::{PATCHES_DIR, PackagePatch, patch_path};
@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use contract::Result;
+use package::PackageJson;
+
+use crate::diff::hash;
+
+/// Directory of the patch files, next to the root package.json
+pub const PATCHES_DIR: &str = "patches";
+
+/// Where the patch of `name@version` is written, relative to the root package.json
+pub fn patch_path(name: &str, version: &str) -> String {
+    format!("{}/{}@{}.patch", PATCHES_DIR, name, version)
+}
+
+/// A patch from the root package.json's `patchedDependencies`, applied to one
+/// version of a package when it's extracted
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackagePatch {
+    pub name: String,
+    pub version: String,
+    pub contents: String,
+    pub hash: String,
+}
+
+impl PackagePatch {
+    pub fn new(name: String, version: String, contents: String) -> Self {
+        Self {
+            hash: hash(&contents),
+            name,
+            version,
+            contents,
+        }
+    }
+
+    /// The `name@version` key of `patchedDependencies`
+    pub fn key(&self) -> String {
+        format!("{}@{}", self.name, self.version)
+    }
+
+    /// The patches listed in `package_json`, with paths relative to `root`, by key
+    pub async fn load_all(
+        package_json: &PackageJson,
+        root: &Path,
+    ) -> Result<BTreeMap<String, PackagePatch>> {
+        let mut patches = BTreeMap::new();
+
+        for (key, path) in package_json.patched_dependencies.iter().flatten() {
+            let Some(at) = key.get(1..).and_then(|rest| rest.find('@')) else {
+                anyhow::bail!("patchedDependencies key {:?} must be name@version", key);
+            };
+            let contents = tokio::fs::read_to_string(root.join(path))
+                .await
+                .map_err(|e| anyhow::anyhow!("Can't read the patch {} of {}: {}", path, key, e))?;
+
+            let patch = Self::new(
+                key[..at + 1].to_string(),
+                key[at + 2..].to_string(),
+                contents,
+            );
+            patches.insert(patch.key(), patch);
+        }
+
+        Ok(patches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_load_all() {
+        let root = tempfile::tempdir().unwrap();
+        let path = patch_path("@scope/lib", "1.0.0");
+        std::fs::create_dir_all(root.path().join("patches/@scope")).unwrap();
+        std::fs::write(root.path().join(&path), "diff").unwrap();
+
+        let package_json: PackageJson = serde_json::from_str(&format!(
+            r#"{{"patchedDependencies": {{"@scope/lib@1.0.0": "{}"}}}}"#,
+            path
+        ))
+        .unwrap();
+        let patches = PackagePatch::load_all(&package_json, root.path())
+            .await
+            .unwrap();
+
+        let patch = &patches["@scope/lib@1.0.0"];
+        assert_eq!(patch.name, "@scope/lib");
+        assert_eq!(patch.version, "1.0.0");
+        assert_eq!(patch.hash, hash("diff"));
+
+        let missing: PackageJson =
+            serde_json::from_str(r#"{"patchedDependencies": {"lib@1.0.0": "patches/none"}}"#)
+                .unwrap();
+        assert!(PackagePatch::load_all(&missing, root.path()).await.is_err());
+    }
+}
@@ -1,18 +1,32 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
 
-use contract::{Pipeline, Result, get_package_cache_dir};
+use contract::{Pipeline, PipelineError, Result};
 use futures::stream::{self, StreamExt};
-use package::InstallPackage;
-use resolver::{ResolvedArtifact, Resolver};
-use tarball::gzip::unzip;
+use network::Transport;
+use node_semver::{Range, Version};
+use package::{InstallPackage, Overrides};
+use patch::PackagePatch;
+use resolver::{ArtifactSource, ResolvedArtifact, Resolver};
+use tarball::gzip::unpack;
 use tokio::sync::Mutex;
 
 type LockedPackage = Arc<Mutex<HashMap<String, Arc<Mutex<Option<ResolvedArtifact>>>>>>;
 
+/// `(name, version)` of the packages from a direct dependency down to the one
+/// being resolved
+type Chain = Vec<(String, String)>;
+
 pub struct InstallPipe {
     packages: Vec<InstallPackage>,
     resolver: Resolver,
+    overrides: Overrides,
+    // Key and spec of the overrides that replaced a range
+    applied_overrides: Arc<Mutex<BTreeMap<String, String>>>,
+    patches: BTreeMap<String, PackagePatch>,
+    // Versions to keep, by name, while the range of a dependency allows them
+    locked: HashMap<String, Vec<String>>,
     // - None means resolution is in progress
     // - Some(artifact) means resolution is complete
     locked_packages: LockedPackage,
@@ -25,16 +39,140 @@ impl InstallPipe {
         Self {
             packages,
             resolver: Resolver::new(),
+            overrides: Overrides::new(),
+            applied_overrides: Arc::new(Mutex::new(BTreeMap::new())),
+            patches: BTreeMap::new(),
+            locked: HashMap::new(),
             locked_packages: Arc::new(Mutex::new(HashMap::new())),
             unzip_locks: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    /// Fetch packages through `transport` instead of a new [network::Network]
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.resolver = self.resolver.with_transport(transport);
+        self
+    }
+
+    /// Resolve registry packages against `registry` instead of the npm registry
+    pub fn with_registry(mut self, registry: String) -> Self {
+        self.resolver = self.resolver.with_registry(registry);
+        self
+    }
+
+    /// Keep downloaded and extracted packages in `dir` instead of the global
+    /// package cache
+    pub fn with_cache_dir(mut self, dir: PathBuf) -> Self {
+        self.resolver = self.resolver.with_cache_dir(dir);
+        self
+    }
+
+    /// Copy registry tarballs to the offline mirror in `dir`, and install from
+    /// it before the network
+    pub fn with_offline_mirror(mut self, dir: PathBuf) -> Self {
+        self.resolver = self.resolver.with_offline_mirror(dir);
+        self
+    }
+
+    /// Install registry packages only from the offline mirror
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.resolver = self.resolver.with_offline(offline);
+        self
+    }
+
+    /// Replace the ranges of transitive dependencies matching `overrides`
+    pub fn with_overrides(mut self, overrides: Overrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Apply `patches`, by `name@version`, to the packages they're for
+    pub fn with_patches(mut self, patches: BTreeMap<String, PackagePatch>) -> Self {
+        self.patches = patches;
+        self
+    }
+
+    /// Resolve registry dependencies to one of the `locked` versions of their name,
+    /// e.g. from the lockfile, when it satisfies their range, instead of the highest
+    pub fn with_locked(mut self, locked: HashMap<String, Vec<String>>) -> Self {
+        self.locked = locked;
+        self
+    }
+
+    /// The overrides that replaced the range of a dependency during [Pipeline::run],
+    /// by key, to record in the lockfile
+    pub async fn applied_overrides(&self) -> BTreeMap<String, String> {
+        self.applied_overrides.lock().await.clone()
+    }
+
+    /// The spec a dependency on `name@range` of the last package in `chain` is
+    /// resolved with, when an override applies to it
+    async fn override_spec(
+        &self,
+        name: &str,
+        range: &str,
+        chain: &Chain,
+    ) -> Result<Option<String>> {
+        for o in self.overrides.matching(name, chain) {
+            if o.spec == range {
+                return Ok(None);
+            }
+            // `name@range` overrides only replace the versions in that range
+            if o.target.range.is_some() {
+                let package = InstallPackage::new(name.to_string(), Some(range.to_string()), false);
+                let resolved = self.resolver.resolve(&package).await?;
+                if !o.target.matches_version(&resolved.version) {
+                    continue;
+                }
+            }
+
+            debug::info!(
+                "Overriding {}@{} with {} ({})",
+                name,
+                range,
+                o.spec,
+                o.key()
+            );
+            self.applied_overrides
+                .lock()
+                .await
+                .insert(o.key(), o.spec.clone());
+            return Ok(Some(o.spec.clone()));
+        }
+
+        Ok(None)
+    }
+
+    /// `package` pinned to the highest locked version its range allows, if any
+    fn locked_package(&self, package: &InstallPackage) -> Option<InstallPackage> {
+        if !package.is_registry() {
+            return None;
+        }
+        let range = Range::parse(package.version.as_deref()?).ok()?;
+        let version = self
+            .locked
+            .get(&package.name)?
+            .iter()
+            .filter_map(|v| Version::parse(v).ok())
+            .filter(|v| range.satisfies(v))
+            .max()?;
+
+        let mut locked = package.clone();
+        locked.version = Some(version.to_string());
+        Some(locked)
+    }
+
     #[async_recursion::async_recursion]
-    async fn resolve_package(&self, package: &InstallPackage) -> Result<()> {
+    async fn resolve_package(&self, package: &InstallPackage, chain: &Chain) -> Result<()> {
         debug::info!("Resolving package: {package:?}");
 
-        let cache_key = package.to_cache_key();
+        // Overrides scoped to parents may apply below the package on one path and
+        // not on another, so it's resolved once per scope
+        let scope = self.overrides.scope(chain);
+        let cache_key = match &scope {
+            Some(scope) => format!("{}#{}", package.to_cache_key(), scope),
+            None => package.to_cache_key(),
+        };
 
         // Get or create a lock for this specific package
         let package_lock = {
@@ -62,7 +200,46 @@ impl InstallPipe {
 
         // This thread won the race - do the actual work
         debug::info!("This thread will resolve {}", cache_key);
-        let artifact = self.resolver.resolve(package).await?;
+        let mut artifact = match self.locked_package(package) {
+            Some(locked) => self.resolver.resolve(&locked).await?,
+            None => self.resolver.resolve(package).await?,
+        };
+
+        // Overrides rewrite the declared ranges, so linking and the lockfile use them too
+        let mut chain = chain.clone();
+        chain.push((artifact.name.clone(), artifact.version.clone()));
+        artifact.scope = scope;
+        artifact.dependency_scope = self.overrides.scope(&chain);
+        if !self.overrides.is_empty()
+            && artifact.installs_dependencies()
+            && let Some(deps) = artifact
+                .package
+                .as_mut()
+                .and_then(|p| p.dependencies.as_mut())
+        {
+            for (name, range) in deps.iter_mut() {
+                if let Some(spec) = self.override_spec(name, range, &chain).await? {
+                    *range = spec;
+                }
+            }
+        }
+
+        let patch = self
+            .patches
+            .get(&format!("{}@{}", artifact.name, artifact.version));
+        if let Some(patch) = patch {
+            if artifact.source != ArtifactSource::Registry {
+                return Err(PipelineError::PatchError {
+                    message: format!(
+                        "{} isn't a registry package, only those can be patched",
+                        patch.key()
+                    ),
+                }
+                .into());
+            }
+            artifact.patch = Some(patch.hash.clone());
+        }
+
         let download_artifact = self.resolver.download(&artifact).await?;
 
         // Store the result so other threads can use it
@@ -72,7 +249,7 @@ impl InstallPipe {
         drop(artifact_slot);
 
         // Synchronize unzipping
-        let unzip_key = format!("{}@{}", artifact.name, artifact.version);
+        let unzip_key = artifact.cache_dir().to_string_lossy().to_string();
         let unzip_lock = {
             let mut locks = self.unzip_locks.lock().await;
             locks
@@ -82,27 +259,54 @@ impl InstallPipe {
         };
         let _unzip_guard = unzip_lock.lock().await;
 
-        let unzip_dir =
-            get_package_cache_dir().join(format!("{}-{}", artifact.name, artifact.version));
+        let unzip_dir = artifact.cache_dir();
+
+        if !artifact.installs_dependencies() {
+            // Linked packages are used in place and bring their own dependencies
+            debug::info!("Package {} is linked, skipping extraction", artifact.name);
+            return Ok(());
+        } else if !unzip_dir.exists() {
+            unpack(download_artifact.path, unzip_dir.clone()).await?;
 
-        if !unzip_dir.exists() {
-            unzip(download_artifact.path, unzip_dir).await?;
+            if let Some(patch) = patch {
+                debug::info!("Applying patch {} to {}", patch.hash, artifact.name);
+                let contents = patch.contents.clone();
+                let package_dir = artifact.package_dir();
+                let applied =
+                    tokio::task::spawn_blocking(move || patch::apply(&contents, &package_dir))
+                        .await?;
+                if let Err(e) = applied {
+                    // Don't leave a half patched entry behind for the next install
+                    tokio::fs::remove_dir_all(&unzip_dir).await?;
+                    return Err(PipelineError::PatchError {
+                        message: format!("{}: {}", patch.key(), e),
+                    }
+                    .into());
+                }
+            }
         } else {
             debug::info!("Package {} already unzipped", artifact.name);
         }
         drop(_unzip_guard);
 
+        let source_dir = artifact.source_dir();
         if let Some(deps) = artifact.package.and_then(|p| p.dependencies) {
             debug::info!("Installing dependencies for {}: {:?}", artifact.name, deps);
 
             let dep_packages: Vec<InstallPackage> = deps
                 .into_iter()
-                .map(|(name, version)| InstallPackage::new(name, Some(version), false))
+                .map(|(name, version)| {
+                    InstallPackage::new(name, Some(version), false)
+                        .with_base_dir(source_dir.clone())
+                })
                 .collect();
 
             // Process dependencies in parallel
             let results: Vec<Result<()>> = stream::iter(dep_packages)
-                .map(|pkg| async move { self.resolve_package(&pkg).await })
+                .map(|pkg| {
+                    let chain = &chain;
+                    async move { self.resolve_package(&pkg, chain).await }
+                })
                 .buffer_unordered(10) // Concurrency limit for dependencies
                 .collect()
                 .await;
@@ -136,7 +340,7 @@ impl Pipeline<Vec<ResolvedArtifact>> for InstallPipe {
 
         // Process packages in parallel with dynamic concurrency limit
         let results: Vec<Result<()>> = stream::iter(packages)
-            .map(|pkg| async move { self.resolve_package(&pkg).await })
+            .map(|pkg| async move { self.resolve_package(&pkg, &Chain::new()).await })
             .buffer_unordered(concurrency)
             .collect()
             .await;
@@ -154,14 +358,288 @@ impl Pipeline<Vec<ResolvedArtifact>> for InstallPipe {
         for lock in locked.values() {
             let artifact_guard = lock.lock().await;
             if let Some(artifact) = artifact_guard.as_ref() {
-                let key = format!("{}@{}", artifact.name, artifact.version);
+                let key = (
+                    artifact.name.clone(),
+                    artifact.version.clone(),
+                    artifact.scope.clone(),
+                );
                 if !seen.contains(&key) {
                     seen.insert(key);
                     artifacts.push(artifact.clone());
                 }
             }
         }
+        // Unscoped copies first, the ones the lockfile records
+        artifacts
+            .sort_by(|a, b| (&a.name, &a.version, &a.scope).cmp(&(&b.name, &b.version, &b.scope)));
 
         Ok(artifacts)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LinkerPipe, LockfilePipe};
+    use contract::LOCKFILE_NAME;
+    use lockfile::Lockfile;
+    use registry::MockRegistry;
+
+    #[tokio::test]
+    async fn test_install_link_and_lock_from_mock_registry() {
+        let registry = MockRegistry::start().unwrap();
+        registry
+            .add(
+                "lib",
+                "1.0.0",
+                serde_json::json!({ "dependencies": { "dep": "^1.0.0" } }),
+            )
+            .await
+            .unwrap();
+        for version in ["1.0.0", "1.1.0", "2.0.0"] {
+            registry
+                .add("dep", version, serde_json::json!({}))
+                .await
+                .unwrap();
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("cache");
+        let project = dir.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+
+        let packages = vec![InstallPackage::new(
+            "lib".to_string(),
+            Some("^1.0.0".to_string()),
+            false,
+        )];
+        let artifacts = InstallPipe::new(packages.clone())
+            .with_registry(registry.url().to_string())
+            .with_cache_dir(cache.clone())
+            .run()
+            .await
+            .unwrap();
+
+        let mut installed: Vec<String> = artifacts
+            .iter()
+            .map(|a| format!("{}@{}", a.name, a.version))
+            .collect();
+        installed.sort();
+        assert_eq!(installed, ["dep@1.1.0", "lib@1.0.0"]);
+        assert!(cache.join("lib-1.0.0.tgz").exists());
+        assert!(cache.join("dep-1.1.0/package/package.json").exists());
+
+        LinkerPipe::new(artifacts.clone(), packages)
+            .with_project_dir(project.clone())
+            .run()
+            .await
+            .unwrap();
+        assert!(project.join("node_modules/lib/index.js").exists());
+        let dep: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(
+                project.join("node_modules/lib/node_modules/dep/package.json"),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(dep["version"], "1.1.0");
+
+        LockfilePipe::new(artifacts)
+            .with_project_dir(project.clone())
+            .run()
+            .await
+            .unwrap();
+        let lockfile = Lockfile::load(&project.join(LOCKFILE_NAME)).await.unwrap();
+        let lib = lockfile.get_package("lib", "1.0.0").unwrap();
+        assert_eq!(
+            lib.resolved,
+            format!("{}/lib/-/lib-1.0.0.tgz", registry.url())
+        );
+        assert!(lockfile.has_package("dep", "1.1.0"));
+        assert!(!lockfile.has_package("dep", "2.0.0"));
+    }
+
+    #[tokio::test]
+    async fn test_records_only_applied_overrides() {
+        let registry = MockRegistry::start().unwrap();
+        registry
+            .add(
+                "lib",
+                "1.0.0",
+                serde_json::json!({ "dependencies": { "dep": "^1.0.0" } }),
+            )
+            .await
+            .unwrap();
+        for version in ["1.0.0", "2.0.0"] {
+            registry
+                .add("dep", version, serde_json::json!({}))
+                .await
+                .unwrap();
+        }
+
+        let package_json: package::PackageJson = serde_json::from_str(
+            r#"{"overrides": {"dep": "2.0.0", "unused": "1.0.0", "lib>other": "1.0.0"}}"#,
+        )
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let install_pipe = InstallPipe::new(vec![InstallPackage::new(
+            "lib".to_string(),
+            Some("^1.0.0".to_string()),
+            false,
+        )])
+        .with_registry(registry.url().to_string())
+        .with_cache_dir(dir.path().to_path_buf())
+        .with_overrides(Overrides::from_package_json(&package_json).unwrap());
+
+        let artifacts = install_pipe.run().await.unwrap();
+        assert!(
+            artifacts
+                .iter()
+                .any(|a| a.name == "dep" && a.version == "2.0.0")
+        );
+        assert_eq!(
+            install_pipe.applied_overrides().await,
+            BTreeMap::from([("dep".to_string(), "2.0.0".to_string())])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_locked_versions_are_kept_within_range() {
+        let registry = MockRegistry::start().unwrap();
+        registry
+            .add(
+                "lib",
+                "1.0.0",
+                serde_json::json!({ "dependencies": { "dep": "^1.0.0" } }),
+            )
+            .await
+            .unwrap();
+        for version in ["1.0.0", "1.1.0", "2.0.0"] {
+            registry
+                .add("dep", version, serde_json::json!({}))
+                .await
+                .unwrap();
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let packages = vec![
+            InstallPackage::new("lib".to_string(), Some("^1.0.0".to_string()), false),
+            InstallPackage::new("dep".to_string(), Some("^2.0.0".to_string()), false),
+        ];
+        // 2.0.0 isn't locked, it's resolved as usual
+        let artifacts = InstallPipe::new(packages)
+            .with_registry(registry.url().to_string())
+            .with_cache_dir(dir.path().to_path_buf())
+            .with_locked(HashMap::from([(
+                "dep".to_string(),
+                vec!["1.0.0".to_string(), "3.0.0".to_string()],
+            )]))
+            .run()
+            .await
+            .unwrap();
+
+        let versions: Vec<&str> = artifacts
+            .iter()
+            .filter(|a| a.name == "dep")
+            .map(|a| a.version.as_str())
+            .collect();
+        assert_eq!(versions, vec!["1.0.0", "2.0.0"]);
+    }
+
+    #[tokio::test]
+    async fn test_scoped_overrides_apply_per_path() {
+        let registry = MockRegistry::start().unwrap();
+        for name in ["a", "b"] {
+            registry
+                .add(
+                    name,
+                    "1.0.0",
+                    serde_json::json!({ "dependencies": { "mid": "^1.0.0" } }),
+                )
+                .await
+                .unwrap();
+        }
+        registry
+            .add(
+                "mid",
+                "1.0.0",
+                serde_json::json!({ "dependencies": { "dep": "^1.0.0" } }),
+            )
+            .await
+            .unwrap();
+        for version in ["1.0.0", "2.0.0"] {
+            registry
+                .add("dep", version, serde_json::json!({}))
+                .await
+                .unwrap();
+        }
+
+        // dep is only overridden below a, mid is shared by a and b
+        let package_json: package::PackageJson =
+            serde_json::from_str(r#"{"overrides": {"a": {"dep": "2.0.0"}}}"#).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        let packages: Vec<InstallPackage> = ["a", "b"]
+            .into_iter()
+            .map(|name| InstallPackage::new(name.to_string(), Some("^1.0.0".to_string()), false))
+            .collect();
+        let artifacts = InstallPipe::new(packages.clone())
+            .with_registry(registry.url().to_string())
+            .with_cache_dir(dir.path().join("cache"))
+            .with_overrides(Overrides::from_package_json(&package_json).unwrap())
+            .run()
+            .await
+            .unwrap();
+        LinkerPipe::new(artifacts, packages)
+            .with_project_dir(project.clone())
+            .run()
+            .await
+            .unwrap();
+
+        let dep_version = |parent: &str| {
+            let path = project
+                .join("node_modules")
+                .join(parent)
+                .join("node_modules/mid/node_modules/dep/package.json");
+            let package_json: package::PackageJson =
+                serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+            package_json.version.unwrap()
+        };
+        assert_eq!(dep_version("a"), "2.0.0");
+        assert_eq!(dep_version("b"), "1.0.0");
+    }
+
+    #[tokio::test]
+    async fn test_relative_file_specs_resolve_from_declaring_package() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        let vendor = project.join("vendor");
+        for (name, manifest) in [
+            (
+                "a",
+                r#"{"name": "a", "version": "1.0.0", "dependencies": {"b": "file:../b"}}"#,
+            ),
+            ("b", r#"{"name": "b", "version": "2.0.0"}"#),
+        ] {
+            std::fs::create_dir_all(vendor.join(name)).unwrap();
+            std::fs::write(vendor.join(name).join("package.json"), manifest).unwrap();
+        }
+
+        let packages = vec![
+            InstallPackage::new("a".to_string(), Some("file:vendor/a".to_string()), false)
+                .with_base_dir(project.clone()),
+        ];
+        let artifacts = InstallPipe::new(packages)
+            .with_cache_dir(dir.path().join("cache"))
+            .run()
+            .await
+            .unwrap();
+
+        let b = artifacts.iter().find(|a| a.name == "b").unwrap();
+        assert_eq!(b.version, "2.0.0");
+        let ArtifactSource::File { path, .. } = &b.source else {
+            panic!("b should come from a local directory");
+        };
+        assert_eq!(path, &std::path::absolute(vendor.join("a/../b")).unwrap());
+    }
+}
@@ -0,0 +1,636 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use contract::{DEFAULT_REGISTRY, Pipeline, PipelineError, Result};
+use package::PackageJson;
+use process::{LifecycleEnv, Process};
+use resolver::{ArtifactSource, ResolvedArtifact};
+use sha2::{Digest, Sha256};
+
+/// Install-time lifecycle stages, in the order npm runs them
+const INSTALL_EVENTS: [&str; 3] = ["preinstall", "install", "postinstall"];
+
+/// Written next to a build's package directory once its scripts succeeded,
+/// so builds are not redone on every install
+const BUILT_MARKER: &str = ".craft-built";
+
+/// Runs `preinstall`, `install` and `postinstall` scripts of installed packages.
+///
+/// Only packages named in the project's `trustedDependencies` are built, the
+/// others are returned so the caller can report them as skipped. Packages are
+/// built after the dependencies they need, with the package's and the project's
+/// `node_modules/.bin` on `PATH`.
+///
+/// Scripts never run in the shared package cache. Each build works on a copy in
+/// the project's `node_modules/.craft/builds`, keyed by the package, its scripts,
+/// the platform and the dependencies it was built against. Node resolves modules
+/// from where their files really are, so packages depending on a build are
+/// copied there too, and the project's links point to the copies.
+pub struct LifecyclePipe {
+    artifacts: Vec<ResolvedArtifact>,
+    trusted: Vec<String>,
+    project_dir: Option<PathBuf>,
+    workspace_dirs: Vec<PathBuf>,
+    registry: String,
+}
+
+impl LifecyclePipe {
+    pub fn new(artifacts: Vec<ResolvedArtifact>, trusted: Vec<String>) -> Self {
+        Self {
+            artifacts,
+            trusted,
+            project_dir: None,
+            workspace_dirs: Vec::new(),
+            registry: DEFAULT_REGISTRY.to_string(),
+        }
+    }
+
+    /// Project whose `node_modules/.bin` is put on `PATH` and which keeps the
+    /// builds, defaults to the current directory
+    pub fn with_project_dir(mut self, dir: PathBuf) -> Self {
+        self.project_dir = Some(dir);
+        self
+    }
+
+    /// Also point the links of the workspace package at `dir` to the builds
+    pub fn with_workspace_package(mut self, dir: PathBuf) -> Self {
+        self.workspace_dirs.push(dir);
+        self
+    }
+
+    /// Registry passed to scripts as `npm_config_registry`, defaults to the npm registry
+    pub fn with_registry(mut self, registry: String) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Install scripts of a package, with npm's implicit `node-gyp rebuild`
+    /// for native addons that don't declare their own
+    fn install_scripts(package_json: &PackageJson, package_dir: &Path) -> Vec<(String, String)> {
+        let scripts = package_json.scripts.clone().unwrap_or_default();
+        let mut install_scripts: Vec<(String, String)> = INSTALL_EVENTS
+            .iter()
+            .filter_map(|event| {
+                scripts
+                    .get(*event)
+                    .map(|script| (event.to_string(), script.clone()))
+            })
+            .collect();
+
+        let has_install = scripts.contains_key("install") || scripts.contains_key("preinstall");
+        if !has_install && package_dir.join("binding.gyp").exists() {
+            let position = usize::from(scripts.contains_key("preinstall"));
+            install_scripts.insert(
+                position,
+                ("install".to_string(), "node-gyp rebuild".to_string()),
+            );
+        }
+
+        install_scripts
+    }
+
+    /// Artifacts ordered so that every package comes after its dependencies
+    fn build_order(&self) -> Vec<&ResolvedArtifact> {
+        let mut by_name: HashMap<&str, Vec<&ResolvedArtifact>> = HashMap::new();
+        for artifact in &self.artifacts {
+            by_name.entry(&artifact.name).or_default().push(artifact);
+        }
+
+        fn visit<'a>(
+            artifact: &'a ResolvedArtifact,
+            by_name: &HashMap<&str, Vec<&'a ResolvedArtifact>>,
+            visited: &mut HashSet<PathBuf>,
+            order: &mut Vec<&'a ResolvedArtifact>,
+        ) {
+            // Copies of a package for other override scopes are built too
+            if !visited.insert(artifact.cache_dir()) {
+                return;
+            }
+
+            let mut deps: Vec<&String> = artifact
+                .package
+                .as_ref()
+                .and_then(|p| p.dependencies.as_ref())
+                .map(|deps| deps.keys().collect())
+                .unwrap_or_default();
+            deps.sort();
+
+            for dep in deps {
+                for dep_artifact in by_name.get(dep.as_str()).into_iter().flatten() {
+                    visit(dep_artifact, by_name, visited, order);
+                }
+            }
+
+            order.push(artifact);
+        }
+
+        let mut sorted: Vec<&ResolvedArtifact> = self.artifacts.iter().collect();
+        sorted.sort_by_key(|a| a.to_cache_key());
+
+        let mut visited = HashSet::new();
+        let mut order = Vec::new();
+        for artifact in sorted {
+            visit(artifact, &by_name, &mut visited, &mut order);
+        }
+
+        order
+    }
+
+    async fn build(
+        &self,
+        artifact: &ResolvedArtifact,
+        package_json: &PackageJson,
+        scripts: Vec<(String, String)>,
+        package_dir: &Path,
+        project_dir: &Path,
+    ) -> Result<()> {
+        for (event, script) in scripts {
+            debug::info!("Running {} script of {}: {}", event, artifact.name, script);
+
+            let env = LifecycleEnv::new(&event, &script)
+                .with_package(
+                    package_json.name.as_deref(),
+                    package_json.version.as_deref(),
+                )
+                .with_package_json(package_dir.join("package.json"))
+                .with_init_cwd(project_dir.to_path_buf())
+                .with_command("install")
+                .with_config("registry", &self.registry)
+                .with_bin_dir(package_dir.join("node_modules").join(".bin"))
+                .with_bin_dir(project_dir.join("node_modules").join(".bin"));
+
+            let prefix = format!("{}@{} {} |", artifact.name, artifact.version, event);
+            let status = env
+                .apply(Process::script(&script).current_dir(&package_dir.to_string_lossy()))
+                .run_prefixed(&prefix, false)
+                .await?;
+
+            if !status.success() {
+                return Err(PipelineError::ScriptError {
+                    message: format!(
+                        "{} script of {}@{} failed with {}",
+                        event, artifact.name, artifact.version, status
+                    ),
+                }
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Pipeline<Vec<String>> for LifecyclePipe {
+    /// Build trusted packages, returning `name@version` of those that were skipped
+    async fn run(&self) -> Result<Vec<String>> {
+        let project_dir = match &self.project_dir {
+            Some(dir) => dir.clone(),
+            None => std::env::current_dir()?,
+        };
+        let builds_dir = project_dir
+            .join("node_modules")
+            .join(".craft")
+            .join("builds");
+        let mut skipped = Vec::new();
+        // Package directories in the cache and their copies in the project
+        let mut copies: HashMap<PathBuf, PathBuf> = HashMap::new();
+
+        for artifact in self.build_order() {
+            // Linked packages are the user's own code, built by the user
+            if !artifact.installs_dependencies() {
+                continue;
+            }
+
+            let package_dir = artifact.package_dir();
+            let Ok(package_json) = PackageJson::from_path(&package_dir.join("package.json")).await
+            else {
+                continue;
+            };
+
+            let mut scripts = Self::install_scripts(&package_json, &package_dir);
+            if !scripts.is_empty() && !self.trusted.contains(&artifact.name) {
+                let key = format!("{}@{}", artifact.name, artifact.version);
+                if !skipped.contains(&key) {
+                    skipped.push(key);
+                }
+                scripts.clear();
+            }
+
+            let links = dependency_links(&package_dir).await?;
+            let depends_on_copy = links.iter().any(|(_, target)| copies.contains_key(target));
+            if scripts.is_empty() && !depends_on_copy {
+                continue;
+            }
+
+            let build_dir = builds_dir.join(build_key(artifact, &scripts, &links, &copies));
+            let copy = build_dir.join("package");
+            let marker = build_dir.join(BUILT_MARKER);
+
+            // Local copies are refreshed on every install, everything else is immutable
+            let is_file = matches!(artifact.source, ArtifactSource::File { .. });
+            if !is_file && marker.exists() {
+                debug::info!("{} already built", artifact.name);
+            } else {
+                if build_dir.exists() {
+                    tokio::fs::remove_dir_all(&build_dir).await?;
+                }
+                let (from, to, targets) = (package_dir.clone(), copy.clone(), copies.clone());
+                tokio::task::spawn_blocking(move || copy_package(&from, &to, &targets)).await??;
+
+                self.build(artifact, &package_json, scripts, &copy, &project_dir)
+                    .await?;
+                tokio::fs::write(&marker, "").await?;
+            }
+
+            copies.insert(package_dir, copy);
+        }
+
+        for dir in std::iter::once(&project_dir).chain(&self.workspace_dirs) {
+            relink(&dir.join("node_modules"), &copies).await?;
+        }
+
+        Ok(skipped)
+    }
+}
+
+/// The dependencies linked into `package_dir/node_modules`, by name, with the
+/// directories they link to
+async fn dependency_links(package_dir: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut links = Vec::new();
+    let node_modules = package_dir.join("node_modules");
+    let mut dirs = vec![PathBuf::new()];
+
+    while let Some(scope) = dirs.pop() {
+        let Ok(mut entries) = tokio::fs::read_dir(node_modules.join(&scope)).await else {
+            continue;
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let name = scope.join(entry.file_name());
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if entry.file_type().await?.is_symlink() {
+                links.push((name, tokio::fs::read_link(entry.path()).await?));
+            } else if scope.as_os_str().is_empty() && file_name.starts_with('@') {
+                dirs.push(name);
+            }
+        }
+    }
+
+    links.sort();
+    Ok(links)
+}
+
+/// Name of the build of `artifact`: what it's built from, how, where, and
+/// against which dependencies
+fn build_key(
+    artifact: &ResolvedArtifact,
+    scripts: &[(String, String)],
+    links: &[(PathBuf, PathBuf)],
+    copies: &HashMap<PathBuf, PathBuf>,
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(artifact.cache_dir().to_string_lossy().as_bytes());
+    hasher.update(format!(
+        "\0{}-{}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    ));
+    for (event, script) in scripts {
+        hasher.update(format!("\0{}={}", event, script));
+    }
+    for (name, target) in links {
+        let target = copies.get(target).unwrap_or(target);
+        hasher.update(format!("\0{}={}", name.display(), target.display()));
+    }
+
+    let hash: String = hasher
+        .finalize()
+        .iter()
+        .take(8)
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!(
+        "{}-{}-{}",
+        artifact.name.replace('/', "+"),
+        artifact.version,
+        hash
+    )
+}
+
+/// Copy the package at `from` to `to`. Its dependencies are linked again,
+/// to their copy in `copies` when they have one, and bundled ones are copied.
+fn copy_package(from: &Path, to: &Path, copies: &HashMap<PathBuf, PathBuf>) -> std::io::Result<()> {
+    copy_files(from, to)?;
+
+    let node_modules = from.join("node_modules");
+    let Ok(entries) = std::fs::read_dir(&node_modules) else {
+        return Ok(());
+    };
+    for entry in entries {
+        let entry = entry?;
+        let name = PathBuf::from(entry.file_name());
+        let file_type = entry.file_type()?;
+        let is_group = name == Path::new(".bin") || name.to_string_lossy().starts_with('@');
+
+        if file_type.is_symlink() {
+            link_dependency(from, to, &name, copies)?;
+        } else if file_type.is_dir() && is_group {
+            // Scopes and `.bin`
+            for child in std::fs::read_dir(entry.path())? {
+                let child = child?;
+                let name = name.join(child.file_name());
+                if child.file_type()?.is_symlink() {
+                    link_dependency(from, to, &name, copies)?;
+                } else {
+                    copy_tree(&child.path(), &to.join("node_modules").join(&name))?;
+                }
+            }
+        } else {
+            copy_tree(&entry.path(), &to.join("node_modules").join(&name))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Link `node_modules/<name>` of the copy at `to` like the one of `from`
+fn link_dependency(
+    from: &Path,
+    to: &Path,
+    name: &Path,
+    copies: &HashMap<PathBuf, PathBuf>,
+) -> std::io::Result<()> {
+    let target = std::fs::read_link(from.join("node_modules").join(name))?;
+    // Binaries point into the package's own node_modules
+    let target = match (copies.get(&target), target.strip_prefix(from)) {
+        (Some(copy), _) => copy.clone(),
+        (None, Ok(relative)) => to.join(relative),
+        (None, Err(_)) => target,
+    };
+    let link = to.join("node_modules").join(name);
+    if let Some(parent) = link.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    symlink(&target, &link)
+}
+
+/// Copy the file or directory at `from` as is, links included
+fn copy_tree(from: &Path, to: &Path) -> std::io::Result<()> {
+    let file_type = std::fs::symlink_metadata(from)?.file_type();
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    if file_type.is_symlink() {
+        symlink(&std::fs::read_link(from)?, to)
+    } else if file_type.is_dir() {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_tree(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        std::fs::copy(from, to).map(|_| ())
+    }
+}
+
+/// Copy the files of a package, without its `node_modules`
+fn copy_files(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_name() == "node_modules" {
+            continue;
+        }
+
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_files(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Point the links in `node_modules` that lead to a copied package to its copy
+async fn relink(node_modules: &Path, copies: &HashMap<PathBuf, PathBuf>) -> Result<()> {
+    let Ok(mut entries) = tokio::fs::read_dir(node_modules).await else {
+        return Ok(());
+    };
+    let mut links = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_type().await?.is_symlink() {
+            links.push(entry.path());
+        } else if entry.file_name().to_string_lossy().starts_with('@') {
+            let mut scoped = tokio::fs::read_dir(entry.path()).await?;
+            while let Some(entry) = scoped.next_entry().await? {
+                links.push(entry.path());
+            }
+        }
+    }
+
+    for link in links {
+        let Ok(target) = tokio::fs::read_link(&link).await else {
+            continue;
+        };
+        if let Some(copy) = copies.get(&target) {
+            debug::info!("Linking {:?} to its build {:?}", link, copy);
+            tokio::fs::remove_file(&link).await?;
+            symlink(copy, &link)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(target, link);
+    #[cfg(windows)]
+    return if target.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifact(name: &str, deps: &[&str]) -> ResolvedArtifact {
+        let dependencies = deps
+            .iter()
+            .map(|dep| (dep.to_string(), "*".to_string()))
+            .collect();
+
+        ResolvedArtifact {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            download_url: String::new(),
+            package: Some(PackageJson {
+                name: Some(name.to_string()),
+                dependencies: Some(dependencies),
+                ..Default::default()
+            }),
+            source: ArtifactSource::Registry,
+            patch: None,
+            cache: None,
+            scope: None,
+            dependency_scope: None,
+        }
+    }
+
+    #[test]
+    fn test_build_order() {
+        let pipe = LifecyclePipe::new(
+            vec![
+                artifact("app", &["native", "util"]),
+                artifact("native", &["node-addon-api"]),
+                artifact("node-addon-api", &[]),
+                artifact("util", &["app"]),
+            ],
+            vec![],
+        );
+
+        let order: Vec<&str> = pipe.build_order().iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(order, vec!["node-addon-api", "native", "util", "app"]);
+    }
+
+    #[test]
+    fn test_install_scripts() {
+        let dir = tempfile::tempdir().unwrap();
+        let package_json: PackageJson = serde_json::from_str(
+            r#"{"scripts": {"postinstall": "node post.js", "preinstall": "node pre.js", "test": "jest"}}"#,
+        )
+        .unwrap();
+
+        let events: Vec<String> = LifecyclePipe::install_scripts(&package_json, dir.path())
+            .into_iter()
+            .map(|(event, _)| event)
+            .collect();
+        assert_eq!(events, vec!["preinstall", "postinstall"]);
+
+        // Native addons without an install script get node-gyp
+        std::fs::write(dir.path().join("binding.gyp"), "{}").unwrap();
+        let package_json: PackageJson =
+            serde_json::from_str(r#"{"scripts": {"postinstall": "node post.js"}}"#).unwrap();
+        let scripts = LifecyclePipe::install_scripts(&package_json, dir.path());
+        assert_eq!(
+            scripts[0],
+            ("install".to_string(), "node-gyp rebuild".to_string())
+        );
+        assert_eq!(scripts[1].0, "postinstall");
+    }
+
+    #[test]
+    fn test_copy_package_with_bundled_dependencies() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("cache/native");
+        let dep = dir.path().join("cache/dep");
+        let to = dir.path().join("builds/native");
+        std::fs::create_dir_all(from.join("node_modules/@scope/bundled")).unwrap();
+        std::fs::create_dir_all(from.join("node_modules/bundled/lib")).unwrap();
+        std::fs::create_dir_all(from.join("node_modules/.bin")).unwrap();
+        std::fs::create_dir_all(&dep).unwrap();
+        std::fs::write(from.join("index.js"), "").unwrap();
+        std::fs::write(from.join("node_modules/bundled/lib/index.js"), "").unwrap();
+        std::fs::write(from.join("node_modules/.package-lock.json"), "{}").unwrap();
+        symlink(&dep, &from.join("node_modules/dep")).unwrap();
+        symlink(
+            &from.join("node_modules/bundled/lib/index.js"),
+            &from.join("node_modules/.bin/bundled"),
+        )
+        .unwrap();
+
+        copy_package(&from, &to, &HashMap::new()).unwrap();
+
+        assert!(to.join("index.js").is_file());
+        assert_eq!(
+            std::fs::read_link(to.join("node_modules/dep")).unwrap(),
+            dep
+        );
+        assert!(to.join("node_modules/bundled/lib/index.js").is_file());
+        assert!(to.join("node_modules/@scope/bundled").is_dir());
+        assert!(to.join("node_modules/.package-lock.json").is_file());
+        assert_eq!(
+            std::fs::read_link(to.join("node_modules/.bin/bundled")).unwrap(),
+            to.join("node_modules/bundled/lib/index.js")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_builds_in_the_project_not_the_cache() {
+        use crate::{InstallPipe, LinkerPipe};
+        use package::InstallPackage;
+        use registry::MockRegistry;
+
+        let registry = MockRegistry::start().unwrap();
+        registry
+            .add(
+                "native",
+                "1.0.0",
+                serde_json::json!({ "scripts": { "postinstall": "echo $npm_config_registry > built.txt" } }),
+            )
+            .await
+            .unwrap();
+        registry
+            .add(
+                "app",
+                "1.0.0",
+                serde_json::json!({ "dependencies": { "native": "^1.0.0" } }),
+            )
+            .await
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("cache");
+        let project = dir.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+
+        let packages = vec![InstallPackage::new(
+            "app".to_string(),
+            Some("^1.0.0".to_string()),
+            false,
+        )];
+        let artifacts = InstallPipe::new(packages.clone())
+            .with_registry(registry.url().to_string())
+            .with_cache_dir(cache.clone())
+            .run()
+            .await
+            .unwrap();
+        LinkerPipe::new(artifacts.clone(), packages)
+            .with_project_dir(project.clone())
+            .run()
+            .await
+            .unwrap();
+
+        let skipped = LifecyclePipe::new(artifacts.clone(), vec![])
+            .with_project_dir(project.clone())
+            .run()
+            .await
+            .unwrap();
+        assert_eq!(skipped, vec!["native@1.0.0"]);
+        assert!(!project.join("node_modules/.craft").exists());
+
+        let skipped = LifecyclePipe::new(artifacts, vec!["native".to_string()])
+            .with_project_dir(project.clone())
+            .with_registry(registry.url().to_string())
+            .run()
+            .await
+            .unwrap();
+        assert!(skipped.is_empty());
+
+        // The dependent is copied along, so it loads the built package
+        let app = std::fs::canonicalize(project.join("node_modules/app")).unwrap();
+        let builds = std::fs::canonicalize(project.join("node_modules/.craft/builds")).unwrap();
+        assert!(app.starts_with(&builds));
+        let built = std::fs::read_to_string(app.join("node_modules/native/built.txt")).unwrap();
+        assert_eq!(built.trim(), registry.url());
+        assert!(!cache.join("native-1.0.0/package/built.txt").exists());
+        assert!(!cache.join("native-1.0.0").join(BUILT_MARKER).exists());
+    }
+}
@@ -1,7 +1,8 @@
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use contract::{Pipeline, Result, get_package_cache_dir};
+use contract::{Pipeline, Result};
 use futures::stream::{self, StreamExt};
 use node_semver::{Range, Version};
 use package::{InstallPackage, PackageBin};
@@ -11,6 +12,9 @@ use tokio::fs;
 pub struct LinkerPipe {
     artifacts: Vec<ResolvedArtifact>,
     root_packages: Vec<InstallPackage>,
+    project_dir: Option<PathBuf>,
+    // Additional projects (workspace packages) linked from the same artifacts
+    workspace_packages: Vec<(PathBuf, Vec<InstallPackage>)>,
 }
 
 impl LinkerPipe {
@@ -18,19 +22,36 @@ impl LinkerPipe {
         Self {
             artifacts,
             root_packages,
+            project_dir: None,
+            workspace_packages: Vec::new(),
         }
     }
 
+    /// Link root packages into `dir/node_modules` instead of the current directory
+    pub fn with_project_dir(mut self, dir: PathBuf) -> Self {
+        self.project_dir = Some(dir);
+        self
+    }
+
+    /// Also link `packages` into the workspace package at `dir`
+    pub fn with_workspace_package(mut self, dir: PathBuf, packages: Vec<InstallPackage>) -> Self {
+        self.workspace_packages.push((dir, packages));
+        self
+    }
+
     /// Links dependencies of an artifact into its cached node_modules
     async fn hydrate_artifact(
         &self,
         artifact: &ResolvedArtifact,
         artifact_map: &HashMap<String, Vec<ResolvedArtifact>>,
     ) -> Result<()> {
+        if !artifact.installs_dependencies() {
+            return Ok(());
+        }
+
         debug::info!("Hydrating package: {}", artifact.name);
 
-        let source_dir =
-            get_package_cache_dir().join(format!("{}-{}/package", artifact.name, artifact.version));
+        let source_dir = artifact.package_dir();
 
         // Link dependencies into the artifact's node_modules
         if let Some(deps) = artifact
@@ -47,9 +68,11 @@ impl LinkerPipe {
                     let req = Range::parse(dep_version).unwrap_or_else(|_| Range::any());
 
                     // Find the candidate that satisfies the version requirement
-                    // We prefer the highest version that satisfies it
+                    // We prefer the highest version that satisfies it, among the
+                    // copies resolved in the scope of this artifact's dependencies
                     let best_match = candidates
                         .iter()
+                        .filter(|c| c.scope == artifact.dependency_scope)
                         .filter(|c| {
                             if let Ok(ver) = Version::parse(&c.version) {
                                 req.satisfies(&ver)
@@ -64,10 +87,7 @@ impl LinkerPipe {
                         });
 
                     if let Some(dep_artifact) = best_match {
-                        let dep_source_dir = get_package_cache_dir().join(format!(
-                            "{}-{}/package",
-                            dep_artifact.name, dep_artifact.version
-                        ));
+                        let dep_source_dir = dep_artifact.package_dir();
                         let dep_dest_path = artifact_node_modules.join(dep_name);
 
                         // Create parent dirs for scoped packages
@@ -105,6 +125,7 @@ impl LinkerPipe {
         &self,
         package: &InstallPackage,
         artifact_map: &HashMap<String, Vec<ResolvedArtifact>>,
+        node_modules: &Path,
     ) -> Result<()> {
         debug::info!("Linking root package: {}", package.name);
 
@@ -115,17 +136,25 @@ impl LinkerPipe {
         // In practice, InstallPipe resolved it, so it must be in artifacts.
 
         let candidates = match artifact_map.get(&package.name) {
-            Some(c) => c,
+            Some(c) => c.as_slice(),
+            None if !package.is_registry() => &[],
             None => {
                 debug::warning!("Root package {} not found in artifacts", package.name);
                 return Ok(());
             }
         };
 
-        let best_match = if let Some(req_version) = &package.version {
+        let best_match = if !package.is_registry() {
+            // Git, local and tarball packages are named after their package.json,
+            // match them by spec instead
+            self.artifacts
+                .iter()
+                .find(|a| a.scope.is_none() && a.matches_spec(package.spec()))
+        } else if let Some(req_version) = &package.version {
             let req = Range::parse(req_version).unwrap_or_else(|_| Range::any());
             candidates
                 .iter()
+                .filter(|c| c.scope.is_none())
                 .filter(|c| {
                     if let Ok(ver) = Version::parse(&c.version) {
                         req.satisfies(&ver)
@@ -140,11 +169,14 @@ impl LinkerPipe {
                 })
         } else {
             // If no version specified, pick the highest version available
-            candidates.iter().max_by(|a, b| {
-                let ver_a = Version::parse(&a.version).expect("should not panic");
-                let ver_b = Version::parse(&b.version).expect("should not panic");
-                ver_a.partial_cmp(&ver_b).expect("should not panic")
-            })
+            candidates
+                .iter()
+                .filter(|c| c.scope.is_none())
+                .max_by(|a, b| {
+                    let ver_a = Version::parse(&a.version).expect("should not panic");
+                    let ver_b = Version::parse(&b.version).expect("should not panic");
+                    ver_a.partial_cmp(&ver_b).expect("should not panic")
+                })
         };
 
         let artifact = match best_match {
@@ -159,12 +191,9 @@ impl LinkerPipe {
         };
 
         // Source path in global cache
-        let source_dir =
-            get_package_cache_dir().join(format!("{}-{}/package", artifact.name, artifact.version));
+        let source_dir = artifact.package_dir();
 
         // Destination path in node_modules
-        let cwd = std::env::current_dir()?;
-        let node_modules = cwd.join("node_modules");
         let dest_path = node_modules.join(&artifact.name);
 
         // Create node_modules and parent dirs (for scoped packages)
@@ -194,7 +223,34 @@ impl LinkerPipe {
         debug::info!("Linked root {} -> {:?}", artifact.name, source_dir);
 
         // Link binaries for the root package
-        self.link_package_binaries(artifact, &node_modules).await?;
+        self.link_package_binaries(artifact, node_modules).await?;
+
+        Ok(())
+    }
+
+    /// Links a project's direct dependencies into its node_modules
+    async fn link_project(
+        &self,
+        project_dir: &Path,
+        packages: &[InstallPackage],
+        artifact_map: &Arc<HashMap<String, Vec<ResolvedArtifact>>>,
+    ) -> Result<()> {
+        let node_modules = project_dir.join("node_modules");
+        fs::create_dir_all(&node_modules).await?;
+
+        let linking_results: Vec<Result<()>> = stream::iter(packages.to_vec())
+            .map(|pkg| {
+                let map = artifact_map.clone();
+                let node_modules = node_modules.clone();
+                async move { self.link_root_package(&pkg, &map, &node_modules).await }
+            })
+            .buffer_unordered(10)
+            .collect()
+            .await;
+
+        for result in linking_results {
+            result?;
+        }
 
         Ok(())
     }
@@ -268,10 +324,10 @@ impl LinkerPipe {
 
 impl Pipeline<()> for LinkerPipe {
     async fn run(&self) -> Result<()> {
-        // Ensure node_modules exists
-        let cwd = std::env::current_dir()?;
-        let node_modules = cwd.join("node_modules");
-        fs::create_dir_all(&node_modules).await?;
+        let project_dir = match &self.project_dir {
+            Some(dir) => dir.clone(),
+            None => std::env::current_dir()?,
+        };
 
         // Index artifacts by name for dependency resolution
         let mut artifact_map: HashMap<String, Vec<ResolvedArtifact>> = HashMap::new();
@@ -300,18 +356,13 @@ impl Pipeline<()> for LinkerPipe {
 
         debug::trace!("LINKED NON_ROOT PACKAGES");
         // 2. Link root packages to project node_modules
-        let root_packages = self.root_packages.clone();
-        let linking_results: Vec<Result<()>> = stream::iter(root_packages)
-            .map(|pkg| {
-                let map = artifact_map.clone();
-                async move { self.link_root_package(&pkg, &map).await }
-            })
-            .buffer_unordered(10)
-            .collect()
-            .await;
+        self.link_project(&project_dir, &self.root_packages, &artifact_map)
+            .await?;
 
-        for result in linking_results {
-            result?;
+        // 3. Link each workspace package's own dependencies
+        for (dir, packages) in &self.workspace_packages {
+            debug::info!("Linking workspace package at {:?}", dir);
+            self.link_project(dir, packages, &artifact_map).await?;
         }
 
         Ok(())
@@ -356,14 +407,9 @@ mod tests {
         let pkg_json = package::PackageJson {
             name: Some(pkg_name.to_string()),
             version: Some(pkg_version.to_string()),
-            description: None,
-            scripts: None,
-            keywords: None,
             dependencies: None,
-            dev_dependencies: None,
-            dist: None,
             bin: Some(package::PackageBin::String("cli.js".to_string())),
-            other: HashMap::new(),
+            ..Default::default()
         };
 
         let artifact = ResolvedArtifact {
@@ -371,6 +417,11 @@ mod tests {
             version: pkg_version.to_string(),
             download_url: "http://example.com".to_string(),
             package: Some(pkg_json),
+            source: resolver::ArtifactSource::Registry,
+            patch: None,
+            cache: None,
+            scope: None,
+            dependency_scope: None,
         };
 
         let root_pkg =
@@ -0,0 +1,244 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use contract::{LOCKFILE_NAME, Pipeline, Result};
+use lockfile::{Lockfile, PackageEntry};
+use resolver::{ArtifactSource, ResolvedArtifact};
+
+pub struct LockfilePipe {
+    artifacts: Vec<ResolvedArtifact>,
+    project_dir: Option<PathBuf>,
+    existing: Option<Lockfile>,
+    overrides: BTreeMap<String, String>,
+    declared_overrides: BTreeMap<String, String>,
+    patches: BTreeMap<String, String>,
+}
+
+impl LockfilePipe {
+    pub fn new(artifacts: Vec<ResolvedArtifact>) -> Self {
+        Self {
+            artifacts,
+            project_dir: None,
+            existing: None,
+            overrides: BTreeMap::new(),
+            declared_overrides: BTreeMap::new(),
+            patches: BTreeMap::new(),
+        }
+    }
+
+    /// Keep the entries of an existing lockfile, e.g. when only adding packages
+    pub fn with_existing(mut self, lockfile: Lockfile) -> Self {
+        self.existing = Some(lockfile);
+        self
+    }
+
+    /// Record the overrides the artifacts were resolved with, by key, next to
+    /// those of the existing lockfile
+    pub fn with_overrides(mut self, overrides: BTreeMap<String, String>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Record every override of the root package.json, by key, so the next
+    /// install can tell they changed
+    pub fn with_declared_overrides(mut self, overrides: BTreeMap<String, String>) -> Self {
+        self.declared_overrides = overrides;
+        self
+    }
+
+    /// Record the hashes of the patches applied to the artifacts, by `name@version`
+    pub fn with_patches(mut self, patches: BTreeMap<String, String>) -> Self {
+        self.patches = patches;
+        self
+    }
+
+    /// Write the lockfile into `dir` instead of the current directory
+    pub fn with_project_dir(mut self, dir: PathBuf) -> Self {
+        self.project_dir = Some(dir);
+        self
+    }
+
+    /// Build the lockfile entry for a resolved artifact.
+    ///
+    /// Registry packages record their tarball URL, every other source records
+    /// the spec it was requested with (`file:../lib`, `link:../lib`, a git spec
+    /// or a tarball URL). Local paths inside the project, such as workspace
+    /// packages, are stored relative to it so the lockfile stays portable.
+    pub fn entry(artifact: &ResolvedArtifact, project_dir: &Path) -> PackageEntry {
+        let resolved = match &artifact.source {
+            ArtifactSource::Registry => artifact.download_url.clone(),
+            ArtifactSource::File { path, .. } | ArtifactSource::Link { path, .. }
+                if let Ok(relative) = path.strip_prefix(project_dir) =>
+            {
+                let protocol = match artifact.source {
+                    ArtifactSource::Link { .. } => "link",
+                    _ => "file",
+                };
+                format!("{}:{}", protocol, relative.to_string_lossy())
+            }
+            source => source
+                .spec()
+                .map(str::to_string)
+                .unwrap_or_else(|| artifact.download_url.clone()),
+        };
+
+        let mut entry =
+            PackageEntry::new(artifact.name.clone(), artifact.version.clone(), resolved);
+
+        if let Some(integrity) = artifact
+            .package
+            .as_ref()
+            .and_then(|p| p.dist.as_ref())
+            .and_then(|d| d.integrity.clone())
+        {
+            entry = entry.with_integrity(integrity);
+        }
+
+        // Linked packages manage their own dependencies
+        if artifact.installs_dependencies()
+            && let Some(deps) = artifact
+                .package
+                .as_ref()
+                .and_then(|p| p.dependencies.as_ref())
+            && !deps.is_empty()
+        {
+            entry = entry.with_dependencies(deps.clone().into_iter().collect());
+        }
+
+        entry
+    }
+}
+
+impl Pipeline<Lockfile> for LockfilePipe {
+    async fn run(&self) -> Result<Lockfile> {
+        let mut lockfile = self.existing.clone().unwrap_or_default();
+        lockfile.overrides.extend(self.overrides.clone());
+        lockfile.declared_overrides = self.declared_overrides.clone();
+        lockfile.patches = self.patches.clone();
+
+        let project_dir = match &self.project_dir {
+            Some(dir) => dir.clone(),
+            None => std::env::current_dir()?,
+        };
+
+        // A package reached in several override scopes is recorded once, the
+        // first copy winning
+        let mut written = HashSet::new();
+        for artifact in &self.artifacts {
+            let entry = Self::entry(artifact, &project_dir);
+            if written.insert(entry.key()) {
+                lockfile.add_package(entry);
+            }
+        }
+
+        let path = project_dir.join(LOCKFILE_NAME);
+
+        lockfile.save(&path).await?;
+        debug::info!("Wrote {} packages to {:?}", lockfile.package_count(), path);
+
+        Ok(lockfile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn artifact(name: &str, source: ArtifactSource) -> ResolvedArtifact {
+        let mut deps = HashMap::new();
+        deps.insert("dep".to_string(), "^1.0.0".to_string());
+
+        ResolvedArtifact {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            download_url: format!("https://registry.npmjs.org/{name}/-/{name}-1.0.0.tgz"),
+            package: Some(package::PackageJson {
+                name: Some(name.to_string()),
+                version: Some("1.0.0".to_string()),
+                dependencies: Some(deps),
+                bin: None,
+                ..Default::default()
+            }),
+            source,
+            patch: None,
+            cache: None,
+            scope: None,
+            dependency_scope: None,
+        }
+    }
+
+    #[test]
+    fn test_entry_representation() {
+        let project_dir = Path::new("/work/app");
+
+        let registry = LockfilePipe::entry(&artifact("a", ArtifactSource::Registry), project_dir);
+        assert_eq!(
+            registry.resolved,
+            "https://registry.npmjs.org/a/-/a-1.0.0.tgz"
+        );
+        assert!(registry.dependencies.is_some());
+
+        let file = LockfilePipe::entry(
+            &artifact(
+                "b",
+                ArtifactSource::File {
+                    spec: "file:../b".to_string(),
+                    path: PathBuf::from("/work/b"),
+                },
+            ),
+            project_dir,
+        );
+        assert_eq!(file.resolved, "file:../b");
+
+        let link = LockfilePipe::entry(
+            &artifact(
+                "c",
+                ArtifactSource::Link {
+                    spec: "link:../c".to_string(),
+                    path: PathBuf::from("/work/c"),
+                },
+            ),
+            project_dir,
+        );
+        assert_eq!(link.resolved, "link:../c");
+        assert!(link.dependencies.is_none());
+
+        let member = LockfilePipe::entry(
+            &artifact(
+                "d",
+                ArtifactSource::Link {
+                    spec: "link:/work/app/packages/d".to_string(),
+                    path: PathBuf::from("/work/app/packages/d"),
+                },
+            ),
+            project_dir,
+        );
+        assert_eq!(member.resolved, "link:packages/d");
+    }
+
+    #[tokio::test]
+    async fn test_writes_lockfile() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        let lockfile = LockfilePipe::new(vec![artifact(
+            "d",
+            ArtifactSource::Tarball {
+                url: "https://example.com/d-1.0.0.tgz".to_string(),
+            },
+        )])
+        .with_project_dir(dir.path().to_path_buf())
+        .run()
+        .await?;
+
+        assert!(lockfile.has_package("d", "1.0.0"));
+
+        let saved = Lockfile::load(&dir.path().join(LOCKFILE_NAME)).await?;
+        let entry = saved
+            .get_package("d", "1.0.0")
+            .expect("entry should be saved");
+        assert_eq!(entry.resolved, "https://example.com/d-1.0.0.tgz");
+
+        Ok(())
+    }
+}
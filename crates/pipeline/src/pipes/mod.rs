@@ -1,5 +1,9 @@
 pub mod install_pipe;
+pub mod lifecycle_pipe;
 pub mod linker_pipe;
+pub mod lockfile_pipe;
 
 pub use install_pipe::InstallPipe;
+pub use lifecycle_pipe::LifecyclePipe;
 pub use linker_pipe::LinkerPipe;
+pub use lockfile_pipe::LockfilePipe;
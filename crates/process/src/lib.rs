@@ -1,3 +1,7 @@
+mod lifecycle;
 mod process;
+pub mod shell;
+mod signals;
 
+pub use lifecycle::LifecycleEnv;
 pub use process::Process;
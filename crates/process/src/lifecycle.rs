@@ -0,0 +1,168 @@
+use std::path::PathBuf;
+
+use crate::Process;
+
+/// Environment npm gives lifecycle scripts (`npm_lifecycle_event`, `npm_package_name`, ...)
+///
+/// Scripts such as node-gyp and prebuild-install read these to find out what
+/// they are building, and expect `node_modules/.bin` directories on `PATH`.
+#[derive(Debug, Clone, Default)]
+pub struct LifecycleEnv {
+    event: String,
+    script: String,
+    package_name: Option<String>,
+    package_version: Option<String>,
+    package_json: Option<PathBuf>,
+    init_cwd: Option<PathBuf>,
+    bin_dirs: Vec<PathBuf>,
+    command: Option<String>,
+    config: Vec<(String, String)>,
+}
+
+impl LifecycleEnv {
+    /// Environment for running `script` as the `event` lifecycle stage (`postinstall`, `build`, ...)
+    pub fn new(event: &str, script: &str) -> Self {
+        Self {
+            event: event.to_string(),
+            script: script.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_package(mut self, name: Option<&str>, version: Option<&str>) -> Self {
+        self.package_name = name.map(str::to_string);
+        self.package_version = version.map(str::to_string);
+        self
+    }
+
+    pub fn with_package_json(mut self, path: PathBuf) -> Self {
+        self.package_json = Some(path);
+        self
+    }
+
+    /// Directory the user ran craft from, `INIT_CWD`
+    pub fn with_init_cwd(mut self, dir: PathBuf) -> Self {
+        self.init_cwd = Some(dir);
+        self
+    }
+
+    /// The craft command that triggered the script, `npm_command` (`run-script`, `install`)
+    pub fn with_command(mut self, command: &str) -> Self {
+        self.command = Some(command.to_string());
+        self
+    }
+
+    /// A configuration value, exposed as `npm_config_<key>`
+    pub fn with_config(mut self, key: &str, value: &str) -> Self {
+        self.config.push((key.replace('-', "_"), value.to_string()));
+        self
+    }
+
+    /// Prepend a `node_modules/.bin` directory to `PATH`, earlier calls take precedence
+    pub fn with_bin_dir(mut self, dir: PathBuf) -> Self {
+        self.bin_dirs.push(dir);
+        self
+    }
+
+    pub fn vars(&self) -> Vec<(String, String)> {
+        let mut vars = vec![
+            ("npm_lifecycle_event".to_string(), self.event.clone()),
+            ("npm_lifecycle_script".to_string(), self.script.clone()),
+            (
+                "npm_config_user_agent".to_string(),
+                format!(
+                    "craft/{} {} {}",
+                    env!("CARGO_PKG_VERSION"),
+                    std::env::consts::OS,
+                    std::env::consts::ARCH
+                ),
+            ),
+        ];
+
+        if let Some(name) = &self.package_name {
+            vars.push(("npm_package_name".to_string(), name.clone()));
+        }
+        if let Some(version) = &self.package_version {
+            vars.push(("npm_package_version".to_string(), version.clone()));
+        }
+        if let Some(path) = &self.package_json {
+            vars.push((
+                "npm_package_json".to_string(),
+                path.to_string_lossy().to_string(),
+            ));
+        }
+        if let Some(dir) = &self.init_cwd {
+            vars.push(("INIT_CWD".to_string(), dir.to_string_lossy().to_string()));
+        }
+        if let Ok(exe) = std::env::current_exe() {
+            vars.push((
+                "npm_execpath".to_string(),
+                exe.to_string_lossy().to_string(),
+            ));
+        }
+        if let Some(command) = &self.command {
+            vars.push(("npm_command".to_string(), command.clone()));
+        }
+        for (key, value) in &self.config {
+            vars.push((format!("npm_config_{}", key), value.clone()));
+        }
+        if let Some(path) = self.path() {
+            vars.push(("PATH".to_string(), path));
+        }
+
+        vars
+    }
+
+    /// `PATH` with the bin directories in front of the inherited one
+    fn path(&self) -> Option<String> {
+        if self.bin_dirs.is_empty() {
+            return None;
+        }
+
+        let inherited = std::env::var_os("PATH").unwrap_or_default();
+        let dirs = self
+            .bin_dirs
+            .iter()
+            .cloned()
+            .chain(std::env::split_paths(&inherited));
+
+        std::env::join_paths(dirs)
+            .ok()
+            .map(|path| path.to_string_lossy().to_string())
+    }
+
+    /// Set these variables on `process`
+    pub fn apply(&self, process: Process) -> Process {
+        self.vars()
+            .iter()
+            .fold(process, |process, (key, val)| process.env(key, val))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lifecycle_vars() {
+        let env = LifecycleEnv::new("postinstall", "node install.js")
+            .with_package(Some("bcrypt"), Some("5.1.1"))
+            .with_command("install")
+            .with_config("node-gyp", "/usr/bin/node-gyp")
+            .with_bin_dir(PathBuf::from("/project/node_modules/.bin"));
+
+        let vars = env.vars();
+        let get = |key: &str| vars.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+
+        assert_eq!(get("npm_lifecycle_event"), Some("postinstall"));
+        assert_eq!(get("npm_lifecycle_script"), Some("node install.js"));
+        assert_eq!(get("npm_package_name"), Some("bcrypt"));
+        assert_eq!(get("npm_package_version"), Some("5.1.1"));
+        assert_eq!(get("npm_command"), Some("install"));
+        assert_eq!(get("npm_config_node_gyp"), Some("/usr/bin/node-gyp"));
+        assert!(get("PATH")
+            .unwrap()
+            .starts_with("/project/node_modules/.bin"));
+        assert!(get("npm_config_user_agent").unwrap().starts_with("craft/"));
+    }
+}
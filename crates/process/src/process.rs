@@ -1,8 +1,11 @@
 use anyhow::Context;
-use contract::Result;
-use std::process::Stdio;
+use contract::{ProcessError, Result};
+use std::process::{ExitStatus, Stdio};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
 use tokio::process::Command;
 
+use crate::signals;
+
 pub struct Process {
     command: String,
     args: Vec<String>,
@@ -40,44 +43,160 @@ impl Process {
         self
     }
 
-    pub async fn run(self) -> Result<()> {
+    fn command(&self) -> Command {
         let mut cmd = Command::new(&self.command);
+        cmd.args(&self.args);
 
-        cmd.args(&self.args)
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit());
-
-        if let Some(cwd) = self.cwd {
+        if let Some(cwd) = &self.cwd {
             cmd.current_dir(cwd);
         }
 
-        for (key, val) in self.envs {
+        for (key, val) in &self.envs {
             cmd.env(key, val);
         }
 
-        let status = cmd
-            .status()
-            .await
+        cmd
+    }
+
+    /// The command line, for error messages
+    fn display(&self) -> String {
+        std::iter::once(self.command.as_str())
+            .chain(self.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Run attached to the terminal. Signals sent to craft are forwarded to the
+    /// child, and a non-zero exit becomes a [`ProcessError`] carrying its status.
+    pub async fn run(self) -> Result<()> {
+        let mut cmd = self.command();
+        cmd.stdout(Stdio::inherit()).stderr(Stdio::inherit());
+        let took_terminal = signals::configure(&mut cmd, true);
+
+        let mut child = cmd
+            .spawn()
             .with_context(|| format!("Failed to execute command: {}", self.command))?;
+        let status = signals::wait(&mut child, took_terminal).await?;
 
         if !status.success() {
-            anyhow::bail!("Command failed with status: {}", status);
+            return Err(ProcessError::from_status(&self.display(), &status).into());
         }
 
         Ok(())
     }
 
-    /// Run a script using the platform-specific shell.
+    /// Run without the terminal and return what the process printed on stdout.
+    /// A non-zero exit becomes a [`ProcessError`] carrying its status.
+    pub async fn output(self) -> Result<String> {
+        let mut cmd = self.command();
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit());
+        signals::configure(&mut cmd, false);
+
+        let mut child = cmd
+            .spawn()
+            .with_context(|| format!("Failed to execute command: {}", self.command))?;
+        let mut stdout = child.stdout.take().context("Failed to capture stdout")?;
+
+        let mut output = String::new();
+        let (read, status) = tokio::join!(
+            stdout.read_to_string(&mut output),
+            signals::wait(&mut child, false)
+        );
+        read?;
+        let status = status?;
+
+        if !status.success() {
+            return Err(ProcessError::from_status(&self.display(), &status).into());
+        }
+
+        Ok(output)
+    }
+
+    /// Run with every line of stdout and stderr prefixed by `prefix`.
+    ///
+    /// Lines are printed as they arrive, or all at once when the process exits
+    /// if `grouped` is set, so parallel processes don't interleave (useful in CI).
+    /// A non-zero exit is not an error here, the status is returned to the caller.
+    pub async fn run_prefixed(self, prefix: &str, grouped: bool) -> Result<ExitStatus> {
+        let mut cmd = self.command();
+        // Parallel processes can't share the terminal's input
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        signals::configure(&mut cmd, false);
+
+        let mut child = cmd
+            .spawn()
+            .with_context(|| format!("Failed to execute command: {}", self.command))?;
+
+        let stdout = child.stdout.take().context("Failed to capture stdout")?;
+        let stderr = child.stderr.take().context("Failed to capture stderr")?;
+
+        let (stdout, stderr, status) = tokio::join!(
+            Self::forward_lines(stdout, prefix, grouped, false),
+            Self::forward_lines(stderr, prefix, grouped, true),
+            signals::wait(&mut child, false)
+        );
+
+        if grouped {
+            let (stdout, stderr) = (stdout?, stderr?);
+            if !stdout.is_empty() {
+                print!("{}", stdout);
+            }
+            if !stderr.is_empty() {
+                eprint!("{}", stderr);
+            }
+        } else {
+            stdout?;
+            stderr?;
+        }
+
+        Ok(status?)
+    }
+
+    /// Print each line of `reader` with `prefix`, or collect them when `grouped`
+    async fn forward_lines(
+        reader: impl AsyncRead + Unpin,
+        prefix: &str,
+        grouped: bool,
+        is_stderr: bool,
+    ) -> Result<String> {
+        let mut lines = BufReader::new(reader).lines();
+        let mut collected = String::new();
+
+        while let Some(line) = lines.next_line().await? {
+            let line = format!("{} {}", prefix, line);
+            if grouped {
+                collected.push_str(&line);
+                collected.push('\n');
+            } else if is_stderr {
+                eprintln!("{}", line);
+            } else {
+                println!("{}", line);
+            }
+        }
+
+        Ok(collected)
+    }
+
+    /// A script run through the platform-specific shell.
     /// On Unix systems (Linux, macOS), uses `sh -c`.
     /// On Windows, uses `cmd /C`.
-    pub async fn run_script(script: &str, cwd: Option<&str>) -> Result<()> {
+    pub fn script(script: &str) -> Self {
         #[cfg(target_family = "unix")]
         let (shell, arg) = ("sh", "-c");
 
         #[cfg(target_family = "windows")]
         let (shell, arg) = ("cmd", "/C");
 
-        let mut process = Self::new(shell).arg(arg).arg(script);
+        Self::new(shell).arg(arg).arg(script)
+    }
+
+    /// Run a script using the platform-specific shell.
+    pub async fn run_script(script: &str, cwd: Option<&str>) -> Result<()> {
+        let mut process = Self::script(script);
 
         if let Some(dir) = cwd {
             process = process.current_dir(dir);
@@ -97,4 +216,46 @@ mod tests {
 
         assert!(result.is_ok());
     }
+
+    #[cfg(target_family = "unix")]
+    #[tokio::test]
+    async fn test_output() {
+        let output = Process::script("echo hello; echo world >&2").output().await;
+        assert_eq!(output.unwrap(), "hello\n");
+
+        let error = Process::script("exit 3").output().await.unwrap_err();
+        assert_eq!(error.downcast_ref::<ProcessError>().unwrap().code, Some(3));
+    }
+
+    #[cfg(target_family = "unix")]
+    #[tokio::test]
+    async fn test_run_propagates_exit_code() {
+        let error = Process::script("exit 42").run().await.unwrap_err();
+        let error = error.downcast_ref::<ProcessError>().unwrap();
+
+        assert_eq!(error.code, Some(42));
+        assert_eq!(error.exit_code(), 42);
+    }
+
+    #[cfg(target_family = "unix")]
+    #[tokio::test]
+    async fn test_run_reports_signal() {
+        let error = Process::script("kill -TERM $$").run().await.unwrap_err();
+        let error = error.downcast_ref::<ProcessError>().unwrap();
+
+        assert_eq!(error.signal, Some(15));
+        assert_eq!(error.exit_code(), 143);
+    }
+
+    #[cfg(target_family = "unix")]
+    #[tokio::test]
+    async fn test_run_prefixed_returns_status() {
+        let status = Process::script("echo out; echo err >&2; exit 3")
+            .run_prefixed("[pkg]", true)
+            .await
+            .unwrap();
+
+        assert!(!status.success());
+        assert_eq!(status.code(), Some(3));
+    }
 }
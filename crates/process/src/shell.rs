@@ -0,0 +1,58 @@
+//! Quoting arguments that are appended to a shell command line
+
+use std::borrow::Cow;
+
+/// Quote `arg` so the platform shell passes it through as a single argument
+#[cfg(not(windows))]
+pub fn quote(arg: &str) -> Cow<'_, str> {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-./:=@%+,^".contains(c);
+
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        return Cow::Borrowed(arg);
+    }
+
+    // Inside single quotes nothing is special except the closing quote
+    Cow::Owned(format!("'{}'", arg.replace('\'', r"'\''")))
+}
+
+/// Quote `arg` so the platform shell passes it through as a single argument
+#[cfg(windows)]
+pub fn quote(arg: &str) -> Cow<'_, str> {
+    let needs_quotes = arg.is_empty() || arg.contains([' ', '\t', '"', '&', '|', '<', '>', '^']);
+
+    if !needs_quotes {
+        return Cow::Borrowed(arg);
+    }
+
+    Cow::Owned(format!("\"{}\"", arg.replace('"', "\\\"")))
+}
+
+/// Quote and join arguments for appending to a script
+pub fn join(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("--fix"), "--fix");
+        assert_eq!(quote("src/index.ts"), "src/index.ts");
+        assert_eq!(quote("src/**/*.ts"), "'src/**/*.ts'");
+        assert_eq!(quote("hello world"), "'hello world'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote("$HOME"), "'$HOME'");
+        assert_eq!(quote(""), "''");
+    }
+
+    #[test]
+    fn test_join() {
+        let args = vec!["--pattern".to_string(), "src/**/*.ts".to_string()];
+        assert_eq!(join(&args), "--pattern 'src/**/*.ts'");
+    }
+}
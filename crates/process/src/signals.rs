@@ -0,0 +1,201 @@
+//! Child process groups and signal forwarding.
+//!
+//! Children run in their own process group so that a signal reaches the
+//! shell craft started *and* everything it spawned. SIGINT, SIGTERM and SIGHUP
+//! sent to craft are forwarded to the groups of the children running at the
+//! time instead of killing craft, and the child's exit status is reported once
+//! it has exited. Without a child running, craft exits with 128 + the signal,
+//! like a shell would.
+
+use std::io;
+use std::process::ExitStatus;
+use tokio::process::{Child, Command};
+
+#[cfg(unix)]
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// Process groups of the children being waited for, 0 marks a free slot. A
+/// fixed array, since the signal handler can't lock or allocate.
+#[cfg(unix)]
+static GROUPS: [AtomicI32; 64] = [const { AtomicI32::new(0) }; 64];
+
+/// Put the child in its own process group. With `foreground`, the group is also
+/// given the terminal (when craft has it), so Ctrl-C and interactive input go
+/// straight to the child.
+#[cfg(unix)]
+pub(crate) fn configure(cmd: &mut Command, foreground: bool) -> bool {
+    // SAFETY: isatty, tcgetpgrp and getpgrp only read process state
+    let take_terminal =
+        foreground && unsafe { libc::isatty(0) == 1 && libc::tcgetpgrp(0) == libc::getpgrp() };
+
+    // SAFETY: only async-signal-safe calls between fork and exec
+    unsafe {
+        cmd.pre_exec(move || {
+            if libc::setpgid(0, 0) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if take_terminal {
+                // A background group changing the terminal's group gets SIGTTOU
+                let previous = libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+                libc::tcsetpgrp(0, libc::getpgrp());
+                libc::signal(libc::SIGTTOU, previous);
+            }
+            Ok(())
+        });
+    }
+
+    take_terminal
+}
+
+#[cfg(not(unix))]
+pub(crate) fn configure(_cmd: &mut Command, _foreground: bool) -> bool {
+    false
+}
+
+/// Wait for `child`, forwarding termination signals to its process group.
+/// When the child had the terminal, it is given back to craft afterwards.
+#[cfg(unix)]
+pub(crate) async fn wait(child: &mut Child, took_terminal: bool) -> io::Result<ExitStatus> {
+    let Some(pid) = child.id() else {
+        return child.wait().await;
+    };
+
+    install_handler()?;
+    let group = Group::register(pid as libc::pid_t);
+    let status = child.wait().await;
+    drop(group);
+
+    if took_terminal {
+        // SAFETY: reclaiming the terminal for craft's own process group
+        unsafe {
+            let previous = libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+            libc::tcsetpgrp(0, libc::getpgrp());
+            libc::signal(libc::SIGTTOU, previous);
+        }
+    }
+
+    status
+}
+
+/// Install the handler for SIGINT, SIGTERM and SIGHUP, once for the whole
+/// process. It stays in place for as long as craft runs.
+#[cfg(unix)]
+fn install_handler() -> io::Result<()> {
+    use std::sync::OnceLock;
+
+    static INSTALLED: OnceLock<Result<(), i32>> = OnceLock::new();
+
+    let installed = INSTALLED.get_or_init(|| {
+        for sig in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+            // SAFETY: the handler only makes async-signal-safe calls
+            unsafe {
+                let mut action: libc::sigaction = std::mem::zeroed();
+                action.sa_sigaction = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+                action.sa_flags = libc::SA_RESTART;
+                libc::sigemptyset(&mut action.sa_mask);
+                if libc::sigaction(sig, &action, std::ptr::null_mut()) != 0 {
+                    return Err(io::Error::last_os_error().raw_os_error().unwrap_or(0));
+                }
+            }
+        }
+        Ok(())
+    });
+
+    installed.map_err(io::Error::from_raw_os_error)
+}
+
+#[cfg(unix)]
+extern "C" fn handle(sig: libc::c_int) {
+    if !forward(&GROUPS, sig) {
+        // SAFETY: _exit is async-signal-safe
+        unsafe { libc::_exit(128 + sig) }
+    }
+}
+
+/// Send `sig` to every process group in `groups`, whether there was any
+#[cfg(unix)]
+fn forward(groups: &[AtomicI32], sig: libc::c_int) -> bool {
+    let mut forwarded = false;
+    for group in groups {
+        let pgid = group.load(Ordering::SeqCst);
+        if pgid != 0 {
+            // SAFETY: signalling a process group we created
+            unsafe {
+                libc::kill(-pgid, sig);
+            }
+            forwarded = true;
+        }
+    }
+    forwarded
+}
+
+/// A child's process group registered for forwarding until dropped, so it is
+/// also removed when the wait is cancelled
+#[cfg(unix)]
+struct Group<'a>(Option<&'a AtomicI32>);
+
+#[cfg(unix)]
+impl Group<'static> {
+    /// Register `pgid`. With every slot taken, signals aren't forwarded to it.
+    fn register(pgid: libc::pid_t) -> Self {
+        Self::register_in(&GROUPS, pgid)
+    }
+}
+
+#[cfg(unix)]
+impl<'a> Group<'a> {
+    fn register_in(groups: &'a [AtomicI32], pgid: libc::pid_t) -> Self {
+        let slot = groups.iter().find(|slot| {
+            slot.compare_exchange(0, pgid, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+        });
+        Self(slot)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Group<'_> {
+    fn drop(&mut self) {
+        if let Some(slot) = self.0 {
+            slot.store(0, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) async fn wait(child: &mut Child, _took_terminal: bool) -> io::Result<ExitStatus> {
+    child.wait().await
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_handler_once() {
+        install_handler().unwrap();
+        install_handler().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_forward_to_registered_groups() {
+        let groups: [AtomicI32; 1] = [const { AtomicI32::new(0) }; 1];
+        assert!(!forward(&groups, libc::SIGTERM));
+
+        let mut cmd = Command::new("sleep");
+        cmd.arg("30");
+        configure(&mut cmd, false);
+        let mut child = cmd.spawn().unwrap();
+        let pgid = child.id().unwrap() as libc::pid_t;
+
+        let group = Group::register_in(&groups, pgid);
+        // With every slot taken, another child goes without forwarding
+        assert!(Group::register_in(&groups, pgid).0.is_none());
+        assert!(forward(&groups, libc::SIGTERM));
+        let status = child.wait().await.unwrap();
+        assert!(!status.success());
+
+        drop(group);
+        assert!(!forward(&groups, libc::SIGTERM));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod picker;
 pub mod progress;
 
+pub use picker::{Picker, PickerItem};
 pub use progress::{Progress, ProgressMessage};
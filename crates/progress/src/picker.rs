@@ -0,0 +1,225 @@
+use std::io;
+
+use console::{Key, Term, style};
+
+/// One selectable row of a [`Picker`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PickerItem {
+    /// Heading the item is listed under
+    pub group: String,
+    pub label: String,
+    pub selected: bool,
+}
+
+impl PickerItem {
+    pub fn new(group: &str, label: &str) -> Self {
+        Self {
+            group: group.to_string(),
+            label: label.to_string(),
+            selected: false,
+        }
+    }
+}
+
+/// What a key press did to the picker
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Continue,
+    Confirm,
+    Cancel,
+}
+
+/// Multi-select list in the terminal, with items listed under their group.
+///
+/// Arrow keys (or j/k) move, space toggles, `a` toggles everything, enter
+/// confirms and esc/q cancels.
+pub struct Picker {
+    title: String,
+    header: Option<String>,
+    items: Vec<PickerItem>,
+    cursor: usize,
+}
+
+impl Picker {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            header: None,
+            items: Vec::new(),
+            cursor: 0,
+        }
+    }
+
+    /// Column headings shown above the items, aligned with their labels
+    pub fn with_header(mut self, header: &str) -> Self {
+        self.header = Some(header.to_string());
+        self
+    }
+
+    /// Add an item. Items of the same group should be added together.
+    pub fn with_item(mut self, item: PickerItem) -> Self {
+        self.items.push(item);
+        self
+    }
+
+    /// Show the picker on stderr, returning the indices of the selected items,
+    /// or `None` when it was cancelled
+    pub fn interact(mut self) -> io::Result<Option<Vec<usize>>> {
+        let term = Term::stderr();
+        if !term.is_term() {
+            return Err(io::Error::other("an interactive terminal is required"));
+        }
+
+        term.hide_cursor()?;
+        let result = self.interact_on(&term);
+        term.show_cursor()?;
+        result
+    }
+
+    fn interact_on(&mut self, term: &Term) -> io::Result<Option<Vec<usize>>> {
+        let mut drawn = 0;
+
+        loop {
+            term.clear_last_lines(drawn)?;
+            let lines = self.lines();
+            for line in &lines {
+                term.write_line(line)?;
+            }
+            drawn = lines.len();
+
+            match self.handle(term.read_key()?) {
+                Outcome::Continue => {}
+                Outcome::Confirm => return Ok(Some(self.selected())),
+                Outcome::Cancel => return Ok(None),
+            }
+        }
+    }
+
+    fn handle(&mut self, key: Key) -> Outcome {
+        let last = self.items.len().saturating_sub(1);
+
+        match key {
+            Key::ArrowUp | Key::Char('k') => {
+                self.cursor = if self.cursor == 0 {
+                    last
+                } else {
+                    self.cursor - 1
+                };
+            }
+            Key::ArrowDown | Key::Char('j') | Key::Tab => {
+                self.cursor = if self.cursor >= last {
+                    0
+                } else {
+                    self.cursor + 1
+                };
+            }
+            Key::Char(' ') => {
+                if let Some(item) = self.items.get_mut(self.cursor) {
+                    item.selected = !item.selected;
+                }
+            }
+            Key::Char('a') => {
+                let select = !self.items.iter().all(|item| item.selected);
+                for item in &mut self.items {
+                    item.selected = select;
+                }
+            }
+            Key::Enter => return Outcome::Confirm,
+            Key::Escape | Key::Char('q') | Key::CtrlC => return Outcome::Cancel,
+            _ => {}
+        }
+
+        Outcome::Continue
+    }
+
+    fn selected(&self) -> Vec<usize> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.selected)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// The lines drawn for the current state
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("{}", style(&self.title).bold()),
+            format!(
+                "{}",
+                style("↑/↓ move, space toggle, a toggle all, enter confirm, esc cancel").dim()
+            ),
+        ];
+        if let Some(header) = &self.header {
+            lines.push(format!("    {}", style(header).bold()));
+        }
+
+        let mut group = None;
+        for (i, item) in self.items.iter().enumerate() {
+            if group != Some(&item.group) {
+                lines.push(format!("{}", style(&item.group).underlined()));
+                group = Some(&item.group);
+            }
+
+            let pointer = if i == self.cursor { "❯" } else { " " };
+            let checkbox = if item.selected { "◉" } else { "○" };
+            let line = format!("{} {} {}", pointer, checkbox, item.label);
+            lines.push(if i == self.cursor {
+                format!("{}", style(line).cyan())
+            } else {
+                line
+            });
+        }
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picker() -> Picker {
+        Picker::new("Choose")
+            .with_item(PickerItem::new("dependencies", "react"))
+            .with_item(PickerItem::new("dependencies", "zod"))
+            .with_item(PickerItem::new("devDependencies", "typescript"))
+    }
+
+    #[test]
+    fn test_toggle_and_confirm() {
+        let mut picker = picker();
+
+        assert_eq!(picker.handle(Key::ArrowDown), Outcome::Continue);
+        picker.handle(Key::Char(' '));
+        picker.handle(Key::ArrowUp);
+        picker.handle(Key::ArrowUp);
+        picker.handle(Key::Char(' '));
+        assert_eq!(picker.handle(Key::Enter), Outcome::Confirm);
+
+        // Moving up from the first item wraps to the last one
+        assert_eq!(picker.selected(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_toggle_all_and_cancel() {
+        let mut picker = picker();
+
+        picker.handle(Key::Char('a'));
+        assert_eq!(picker.selected(), vec![0, 1, 2]);
+        picker.handle(Key::Char('a'));
+        assert!(picker.selected().is_empty());
+
+        assert_eq!(picker.handle(Key::Escape), Outcome::Cancel);
+    }
+
+    #[test]
+    fn test_lines_group_items() {
+        let lines = picker().lines();
+
+        assert_eq!(lines.len(), 2 + 2 + 3);
+        assert!(lines[2].contains("dependencies"));
+        assert!(lines[3].contains("❯ ○ react"));
+        assert!(lines[5].contains("devDependencies"));
+    }
+}
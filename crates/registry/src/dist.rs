@@ -0,0 +1,28 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use sha1::Sha1;
+use sha2::{Digest, Sha512};
+
+/// `sha512-…` subresource integrity of a tarball, its `dist.integrity`
+pub fn integrity(tarball: &[u8]) -> String {
+    format!("sha512-{}", BASE64.encode(Sha512::digest(tarball)))
+}
+
+/// Hex SHA-1 of a tarball, its `dist.shasum`
+pub fn shasum(tarball: &[u8]) -> String {
+    format!("{:x}", Sha1::digest(tarball))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digests() {
+        assert_eq!(shasum(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(
+            integrity(b"abc"),
+            "sha512-3a81oZNherrMQXNJriBBMRLm+k6JqX6iCp7u5ktV05ohkpkqJ0/BqDa6PCOj/uu9RU1EI2Q86A4qmslPpUyknw=="
+        );
+    }
+}
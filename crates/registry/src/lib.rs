@@ -0,0 +1,11 @@
+mod dist;
+#[cfg(any(test, feature = "mock"))]
+mod mock;
+mod server;
+mod storage;
+
+pub use dist::{integrity, shasum};
+#[cfg(any(test, feature = "mock"))]
+pub use mock::{MockRegistry, ReceivedRequest};
+pub use server::{Registry, RegistryServer};
+pub use storage::{Storage, tarball_url};
@@ -0,0 +1,295 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use contract::Result;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use tarball::pack::{PackFile, pack};
+use tempfile::TempDir;
+use tokio::task::JoinHandle;
+
+use crate::server::{Registry, error, json};
+use crate::storage::Storage;
+
+/// Where npm clients send `{ name: [versions] }` to get the advisories of those packages
+const ADVISORIES_PATH: &str = "/-/npm/v1/security/advisories/bulk";
+
+/// A request the [MockRegistry] received
+#[derive(Debug, Clone)]
+pub struct ReceivedRequest {
+    pub method: String,
+    pub path: String,
+    /// Header values by lowercase name
+    pub headers: BTreeMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+/// What the server shares with the [MockRegistry] driving it
+#[derive(Debug, Default)]
+struct State {
+    requests: Mutex<Vec<ReceivedRequest>>,
+    advisories: Mutex<serde_json::Map<String, serde_json::Value>>,
+}
+
+/// A registry on a local port serving packages defined by tests, so resolving
+/// and installing never reaches the npm registry. Packages live in a temporary
+/// directory removed, along with the server, when the registry is dropped.
+///
+/// It also answers bulk advisory requests with the advisories tests add, and
+/// keeps every request it receives for tests to look at.
+#[derive(Debug)]
+pub struct MockRegistry {
+    url: String,
+    storage: Storage,
+    state: Arc<State>,
+    server: JoinHandle<Result<()>>,
+    _dir: TempDir,
+}
+
+impl MockRegistry {
+    /// The token publishing to the registry takes
+    pub const TOKEN: &str = "mock-token";
+
+    /// Serve an empty registry, from within a tokio runtime
+    pub fn start() -> Result<Self> {
+        let dir = tempfile::tempdir()?;
+        let storage = Storage::new(dir.path().to_path_buf());
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let url = format!("http://{}", listener.local_addr()?);
+
+        let registry = Arc::new(Registry::new(storage.clone()).with_token(Self::TOKEN.to_string()));
+        let state = Arc::new(State::default());
+        let server = tokio::spawn(serve(listener, registry, state.clone()));
+
+        Ok(Self {
+            url,
+            storage,
+            state,
+            server,
+            _dir: dir,
+        })
+    }
+
+    /// The registry's URL, to resolve packages against
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Publish `name@version` with `manifest` as the rest of its package.json
+    /// and an `index.js`
+    pub async fn add(&self, name: &str, version: &str, manifest: serde_json::Value) -> Result<()> {
+        let index = PackFile::new("index.js".to_string(), b"module.exports = {};\n".to_vec());
+        self.add_files(name, version, manifest, vec![index]).await
+    }
+
+    /// Publish `name@version` with `manifest` as the rest of its package.json
+    /// and `files` next to it
+    pub async fn add_files(
+        &self,
+        name: &str,
+        version: &str,
+        manifest: serde_json::Value,
+        mut files: Vec<PackFile>,
+    ) -> Result<()> {
+        let mut package_json = serde_json::json!({ "name": name, "version": version });
+        if let (Some(package_json), serde_json::Value::Object(manifest)) =
+            (package_json.as_object_mut(), manifest)
+        {
+            package_json.extend(manifest);
+        }
+        files.retain(|file| file.path != "package.json");
+        files.push(PackFile::new(
+            "package.json".to_string(),
+            serde_json::to_vec_pretty(&package_json)?,
+        ));
+
+        let tarball = tokio::task::spawn_blocking(move || pack(&files)).await??;
+        self.storage.write_tarball(name, version, &tarball).await
+    }
+
+    /// Publish the fixture package in `dir`, named and versioned by its
+    /// package.json
+    pub async fn add_dir(&self, dir: &Path) -> Result<()> {
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&tokio::fs::read(dir.join("package.json")).await?)?;
+        let (Some(name), Some(version)) = (manifest["name"].as_str(), manifest["version"].as_str())
+        else {
+            anyhow::bail!("{} needs a name and a version", dir.display());
+        };
+
+        let root = dir.to_path_buf();
+        let files = tokio::task::spawn_blocking(move || fixture_files(&root, &root)).await??;
+        self.add_files(name, version, manifest.clone(), files).await
+    }
+
+    /// Point the dist-tag `tag` of `name` to `version`, `latest` otherwise
+    /// being the highest stable version
+    pub async fn tag(&self, name: &str, tag: &str, version: &str) -> Result<()> {
+        let tags = BTreeMap::from([(tag.to_string(), version.to_string())]);
+        self.storage.set_dist_tags(name, tags).await
+    }
+
+    /// Report `advisory`, in the bulk advisory format, for `name` when it's audited
+    pub fn advise(&self, name: &str, advisory: serde_json::Value) {
+        let mut advisories = self
+            .state
+            .advisories
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let list = advisories
+            .entry(name.to_string())
+            .or_insert_with(|| serde_json::Value::Array(Vec::new()));
+        if let serde_json::Value::Array(list) = list {
+            list.push(advisory);
+        }
+    }
+
+    /// The requests received so far, oldest first
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.state
+            .requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+/// Serve `registry` on `listener`, recording the requests in `state`
+async fn serve(listener: TcpListener, registry: Arc<Registry>, state: Arc<State>) -> Result<()> {
+    let make_service = make_service_fn(move |_| {
+        let (registry, state) = (registry.clone(), state.clone());
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let (registry, state) = (registry.clone(), state.clone());
+                async move { Ok::<_, Infallible>(handle(&registry, &state, request).await) }
+            }))
+        }
+    });
+
+    Server::from_tcp(listener)?.serve(make_service).await?;
+    Ok(())
+}
+
+async fn handle(registry: &Registry, state: &State, request: Request<Body>) -> Response<Body> {
+    let (parts, body) = request.into_parts();
+    let body = hyper::body::to_bytes(body).await.unwrap_or_default();
+
+    let received = ReceivedRequest {
+        method: parts.method.to_string(),
+        path: parts.uri.path().to_string(),
+        headers: parts
+            .headers
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect(),
+        body: body.to_vec(),
+    };
+    state
+        .requests
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(received);
+
+    if parts.method == Method::POST && parts.uri.path() == ADVISORIES_PATH {
+        return advisories(state, &body);
+    }
+    registry
+        .handle(Request::from_parts(parts, Body::from(body)))
+        .await
+}
+
+/// The advisories of the packages in a bulk advisory request
+fn advisories(state: &State, body: &[u8]) -> Response<Body> {
+    let Ok(serde_json::Value::Object(requested)) = serde_json::from_slice(body) else {
+        return error(StatusCode::BAD_REQUEST, "Invalid advisory request");
+    };
+
+    let advisories = state.advisories.lock().unwrap_or_else(|e| e.into_inner());
+    let found: serde_json::Map<String, serde_json::Value> = requested
+        .keys()
+        .filter_map(|name| Some((name.clone(), advisories.get(name)?.clone())))
+        .collect();
+
+    json(StatusCode::OK, &serde_json::Value::Object(found))
+}
+
+impl Drop for MockRegistry {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// Every file below `dir`, with paths relative to `root`
+fn fixture_files(root: &Path, dir: &Path) -> Result<Vec<PackFile>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(fixture_files(root, &path)?);
+        } else {
+            let relative = path
+                .strip_prefix(root)?
+                .to_string_lossy()
+                .replace('\\', "/");
+            files.push(PackFile::new(relative, std::fs::read(&path)?));
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network::Network;
+
+    #[tokio::test]
+    async fn test_serves_added_packages() {
+        let registry = MockRegistry::start().unwrap();
+        registry
+            .add(
+                "lib",
+                "1.0.0",
+                serde_json::json!({ "dependencies": { "dep": "^1.0.0" } }),
+            )
+            .await
+            .unwrap();
+        registry
+            .add("lib", "2.0.0", serde_json::json!({}))
+            .await
+            .unwrap();
+        registry.tag("lib", "latest", "1.0.0").await.unwrap();
+
+        let fixture = tempfile::tempdir().unwrap();
+        std::fs::write(
+            fixture.path().join("package.json"),
+            r#"{"name": "@acme/fixture", "version": "0.1.0"}"#,
+        )
+        .unwrap();
+        std::fs::create_dir(fixture.path().join("lib")).unwrap();
+        std::fs::write(fixture.path().join("lib/main.js"), "").unwrap();
+        registry.add_dir(fixture.path()).await.unwrap();
+
+        let network = Network::new();
+        let packument: serde_json::Value = network
+            .fetch(&format!("{}/lib", registry.url()))
+            .await
+            .unwrap();
+        assert_eq!(packument["dist-tags"]["latest"], "1.0.0");
+        assert_eq!(
+            packument["versions"]["1.0.0"]["dependencies"]["dep"],
+            "^1.0.0"
+        );
+        assert_eq!(packument["versions"]["2.0.0"]["name"], "lib");
+
+        let packument: serde_json::Value = network
+            .fetch(&format!("{}/@acme%2ffixture", registry.url()))
+            .await
+            .unwrap();
+        let tarball = format!("{}/@acme/fixture/-/fixture-0.1.0.tgz", registry.url());
+        assert_eq!(packument["versions"]["0.1.0"]["dist"]["tarball"], tarball);
+    }
+}
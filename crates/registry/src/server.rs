@@ -0,0 +1,597 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::Arc;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use contract::Result;
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE, HOST};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use network::Network;
+
+use crate::dist::integrity;
+use crate::storage::{Storage, tarball_url};
+
+/// An npm-compatible registry serving the packages of a [Storage]: packuments
+/// at `/<name>`, tarballs at `/<name>/-/<file>.tgz`, and `PUT /<name>` to publish.
+/// Publishing takes the registry's token, and is refused when it has none.
+///
+/// With an upstream registry, packages are fetched from it when it's reachable
+/// and their tarballs kept in the cache (the storage without one), so they're
+/// served once it isn't.
+#[derive(Debug)]
+pub struct Registry {
+    storage: Storage,
+    cache: Option<Storage>,
+    upstream: Option<String>,
+    token: Option<String>,
+    network: Network,
+}
+
+/// A [Registry] bound to its address, ready to serve
+#[derive(Debug)]
+pub struct RegistryServer {
+    listener: TcpListener,
+    registry: Arc<Registry>,
+}
+
+/// An error response, `{ "error": message }` like the npm registry's
+pub(crate) fn error(status: StatusCode, message: impl Into<String>) -> Response<Body> {
+    json(status, &serde_json::json!({ "error": message.into() }))
+}
+
+pub(crate) fn json(status: StatusCode, value: &serde_json::Value) -> Response<Body> {
+    let mut response = Response::new(Body::from(value.to_string()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+/// Decode the escapes npm clients use in package paths (`@scope%2flib`)
+fn decode_path(path: &str) -> String {
+    path.replace("%2f", "/")
+        .replace("%2F", "/")
+        .replace("%40", "@")
+}
+
+enum Route {
+    Ping,
+    Packument { name: String },
+    Tarball { name: String, version: String },
+}
+
+impl Route {
+    fn parse(path: &str) -> Option<Self> {
+        let path = decode_path(path.strip_prefix('/')?);
+        if path == "-/ping" {
+            return Some(Self::Ping);
+        }
+
+        let route = match path.split_once("/-/") {
+            Some((name, file)) => {
+                let unscoped = name.rsplit('/').next().unwrap_or(name);
+                let version = file
+                    .strip_prefix(&format!("{}-", unscoped))?
+                    .strip_suffix(".tgz")?;
+                node_semver::Version::parse(version).ok()?;
+                Self::Tarball {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                }
+            }
+            None => Self::Packument { name: path },
+        };
+
+        match &route {
+            Self::Packument { name } | Self::Tarball { name, .. } => {
+                Storage::is_valid_name(name).then_some(route)
+            }
+            Self::Ping => Some(route),
+        }
+    }
+}
+
+impl Registry {
+    pub fn new(storage: Storage) -> Self {
+        Self {
+            storage,
+            cache: None,
+            upstream: None,
+            token: None,
+            network: Network::new(),
+        }
+    }
+
+    /// Also serve the packages of `cache`, which publishing never writes to
+    pub fn with_cache(mut self, cache: Storage) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Accept publishes sent with `Authorization: Bearer <token>`
+    pub fn with_token(mut self, token: String) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Proxy packages missing from the storage to `upstream`
+    pub fn with_upstream(mut self, upstream: String) -> Self {
+        self.upstream = Some(upstream.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Listen on `addr`, port 0 picking a free one
+    pub fn bind(self, addr: impl ToSocketAddrs) -> Result<RegistryServer> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(RegistryServer {
+            listener,
+            registry: Arc::new(self),
+        })
+    }
+
+    pub(crate) async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        // Tarball URLs point back at the address the client used
+        let registry = match request.headers().get(HOST).and_then(|h| h.to_str().ok()) {
+            Some(host) => format!("http://{}", host),
+            None => String::new(),
+        };
+
+        let Some(route) = Route::parse(request.uri().path()) else {
+            return error(StatusCode::NOT_FOUND, "Not found");
+        };
+
+        let result = match (request.method(), route) {
+            (&Method::GET, Route::Ping) => Ok(json(StatusCode::OK, &serde_json::json!({}))),
+            (&Method::GET, Route::Packument { name }) => self.packument(&name, &registry).await,
+            (&Method::GET, Route::Tarball { name, version }) => self.tarball(&name, &version).await,
+            (&Method::PUT, Route::Packument { name }) => {
+                if let Some(response) = self.refuse_publish(&request) {
+                    return response;
+                }
+                match hyper::body::to_bytes(request.into_body()).await {
+                    Ok(body) => self.publish(&name, &body).await,
+                    Err(e) => Err(e.into()),
+                }
+            }
+            _ => Ok(error(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed")),
+        };
+
+        result.unwrap_or_else(|e| error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+    }
+
+    /// The error response for a publish without the registry's token
+    fn refuse_publish(&self, request: &Request<Body>) -> Option<Response<Body>> {
+        let Some(token) = &self.token else {
+            return Some(error(
+                StatusCode::FORBIDDEN,
+                "Publishing is disabled, the registry has no token",
+            ));
+        };
+
+        let sent = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match sent {
+            Some(sent) if same_token(sent, token) => None,
+            _ => Some(error(
+                StatusCode::UNAUTHORIZED,
+                "Publishing needs the registry's token",
+            )),
+        }
+    }
+
+    /// The stored document of `name`, with the cached versions next to the
+    /// published ones
+    async fn local_packument(
+        &self,
+        name: &str,
+        registry: &str,
+    ) -> Result<Option<serde_json::Value>> {
+        let published = self.storage.packument(name, registry).await?;
+        let cached = match &self.cache {
+            Some(cache) => cache.packument(name, registry).await?,
+            None => None,
+        };
+
+        Ok(match (published, cached) {
+            (Some(mut published), Some(cached)) => {
+                for (version, manifest) in cached["versions"].as_object().into_iter().flatten() {
+                    if published["versions"].get(version).is_none() {
+                        published["versions"][version] = manifest.clone();
+                    }
+                }
+                Some(published)
+            }
+            (published, cached) => published.or(cached),
+        })
+    }
+
+    async fn packument(&self, name: &str, registry: &str) -> Result<Response<Body>> {
+        let local = self.local_packument(name, registry).await?;
+
+        let Some(upstream) = &self.upstream else {
+            return Ok(match local {
+                Some(packument) => json(StatusCode::OK, &packument),
+                None => error(StatusCode::NOT_FOUND, format!("{} isn't stored", name)),
+            });
+        };
+
+        let url = format!("{}/{}", upstream, name.replace('/', "%2f"));
+        let mut packument = match self.network.fetch::<serde_json::Value>(&url).await {
+            Ok(packument) => packument,
+            Err(e) => {
+                debug::warning!("Serving {} from the storage, {} failed: {}", name, url, e);
+                return Ok(match local {
+                    Some(packument) => json(StatusCode::OK, &packument),
+                    None => error(StatusCode::NOT_FOUND, format!("{} isn't stored", name)),
+                });
+            }
+        };
+
+        // Tarballs are downloaded through this registry, so they're kept
+        if let Some(versions) = packument["versions"].as_object_mut() {
+            for (version, manifest) in versions.iter_mut() {
+                manifest["dist"]["tarball"] = tarball_url(registry, name, version).into();
+            }
+        }
+
+        // Versions only published here are listed next to the upstream ones
+        if let Some(local) = local {
+            for (version, manifest) in local["versions"].as_object().into_iter().flatten() {
+                if packument["versions"].get(version).is_none() {
+                    packument["versions"][version] = manifest.clone();
+                }
+            }
+            for (tag, version) in self.storage.dist_tags(name).await? {
+                packument["dist-tags"][tag] = version.into();
+            }
+        }
+
+        Ok(json(StatusCode::OK, &packument))
+    }
+
+    async fn tarball(&self, name: &str, version: &str) -> Result<Response<Body>> {
+        for storage in std::iter::once(&self.storage).chain(&self.cache) {
+            if let Some(tarball) = storage.tarball(name, version).await? {
+                return Ok(Response::new(Body::from(tarball)));
+            }
+        }
+
+        let Some(upstream) = &self.upstream else {
+            return Ok(error(
+                StatusCode::NOT_FOUND,
+                format!("{}@{} isn't stored", name, version),
+            ));
+        };
+
+        let path = self
+            .cache
+            .as_ref()
+            .unwrap_or(&self.storage)
+            .tarball_path(name, version);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let url = tarball_url(upstream, name, version);
+        let partial = path.with_extension("tgz.partial");
+        if let Err(e) = self.network.download(&url, partial.clone()).await {
+            return Ok(error(
+                StatusCode::NOT_FOUND,
+                format!("{}@{} isn't stored, {} failed: {}", name, version, url, e),
+            ));
+        }
+
+        tokio::fs::rename(&partial, &path).await?;
+
+        Ok(Response::new(Body::from(tokio::fs::read(&path).await?)))
+    }
+
+    /// Store the versions and dist-tags of a publish document
+    async fn publish(&self, name: &str, body: &[u8]) -> Result<Response<Body>> {
+        let Ok(document) = serde_json::from_slice::<serde_json::Value>(body) else {
+            return Ok(error(StatusCode::BAD_REQUEST, "Invalid publish document"));
+        };
+        if document["name"].as_str() != Some(name) {
+            return Ok(error(
+                StatusCode::BAD_REQUEST,
+                format!("The document publishes {}, not {}", document["name"], name),
+            ));
+        }
+
+        let mut existing = self.storage.versions(name).await?;
+        if let Some(cache) = &self.cache {
+            existing.extend(cache.versions(name).await?);
+        }
+        let mut tarballs = Vec::new();
+        for (version, manifest) in document["versions"].as_object().into_iter().flatten() {
+            let Ok(parsed) = node_semver::Version::parse(version) else {
+                return Ok(error(
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid version {}", version),
+                ));
+            };
+            if existing.contains(&parsed) {
+                return Ok(error(
+                    StatusCode::FORBIDDEN,
+                    format!("{}@{} is already published", name, version),
+                ));
+            }
+
+            let attachment = &document["_attachments"][format!("{}-{}.tgz", name, version)];
+            let Some(tarball) = attachment["data"]
+                .as_str()
+                .and_then(|data| BASE64.decode(data).ok())
+            else {
+                return Ok(error(
+                    StatusCode::BAD_REQUEST,
+                    format!("Missing the tarball of {}@{}", name, version),
+                ));
+            };
+            if let Some(expected) = manifest["dist"]["integrity"].as_str()
+                && expected != integrity(&tarball)
+            {
+                return Ok(error(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "The tarball of {}@{} doesn't match its integrity",
+                        name, version
+                    ),
+                ));
+            }
+
+            tarballs.push((version.clone(), tarball));
+        }
+        if tarballs.is_empty() {
+            return Ok(error(
+                StatusCode::BAD_REQUEST,
+                "The document has no versions",
+            ));
+        }
+
+        for (version, tarball) in &tarballs {
+            self.storage.write_tarball(name, version, tarball).await?;
+        }
+        let dist_tags: BTreeMap<String, String> = document["dist-tags"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(tag, version)| Some((tag.clone(), version.as_str()?.to_string())))
+            .collect();
+        self.storage.set_dist_tags(name, dist_tags).await?;
+
+        Ok(json(
+            StatusCode::CREATED,
+            &serde_json::json!({ "ok": true }),
+        ))
+    }
+}
+
+/// Compare tokens without returning early, so timing doesn't tell how much matched
+fn same_token(sent: &str, token: &str) -> bool {
+    sent.len() == token.len()
+        && sent
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+impl RegistryServer {
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Serve requests until the process stops
+    pub async fn run(self) -> Result<()> {
+        let registry = self.registry;
+        let make_service = make_service_fn(move |_| {
+            let registry = registry.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let registry = registry.clone();
+                    async move { Ok::<_, Infallible>(registry.handle(request).await) }
+                }))
+            }
+        });
+
+        Server::from_tcp(self.listener)?.serve(make_service).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// A gzipped tarball holding `package/package.json`
+    fn tarball(name: &str, version: &str) -> Vec<u8> {
+        let manifest = format!(r#"{{"name": "{}", "version": "{}"}}"#, name, version);
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "package/package.json", manifest.as_bytes())
+            .unwrap();
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&builder.into_inner().unwrap()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn serve(registry: Registry) -> String {
+        let server = registry.bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.local_addr().unwrap());
+        tokio::spawn(server.run());
+        url
+    }
+
+    #[test]
+    fn test_route_parse() {
+        assert!(matches!(
+            Route::parse("/@acme%2flib"),
+            Some(Route::Packument { name }) if name == "@acme/lib"
+        ));
+        assert!(matches!(
+            Route::parse("/@acme/lib/-/lib-1.0.0-beta.1.tgz"),
+            Some(Route::Tarball { name, version }) if name == "@acme/lib" && version == "1.0.0-beta.1"
+        ));
+        assert!(matches!(Route::parse("/-/ping"), Some(Route::Ping)));
+        assert!(Route::parse("/lib/-/other-1.0.0.tgz").is_none());
+        assert!(Route::parse("/..%2f..%2fetc").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_serve_stored_packages() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path().to_path_buf());
+        let stored = tarball("@acme/lib", "1.0.0");
+        storage
+            .write_tarball("@acme/lib", "1.0.0", &stored)
+            .await
+            .unwrap();
+        storage
+            .write_tarball(
+                "@acme/lib",
+                "2.0.0-rc.1",
+                &tarball("@acme/lib", "2.0.0-rc.1"),
+            )
+            .await
+            .unwrap();
+        let url = serve(Registry::new(storage));
+
+        let network = Network::new();
+        let packument: serde_json::Value = network
+            .fetch(&format!("{}/@acme%2flib", url))
+            .await
+            .unwrap();
+        assert_eq!(packument["dist-tags"]["latest"], "1.0.0");
+        let dist = &packument["versions"]["1.0.0"]["dist"];
+        assert_eq!(
+            dist["tarball"],
+            format!("{}/@acme/lib/-/lib-1.0.0.tgz", url)
+        );
+        assert_eq!(dist["integrity"], integrity(&stored));
+
+        let download = dir.path().join("download.tgz");
+        network
+            .download(dist["tarball"].as_str().unwrap(), download.clone())
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(download).unwrap(), stored);
+
+        assert!(
+            network
+                .fetch::<serde_json::Value>(&format!("{}/missing", url))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_publish() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path().join("published"));
+        let cache = Storage::new(dir.path().join("cache"));
+        cache
+            .write_tarball("lib", "0.1.0", &tarball("lib", "0.1.0"))
+            .await
+            .unwrap();
+        let url = serve(
+            Registry::new(storage)
+                .with_cache(cache)
+                .with_token("secret".to_string()),
+        );
+        let published = tarball("lib", "1.0.0");
+        let document = serde_json::json!({
+            "name": "lib",
+            "dist-tags": { "beta": "1.0.0" },
+            "versions": { "1.0.0": { "dist": { "integrity": integrity(&published) } } },
+            "_attachments": { "lib-1.0.0.tgz": { "data": BASE64.encode(&published) } },
+        });
+
+        let network = Network::new();
+        let auth = [("authorization", "Bearer secret".to_string())];
+        for headers in [&[][..], &[("authorization", "Bearer wrong".to_string())]] {
+            let error = network
+                .put(&format!("{}/lib", url), &document, headers)
+                .await
+                .unwrap_err();
+            assert!(error.to_string().starts_with("401 Unauthorized"));
+        }
+        network
+            .put(&format!("{}/lib", url), &document, &auth)
+            .await
+            .unwrap();
+        let error = network
+            .put(&format!("{}/lib", url), &document, &auth)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().starts_with("403 Forbidden"));
+
+        // Published packages are kept apart from the cache, both are served
+        assert!(dir.path().join("published/lib-1.0.0.tgz").is_file());
+        assert!(!dir.path().join("cache/lib-1.0.0.tgz").exists());
+        let packument: serde_json::Value = network.fetch(&format!("{}/lib", url)).await.unwrap();
+        assert_eq!(packument["dist-tags"]["beta"], "1.0.0");
+        assert_eq!(packument["dist-tags"]["latest"], "1.0.0");
+        assert_eq!(packument["versions"]["1.0.0"]["name"], "lib");
+        assert_eq!(packument["versions"]["0.1.0"]["name"], "lib");
+    }
+
+    #[tokio::test]
+    async fn test_publish_disabled_without_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = serve(Registry::new(Storage::new(dir.path().to_path_buf())));
+
+        let error = Network::new()
+            .put(
+                &format!("{}/lib", url),
+                &serde_json::json!({ "name": "lib" }),
+                &[("authorization", "Bearer secret".to_string())],
+            )
+            .await
+            .unwrap_err();
+        assert!(error.to_string().starts_with("403 Forbidden"));
+    }
+
+    #[tokio::test]
+    async fn test_proxy_upstream() {
+        let upstream_dir = tempfile::tempdir().unwrap();
+        let upstream_storage = Storage::new(upstream_dir.path().to_path_buf());
+        upstream_storage
+            .write_tarball("lib", "1.0.0", &tarball("lib", "1.0.0"))
+            .await
+            .unwrap();
+        let upstream = serve(Registry::new(upstream_storage));
+
+        let dir = tempfile::tempdir().unwrap();
+        let url =
+            serve(Registry::new(Storage::new(dir.path().to_path_buf())).with_upstream(upstream));
+
+        let network = Network::new();
+        let packument: serde_json::Value = network.fetch(&format!("{}/lib", url)).await.unwrap();
+        let tarball = packument["versions"]["1.0.0"]["dist"]["tarball"]
+            .as_str()
+            .unwrap();
+        assert_eq!(tarball, format!("{}/lib/-/lib-1.0.0.tgz", url));
+
+        // Downloading through the proxy keeps the tarball
+        network
+            .download(tarball, dir.path().join("download.tgz"))
+            .await
+            .unwrap();
+        assert!(dir.path().join("lib-1.0.0.tgz").is_file());
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use contract::Result;
+use node_semver::Version;
+use tarball::gzip::read_entry;
+
+use crate::dist::{integrity, shasum};
+
+/// Where a registry serves the tarball of `name@version` from, the npm layout
+/// `<registry>/@scope/lib/-/lib-1.0.0.tgz`
+pub fn tarball_url(registry: &str, name: &str, version: &str) -> String {
+    let unscoped = name.rsplit('/').next().unwrap_or(name);
+    format!("{}/{}/-/{}-{}.tgz", registry, name, unscoped, version)
+}
+
+/// Packages kept as `<name>-<version>.tgz` in a directory, the layout of the
+/// craft cache. Dist-tags of published packages are kept next to them in
+/// `<name>.dist-tags.json`.
+#[derive(Debug, Clone)]
+pub struct Storage {
+    dir: PathBuf,
+}
+
+impl Storage {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Whether `name` is a package name that stays inside the directory
+    pub fn is_valid_name(name: &str) -> bool {
+        let parts: Vec<&str> = name.split('/').collect();
+        let scoped = match parts.as_slice() {
+            [_] => false,
+            [scope, _] if scope.starts_with('@') => true,
+            _ => return false,
+        };
+        (scoped || !name.starts_with('@'))
+            && parts.iter().all(|part| {
+                !part.is_empty()
+                    && !part.starts_with('.')
+                    && !part.contains(['\\', ':'])
+                    && !part.chars().any(char::is_control)
+            })
+    }
+
+    pub fn tarball_path(&self, name: &str, version: &str) -> PathBuf {
+        self.dir.join(format!("{}-{}.tgz", name, version))
+    }
+
+    fn dist_tags_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.dist-tags.json", name))
+    }
+
+    /// Versions of `name` with a tarball in the directory
+    pub async fn versions(&self, name: &str) -> Result<Vec<Version>> {
+        let prefix_path = self.dir.join(format!("{}-", name));
+        let (Some(parent), Some(prefix)) = (prefix_path.parent(), prefix_path.file_name()) else {
+            return Ok(Vec::new());
+        };
+        let prefix = prefix.to_string_lossy().to_string();
+
+        let mut entries = match tokio::fs::read_dir(parent).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut versions = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            // `lib-utils-1.0.0.tgz` isn't a version of lib, `utils-1.0.0` doesn't parse
+            let version = file_name
+                .strip_prefix(prefix.as_str())
+                .and_then(|rest| rest.strip_suffix(".tgz"))
+                .and_then(|version| Version::parse(version).ok());
+            if let Some(version) = version.filter(|_| !entry.path().is_dir()) {
+                versions.push(version);
+            }
+        }
+
+        versions.sort();
+        Ok(versions)
+    }
+
+    pub async fn tarball(&self, name: &str, version: &str) -> Result<Option<Vec<u8>>> {
+        match tokio::fs::read(self.tarball_path(name, version)).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn write_tarball(&self, name: &str, version: &str, tarball: &[u8]) -> Result<()> {
+        let path = self.tarball_path(name, version);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        // Installs reading the cache never see a partial tarball
+        let partial = path.with_extension("tgz.partial");
+        tokio::fs::write(&partial, tarball).await?;
+        tokio::fs::rename(&partial, &path).await?;
+
+        Ok(())
+    }
+
+    pub async fn dist_tags(&self, name: &str) -> Result<BTreeMap<String, String>> {
+        match tokio::fs::read_to_string(self.dist_tags_path(name)).await {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Point `tags` to their versions, keeping the other tags of `name`
+    pub async fn set_dist_tags(&self, name: &str, tags: BTreeMap<String, String>) -> Result<()> {
+        let mut dist_tags = self.dist_tags(name).await?;
+        dist_tags.extend(tags);
+
+        let path = self.dist_tags_path(name);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, serde_json::to_string_pretty(&dist_tags)?).await?;
+
+        Ok(())
+    }
+
+    /// The registry document of `name` listing every stored version, with
+    /// tarball URLs below `registry`. `latest` defaults to the highest stable
+    /// version.
+    pub async fn packument(&self, name: &str, registry: &str) -> Result<Option<serde_json::Value>> {
+        let versions = self.versions(name).await?;
+        let mut manifests = serde_json::Map::new();
+
+        for version in &versions {
+            let version = version.to_string();
+            let Some(tarball) = self.tarball(name, &version).await? else {
+                continue;
+            };
+
+            let (tarball, package_json) = tokio::task::spawn_blocking(move || {
+                let package_json = read_entry(&tarball, "package.json");
+                (tarball, package_json)
+            })
+            .await?;
+            let mut manifest = match package_json? {
+                Some(bytes) => serde_json::from_slice(&bytes)?,
+                None => serde_json::json!({ "name": name, "version": version }),
+            };
+
+            manifest["_id"] = format!("{}@{}", name, version).into();
+            manifest["dist"] = serde_json::json!({
+                "tarball": tarball_url(registry, name, &version),
+                "integrity": integrity(&tarball),
+                "shasum": shasum(&tarball),
+            });
+            manifests.insert(version, manifest);
+        }
+
+        if manifests.is_empty() {
+            return Ok(None);
+        }
+
+        let mut dist_tags = self.dist_tags(name).await?;
+        dist_tags.retain(|_, version| manifests.contains_key(version));
+        if !dist_tags.contains_key("latest") {
+            let latest = versions
+                .iter()
+                .rfind(|version| version.pre_release.is_empty())
+                .or(versions.last());
+            if let Some(latest) = latest {
+                dist_tags.insert("latest".to_string(), latest.to_string());
+            }
+        }
+
+        Ok(Some(serde_json::json!({
+            "_id": name,
+            "name": name,
+            "dist-tags": dist_tags,
+            "versions": manifests,
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_name() {
+        assert!(Storage::is_valid_name("lodash"));
+        assert!(Storage::is_valid_name("@acme/lib"));
+        assert!(!Storage::is_valid_name("../etc/passwd"));
+        assert!(!Storage::is_valid_name("@acme/../lib"));
+        assert!(!Storage::is_valid_name("acme/lib"));
+        assert!(!Storage::is_valid_name("@acme"));
+        assert!(!Storage::is_valid_name(""));
+    }
+
+    #[tokio::test]
+    async fn test_versions_ignore_similar_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path().to_path_buf());
+        for file in [
+            "lib-1.0.0.tgz",
+            "lib-2.0.0-beta.1.tgz",
+            "lib-utils-1.0.0.tgz",
+            "lib-1.0.0.tgz.partial",
+        ] {
+            std::fs::write(dir.path().join(file), "").unwrap();
+        }
+        std::fs::create_dir(dir.path().join("lib-1.0.0")).unwrap();
+
+        let versions: Vec<String> = storage
+            .versions("lib")
+            .await
+            .unwrap()
+            .iter()
+            .map(Version::to_string)
+            .collect();
+        assert_eq!(versions, ["1.0.0", "2.0.0-beta.1"]);
+        assert!(storage.versions("@acme/lib").await.unwrap().is_empty());
+    }
+}
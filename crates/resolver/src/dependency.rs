@@ -1,3 +1,22 @@
+use std::path::PathBuf;
+
+use package::InstallPackage;
+
+/// Where a dependency is resolved from
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpecKind {
+    /// Version range (or dist-tag) on the npm registry
+    Registry,
+    /// Git repository, hosted shorthand or URL
+    Git(String),
+    /// Local directory or `.tgz` copied into the cache (`file:../lib`)
+    File(PathBuf),
+    /// Local directory symlinked as-is, without installing its dependencies (`link:../lib`)
+    Link(PathBuf),
+    /// Remote tarball URL
+    Tarball(String),
+}
+
 /// Represents a parsed dependency specification
 #[derive(Debug, Clone, PartialEq)]
 pub struct DependencySpec {
@@ -5,6 +24,8 @@ pub struct DependencySpec {
     pub package_name: String,
     /// The version requirement (None means latest)
     pub version: Option<String>,
+    /// Where the package comes from
+    pub kind: SpecKind,
 }
 
 impl DependencySpec {
@@ -14,7 +35,18 @@ impl DependencySpec {
     /// - Regular: ("package", Some("^1.0.0")) -> DependencySpec { package_name: "package", version: Some("^1.0.0") }
     /// - NPM alias: ("alias", Some("npm:actual-package@^1.0.0")) -> DependencySpec { package_name: "actual-package", version: Some("^1.0.0") }
     /// - No version: ("package", None) -> DependencySpec { package_name: "package", version: None }
+    /// - Protocols: ("lib", Some("file:../lib")) -> DependencySpec { kind: SpecKind::File("../lib"), .. }
+    ///
+    /// For protocol specs without an alias (`craft add link:../lib`) the spec is the name itself.
     pub fn parse(package_name: &str, version: Option<&str>) -> Self {
+        if let Some(kind) = Self::parse_kind(version.unwrap_or(package_name)) {
+            return Self {
+                package_name: package_name.to_string(),
+                version: None,
+                kind,
+            };
+        }
+
         if let Some(version_str) = version {
             if let Some(spec) = Self::parse_npm_alias(version_str) {
                 return spec;
@@ -23,16 +55,35 @@ impl DependencySpec {
             Self {
                 package_name: package_name.to_string(),
                 version: Some(version_str.to_string()),
+                kind: SpecKind::Registry,
             }
         } else {
             // No version specified, use latest
             Self {
                 package_name: package_name.to_string(),
                 version: None,
+                kind: SpecKind::Registry,
             }
         }
     }
 
+    /// Detect non-registry specifications, `None` means a registry range
+    fn parse_kind(spec: &str) -> Option<SpecKind> {
+        if let Some(path) = spec.strip_prefix("link:") {
+            Some(SpecKind::Link(PathBuf::from(path)))
+        } else if let Some(path) = spec.strip_prefix("file:") {
+            Some(SpecKind::File(PathBuf::from(path)))
+        } else if InstallPackage::is_local_spec(spec) {
+            Some(SpecKind::File(PathBuf::from(spec)))
+        } else if InstallPackage::is_tarball_spec(spec) {
+            Some(SpecKind::Tarball(spec.to_string()))
+        } else if InstallPackage::is_git_spec(spec) {
+            Some(SpecKind::Git(spec.to_string()))
+        } else {
+            None
+        }
+    }
+
     /// Parse npm alias format: npm:package-name@version
     fn parse_npm_alias(version_str: &str) -> Option<Self> {
         if !version_str.starts_with("npm:") {
@@ -47,27 +98,37 @@ impl DependencySpec {
             Some(Self {
                 package_name: pkg_name.to_string(),
                 version: Some(ver.to_string()),
+                kind: SpecKind::Registry,
             })
         } else {
             // npm:package-name without version
             Some(Self {
                 package_name: without_prefix.to_string(),
                 version: None,
+                kind: SpecKind::Registry,
             })
         }
     }
 }
 
-impl From<&package::InstallPackage> for DependencySpec {
-    fn from(package: &package::InstallPackage) -> Self {
-        Self::parse(&package.name, package.version.as_deref())
+impl From<&InstallPackage> for DependencySpec {
+    fn from(package: &InstallPackage) -> Self {
+        let mut spec = Self::parse(&package.name, package.version.as_deref());
+        // Relative paths point from the package.json declaring them
+        if let (Some(base_dir), SpecKind::File(path) | SpecKind::Link(path)) =
+            (&package.base_dir, &mut spec.kind)
+            && path.is_relative()
+            && !path.starts_with("~")
+        {
+            *path = base_dir.join(&*path);
+        }
+        spec
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use package::InstallPackage;
 
     #[test]
     fn test_parse_regular_version() {
@@ -104,6 +165,44 @@ mod tests {
         assert_eq!(spec.version, Some("^4.2.0".to_string()));
     }
 
+    #[test]
+    fn test_parse_file_protocol() {
+        let spec = DependencySpec::parse("lib", Some("file:../lib"));
+        assert_eq!(spec.package_name, "lib");
+        assert_eq!(spec.version, None);
+        assert_eq!(spec.kind, SpecKind::File(PathBuf::from("../lib")));
+
+        let spec = DependencySpec::parse("patched", Some("./vendor/patched-1.0.0.tgz"));
+        assert_eq!(
+            spec.kind,
+            SpecKind::File(PathBuf::from("./vendor/patched-1.0.0.tgz"))
+        );
+    }
+
+    #[test]
+    fn test_parse_link_protocol() {
+        let spec = DependencySpec::parse("link:../lib", None);
+        assert_eq!(spec.kind, SpecKind::Link(PathBuf::from("../lib")));
+    }
+
+    #[test]
+    fn test_parse_tarball_and_git() {
+        let spec = DependencySpec::parse("pkg", Some("https://example.com/pkg-1.0.0.tgz"));
+        assert_eq!(
+            spec.kind,
+            SpecKind::Tarball("https://example.com/pkg-1.0.0.tgz".to_string())
+        );
+
+        let spec = DependencySpec::parse("foo", Some("github:user/repo#v1.2.0"));
+        assert_eq!(
+            spec.kind,
+            SpecKind::Git("github:user/repo#v1.2.0".to_string())
+        );
+
+        let spec = DependencySpec::parse("react", Some("^18.0.0"));
+        assert_eq!(spec.kind, SpecKind::Registry);
+    }
+
     #[test]
     fn test_from_install_package() {
         let package = InstallPackage::new("react".to_string(), Some("^18.0.0".to_string()), false);
@@ -123,4 +222,26 @@ mod tests {
         assert_eq!(spec.package_name, "wrap-ansi");
         assert_eq!(spec.version, Some("^7.0.0".to_string()));
     }
+
+    #[test]
+    fn test_from_install_package_with_base_dir() {
+        let base_dir = PathBuf::from("/work/packages/app");
+        let package =
+            InstallPackage::new("lib".to_string(), Some("file:../lib".to_string()), false)
+                .with_base_dir(base_dir.clone());
+        let spec: DependencySpec = (&package).into();
+        assert_eq!(spec.kind, SpecKind::File(base_dir.join("../lib")));
+
+        let package = InstallPackage::new("link:./shared".to_string(), None, false)
+            .with_base_dir(base_dir.clone());
+        let spec: DependencySpec = (&package).into();
+        assert_eq!(spec.kind, SpecKind::Link(base_dir.join("./shared")));
+
+        for absolute in ["file:/opt/lib", "file:~/lib"] {
+            let package = InstallPackage::new("lib".to_string(), Some(absolute.to_string()), false)
+                .with_base_dir(base_dir.clone());
+            let spec: DependencySpec = (&package).into();
+            assert_eq!(spec.kind, SpecKind::File(PathBuf::from(&absolute[5..])));
+        }
+    }
 }
@@ -1,62 +1,288 @@
-use crate::ResolvedArtifact;
+use crate::resolved_artifact::cache_root;
+use crate::{ArtifactSource, ResolvedArtifact};
 use anyhow::{Context, Result};
-use package::InstallPackage;
+use network::{Network, Transport};
+use package::{HostedGit, InstallPackage, PackageJson};
+use process::Process;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Where a git dependency can be fetched from
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitLocation {
+    /// Archive of the requested committish, only known for hosted repositories
+    pub archive_url: Option<String>,
+    /// URL for a full `git clone`
+    pub clone_url: String,
+    pub committish: Option<String>,
+}
 
 #[derive(Debug)]
-pub struct GitResolver;
+pub struct GitResolver {
+    network: Arc<dyn Transport>,
+    cache: Option<PathBuf>,
+}
 
 impl GitResolver {
     pub fn new() -> Self {
-        Self
+        Self {
+            network: Arc::new(Network::new()),
+            cache: None,
+        }
     }
 
-    pub fn resolve(&self, package: &InstallPackage) -> Result<ResolvedArtifact> {
-        let git_url = &package.name;
+    /// Download archives of hosted repositories through `transport`
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.network = transport;
+        self
+    }
+
+    /// Fetch repositories into `dir` instead of the global package cache
+    pub fn with_cache_dir(mut self, dir: PathBuf) -> Self {
+        self.cache = Some(dir);
+        self
+    }
 
-        // Parse git URL to extract repository information
-        let (name, _, download_url) = self.parse_git_url(git_url)?;
+    pub async fn resolve(&self, package: &InstallPackage) -> Result<ResolvedArtifact> {
+        let spec = package.spec();
+        // Branches and tags move, the cache entry is for the commit they point to now
+        let location = self.locate(spec)?;
+        let commit = Self::resolve_commit(&location).await?;
+        let location = Self::pin(spec, location, &commit);
+        let source = ArtifactSource::Git {
+            spec: spec.to_string(),
+            commit: commit.clone(),
+        };
+
+        let cache_key = source
+            .cache_key()
+            .context("Git sources always have a cache key")?;
+        let cache_dir = cache_root(self.cache.as_deref()).join(&cache_key);
+        let package_dir = cache_dir.join("package");
+
+        if !package_dir.join("package.json").exists() {
+            let fetched = match &location.archive_url {
+                Some(archive_url) => match self.fetch_archive(archive_url, &cache_key).await {
+                    Ok(()) => true,
+                    Err(e) => {
+                        debug::warning!(
+                            "Failed to fetch archive {}, falling back to clone: {}",
+                            archive_url,
+                            e
+                        );
+                        false
+                    }
+                },
+                None => false,
+            };
+
+            if !fetched {
+                self.clone(&location, &package_dir).await?;
+            }
+        } else {
+            debug::info!("Git package {} already fetched", spec);
+        }
+
+        // The URL only tells us the repository name, the manifest has the package name
+        let pkg_json = PackageJson::from_path(&package_dir.join("package.json"))
+            .await
+            .with_context(|| format!("Git dependency {} has no package.json", spec))?;
+
+        let name = match &pkg_json.name {
+            Some(name) => name.clone(),
+            None => Self::repository_name(&location.clone_url)?,
+        };
+        let version = pkg_json
+            .version
+            .clone()
+            .unwrap_or_else(|| "0.0.0".to_string());
+
+        debug::trace!("Resolved git package {} as {}@{}", spec, name, version);
 
         Ok(ResolvedArtifact {
-            name: name.to_string(),
-            version: "git".to_string(),
-            download_url: download_url.to_string(),
-            package: None,
+            name,
+            version,
+            download_url: location
+                .archive_url
+                .unwrap_or_else(|| format!("{}#{}", location.clone_url, commit)),
+            package: Some(pkg_json),
+            source,
+            patch: None,
+            cache: self.cache.clone(),
+            scope: None,
+            dependency_scope: None,
         })
     }
 
-    fn parse_git_url(&self, git_url: &str) -> Result<(String, String, String)> {
-        // Remove git protocol prefixes
-        let url = git_url
-            .trim_start_matches("git:")
-            .trim_start_matches("git+ssh:")
-            .trim_start_matches("git+http:")
-            .trim_start_matches("git+https:")
-            .trim_start_matches("ssh:");
-
-        // Extract name from URL (last part of path without .git)
-        let name = url
-            .split('/')
-            .next_back()
-            .context("Invalid git URL: no path segments")?
-            .trim_end_matches(".git")
-            .to_string();
+    /// Map a git specification to archive and clone URLs
+    pub fn locate(&self, spec: &str) -> Result<GitLocation> {
+        if let Some(hosted) = HostedGit::parse(spec) {
+            return Ok(GitLocation {
+                archive_url: Some(hosted.tarball_url()),
+                clone_url: hosted.clone_url(),
+                committish: hosted.committish,
+            });
+        }
 
-        // For now, use "git" as version identifier
-        let version = "git".to_string();
+        let (url, committish) = match spec.split_once('#') {
+            Some((url, committish)) if !committish.is_empty() => {
+                (url, Some(committish.to_string()))
+            }
+            Some((url, _)) => (url, None),
+            None => (spec, None),
+        };
 
-        // Normalize the download URL
-        let download_url = if git_url.starts_with("git:")
-            || git_url.starts_with("git+")
-            || git_url.starts_with("ssh:")
+        // Normalize npm-style prefixes into something `git clone` understands
+        let url = url.trim_start_matches("git+");
+        let clone_url = if let Some(rest) = url.strip_prefix("git:")
+            && !rest.starts_with("//")
         {
-            git_url.to_string()
-        } else if git_url.ends_with(".git") {
-            format!("git+https://{}", url)
+            format!("git://{}", rest)
+        } else if let Some(rest) = url.strip_prefix("ssh:")
+            && !rest.starts_with("//")
+        {
+            rest.to_string()
         } else {
-            git_url.to_string()
+            url.to_string()
         };
 
-        Ok((name, version, download_url))
+        Self::repository_name(&clone_url)?;
+
+        Ok(GitLocation {
+            archive_url: None,
+            clone_url,
+            committish,
+        })
+    }
+
+    /// Whether `committish` already names a commit rather than a branch or tag
+    fn is_commit(committish: &str) -> bool {
+        (7..=40).contains(&committish.len()) && committish.chars().all(|c| c.is_ascii_hexdigit())
+    }
+
+    /// The commit the requested branch, tag or `HEAD` points to
+    async fn resolve_commit(location: &GitLocation) -> Result<String> {
+        if let Some(committish) = &location.committish
+            && Self::is_commit(committish)
+        {
+            return Ok(committish.to_lowercase());
+        }
+
+        let committish = location.committish.as_deref().unwrap_or("HEAD");
+        let refs = Process::new("git")
+            .args(&["ls-remote", &location.clone_url, committish])
+            .output()
+            .await
+            .with_context(|| format!("Failed to list the refs of {}", location.clone_url))?;
+
+        Self::commit_of(&refs, committish).with_context(|| {
+            format!(
+                "{} has no branch or tag named {}",
+                location.clone_url, committish
+            )
+        })
+    }
+
+    /// The commit of `committish` in `git ls-remote` output, annotated tags
+    /// being resolved to the commit they point to
+    fn commit_of(refs: &str, committish: &str) -> Option<String> {
+        let refs: Vec<(&str, &str)> = refs
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .collect();
+
+        let candidates = [
+            format!("refs/tags/{}^{{}}", committish),
+            format!("refs/tags/{}", committish),
+            format!("refs/heads/{}", committish),
+            committish.to_string(),
+        ];
+        candidates.iter().find_map(|candidate| {
+            refs.iter()
+                .find(|(_, name)| name == candidate)
+                .map(|(commit, _)| commit.to_string())
+        })
+    }
+
+    /// `location` fetching exactly `commit`
+    fn pin(spec: &str, location: GitLocation, commit: &str) -> GitLocation {
+        let archive_url = HostedGit::parse(spec).map(|hosted| {
+            HostedGit {
+                committish: Some(commit.to_string()),
+                ..hosted
+            }
+            .tarball_url()
+        });
+
+        GitLocation {
+            archive_url,
+            committish: Some(commit.to_string()),
+            ..location
+        }
+    }
+
+    /// Last path segment of a repository URL, without `.git`
+    fn repository_name(url: &str) -> Result<String> {
+        let name = url
+            .rsplit(['/', ':'])
+            .next()
+            .context("Invalid git URL: no path segments")?
+            .trim_end_matches(".git");
+
+        if name.is_empty() {
+            anyhow::bail!("Invalid git URL: {}", url);
+        }
+
+        Ok(name.to_string())
+    }
+
+    async fn fetch_archive(&self, archive_url: &str, cache_key: &str) -> Result<()> {
+        let cache_dir = cache_root(self.cache.as_deref());
+        tokio::fs::create_dir_all(&cache_dir).await?;
+
+        let archive_path = cache_dir.join(format!("{}.tgz", cache_key));
+        debug::info!(
+            "Downloading git archive {} to {:?}",
+            archive_url,
+            archive_path
+        );
+
+        self.network
+            .download(archive_url, archive_path.clone())
+            .await?;
+        tarball::gzip::unpack(archive_path, cache_dir.join(cache_key)).await?;
+
+        Ok(())
+    }
+
+    async fn clone(&self, location: &GitLocation, package_dir: &Path) -> Result<()> {
+        debug::info!("Cloning {} into {:?}", location.clone_url, package_dir);
+
+        if package_dir.exists() {
+            tokio::fs::remove_dir_all(package_dir).await?;
+        }
+        if let Some(parent) = package_dir.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let dir = package_dir.to_string_lossy();
+        Process::new("git")
+            .args(&["clone", "--quiet", &location.clone_url, &dir])
+            .run()
+            .await?;
+
+        if let Some(committish) = &location.committish {
+            Process::new("git")
+                .args(&["checkout", "--quiet", committish])
+                .current_dir(&dir)
+                .run()
+                .await?;
+        }
+
+        // History is not part of the installed package
+        tokio::fs::remove_dir_all(package_dir.join(".git")).await?;
+
+        Ok(())
     }
 }
 
@@ -71,57 +297,105 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_resolve_git_url() {
-        let pkg = InstallPackage::new("git:github.com/user/repo.git".to_string(), None, false);
+    fn test_locate_git_url() {
         let resolver = GitResolver::new();
-        let result = resolver.resolve(&pkg);
+        let location = resolver
+            .locate("git:gitlab.example.com/user/repo.git")
+            .expect("Failed to locate git package");
 
-        assert!(result.is_ok());
-        let artifact = result.expect("Failed to resolve git package");
-        assert_eq!(artifact.name, "repo");
-        assert_eq!(artifact.version, "git");
-        assert_eq!(artifact.download_url, "git:github.com/user/repo.git");
+        assert_eq!(location.archive_url, None);
+        assert_eq!(location.clone_url, "git://gitlab.example.com/user/repo.git");
+        assert_eq!(location.committish, None);
     }
 
     #[test]
-    fn test_resolve_git_ssh_url() {
-        let pkg = InstallPackage::new(
-            "git+ssh:git@github.com:user/repo.git".to_string(),
-            None,
-            false,
-        );
+    fn test_locate_git_ssh_url() {
+        let resolver = GitResolver::new();
+        let location = resolver
+            .locate("git+ssh://git@example.com:user/repo.git#v2")
+            .expect("Failed to locate git ssh package");
+
+        assert_eq!(location.clone_url, "ssh://git@example.com:user/repo.git");
+        assert_eq!(location.committish, Some("v2".to_string()));
+    }
+
+    #[test]
+    fn test_locate_hosted_url() {
         let resolver = GitResolver::new();
-        let result = resolver.resolve(&pkg);
+        let location = resolver
+            .locate("https://github.com/user/repo.git")
+            .expect("Failed to locate https git package");
 
-        assert!(result.is_ok());
-        let artifact = result.expect("Failed to resolve git ssh package");
-        assert_eq!(artifact.name, "repo");
         assert_eq!(
-            artifact.download_url,
-            "git+ssh:git@github.com:user/repo.git"
+            location.archive_url,
+            Some("https://codeload.github.com/user/repo/tar.gz/HEAD".to_string())
         );
+        assert_eq!(location.clone_url, "https://github.com/user/repo.git");
     }
 
     #[test]
-    fn test_resolve_https_git_url() {
-        let pkg = InstallPackage::new("https://github.com/user/repo.git".to_string(), None, false);
+    fn test_locate_hosted_shorthand() {
         let resolver = GitResolver::new();
-        let result = resolver.resolve(&pkg);
+        let location = resolver
+            .locate("gitlab:package/psc#main")
+            .expect("Failed to locate gitlab package");
+
+        assert_eq!(
+            location.archive_url,
+            Some("https://gitlab.com/package/psc/-/archive/main/psc-main.tar.gz".to_string())
+        );
+        assert_eq!(location.committish, Some("main".to_string()));
+    }
 
-        assert!(result.is_ok());
-        let artifact = result.expect("Failed to resolve https git package");
-        assert_eq!(artifact.name, "repo");
-        assert!(artifact.download_url.contains("github.com/user/repo.git"));
+    #[test]
+    fn test_commit_of() {
+        let refs = "1111111111111111111111111111111111111111\tHEAD\n\
+                    2222222222222222222222222222222222222222\trefs/heads/main\n\
+                    3333333333333333333333333333333333333333\trefs/tags/v1\n\
+                    4444444444444444444444444444444444444444\trefs/tags/v1^{}\n";
+
+        assert_eq!(
+            GitResolver::commit_of(refs, "HEAD").unwrap(),
+            "1".repeat(40)
+        );
+        assert_eq!(
+            GitResolver::commit_of(refs, "main").unwrap(),
+            "2".repeat(40)
+        );
+        // Annotated tags point to the tag object, `^{}` is the commit
+        assert_eq!(GitResolver::commit_of(refs, "v1").unwrap(), "4".repeat(40));
+        assert_eq!(GitResolver::commit_of(refs, "v2"), None);
+        assert!(GitResolver::is_commit("a1b2c3d"));
+        assert!(!GitResolver::is_commit("main"));
     }
 
     #[test]
-    fn test_resolve_gitlab_url() {
-        let pkg = InstallPackage::new("git:gitlab.com/package/psc".to_string(), None, false);
+    fn test_pin() {
         let resolver = GitResolver::new();
-        let result = resolver.resolve(&pkg);
+        let spec = "github:user/repo#main";
+        let commit = "a".repeat(40);
+        let location = GitResolver::pin(spec, resolver.locate(spec).unwrap(), &commit);
+
+        assert_eq!(
+            location.archive_url,
+            Some(format!(
+                "https://codeload.github.com/user/repo/tar.gz/{}",
+                commit
+            ))
+        );
+        assert_eq!(location.committish, Some(commit));
+    }
 
-        assert!(result.is_ok());
-        let artifact = result.expect("Failed to resolve gitlab package");
-        assert_eq!(artifact.name, "psc");
+    #[test]
+    fn test_repository_name() {
+        assert_eq!(
+            GitResolver::repository_name("https://github.com/user/repo.git").expect("name"),
+            "repo"
+        );
+        assert_eq!(
+            GitResolver::repository_name("git@example.com:repo.git").expect("name"),
+            "repo"
+        );
+        assert!(GitResolver::repository_name("https://example.com/").is_err());
     }
 }
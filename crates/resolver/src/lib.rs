@@ -1,12 +1,16 @@
 pub mod dependency;
 mod download_artifact;
 pub mod git;
+pub mod local;
 pub mod npm;
+pub mod remote;
 pub mod resolved_artifact;
 pub mod resolver;
 
-pub use dependency::DependencySpec;
-pub use git::GitResolver;
+pub use dependency::{DependencySpec, SpecKind};
+pub use git::{GitLocation, GitResolver};
+pub use local::LocalResolver;
 pub use npm::NpmResolver;
-pub use resolved_artifact::ResolvedArtifact;
+pub use remote::RemoteTarballResolver;
+pub use resolved_artifact::{ArtifactSource, ResolvedArtifact};
 pub use resolver::Resolver;
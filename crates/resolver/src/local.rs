@@ -0,0 +1,229 @@
+use crate::resolved_artifact::cache_root;
+use crate::{ArtifactSource, ResolvedArtifact};
+use anyhow::{Context, Result};
+use package::{InstallPackage, PackageJson};
+use std::path::{Path, PathBuf};
+
+/// Resolves `file:` and `link:` dependencies from the local file system
+#[derive(Debug)]
+pub struct LocalResolver {
+    cache: Option<PathBuf>,
+}
+
+impl LocalResolver {
+    pub fn new() -> Self {
+        Self { cache: None }
+    }
+
+    /// Copy `file:` dependencies into `dir` instead of the global package cache
+    pub fn with_cache_dir(mut self, dir: PathBuf) -> Self {
+        self.cache = Some(dir);
+        self
+    }
+
+    /// Copy a local directory, or extract a local tarball, into the cache.
+    ///
+    /// Local sources can change between installs, so the cached copy is
+    /// refreshed every time instead of being reused.
+    pub async fn resolve_file(
+        &self,
+        package: &InstallPackage,
+        path: &Path,
+    ) -> Result<ResolvedArtifact> {
+        let path = Self::absolute(path)?;
+        let source = ArtifactSource::File {
+            spec: package.spec().to_string(),
+            path: path.clone(),
+        };
+        let cache_key = source
+            .cache_key()
+            .context("File sources always have a cache key")?;
+        let cache_dir = cache_root(self.cache.as_deref()).join(cache_key);
+        let package_dir = cache_dir.join("package");
+
+        if cache_dir.exists() {
+            tokio::fs::remove_dir_all(&cache_dir).await?;
+        }
+
+        if path.is_dir() {
+            debug::info!("Copying local package {:?} to {:?}", path, package_dir);
+            let from = path.clone();
+            let to = package_dir.clone();
+            tokio::task::spawn_blocking(move || copy_dir(&from, &to)).await??;
+        } else if path.is_file() {
+            debug::info!("Extracting local tarball {:?} to {:?}", path, cache_dir);
+            tarball::gzip::unpack(path.clone(), cache_dir.clone()).await?;
+        } else {
+            anyhow::bail!(
+                "Local dependency {} not found at {:?}",
+                package.spec(),
+                path
+            );
+        }
+
+        let pkg_json = Self::read_package_json(package, &package_dir).await?;
+        Ok(self.artifact(package, &path, pkg_json, source))
+    }
+
+    /// Use a local directory in place
+    pub async fn resolve_link(
+        &self,
+        package: &InstallPackage,
+        path: &Path,
+    ) -> Result<ResolvedArtifact> {
+        let path = Self::absolute(path)?;
+
+        if !path.is_dir() {
+            anyhow::bail!(
+                "Linked dependency {} is not a directory: {:?}",
+                package.spec(),
+                path
+            );
+        }
+
+        let pkg_json = Self::read_package_json(package, &path).await?;
+        let source = ArtifactSource::Link {
+            spec: package.spec().to_string(),
+            path: path.clone(),
+        };
+
+        Ok(self.artifact(package, &path, pkg_json, source))
+    }
+
+    /// Make `path` absolute, expanding `~/`. Specs declared by a package are
+    /// already joined onto its directory, anything else is relative to the
+    /// working directory.
+    fn absolute(path: &Path) -> Result<PathBuf> {
+        let path = match path.strip_prefix("~") {
+            Ok(rest) => {
+                let home = std::env::var("HOME")
+                    .or_else(|_| std::env::var("USERPROFILE"))
+                    .context("Could not determine home directory")?;
+                PathBuf::from(home).join(rest)
+            }
+            Err(_) => path.to_path_buf(),
+        };
+
+        Ok(std::path::absolute(path)?)
+    }
+
+    async fn read_package_json(package: &InstallPackage, dir: &Path) -> Result<PackageJson> {
+        PackageJson::from_path(&dir.join("package.json"))
+            .await
+            .with_context(|| format!("Local dependency {} has no package.json", package.spec()))
+    }
+
+    fn artifact(
+        &self,
+        package: &InstallPackage,
+        path: &Path,
+        pkg_json: PackageJson,
+        source: ArtifactSource,
+    ) -> ResolvedArtifact {
+        // Prefer the manifest name, then the dependency key, then the file name
+        let name = pkg_json.name.clone().unwrap_or_else(|| {
+            if package.name != package.spec() {
+                package.name.clone()
+            } else {
+                path.file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_else(|| package.name.clone())
+            }
+        });
+        let version = pkg_json
+            .version
+            .clone()
+            .unwrap_or_else(|| "0.0.0".to_string());
+
+        ResolvedArtifact {
+            name,
+            version,
+            download_url: path.to_string_lossy().to_string(),
+            package: Some(pkg_json),
+            source,
+            patch: None,
+            cache: self.cache.clone(),
+            scope: None,
+            dependency_scope: None,
+        }
+    }
+}
+
+impl Default for LocalResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Recursively copy a package directory, skipping installed dependencies and VCS data
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+
+        if file_name == "node_modules" || file_name == ".git" {
+            continue;
+        }
+
+        let target = to.join(&file_name);
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resolve_link() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("package.json"),
+            r#"{"name": "local-lib", "version": "2.1.0"}"#,
+        )
+        .unwrap();
+
+        let pkg = InstallPackage::new("lib".to_string(), Some("link:/unused".to_string()), false);
+        let artifact = LocalResolver::new()
+            .resolve_link(&pkg, dir.path())
+            .await
+            .unwrap();
+
+        assert_eq!(artifact.name, "local-lib");
+        assert_eq!(artifact.version, "2.1.0");
+        assert_eq!(artifact.package_dir(), dir.path());
+        assert!(!artifact.installs_dependencies());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_link_missing_dir() {
+        let pkg = InstallPackage::new("link:/does/not/exist".to_string(), None, false);
+        let result = LocalResolver::new()
+            .resolve_link(&pkg, Path::new("/does/not/exist"))
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_copy_dir_skips_node_modules() {
+        let from = tempfile::tempdir().unwrap();
+        let to = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(from.path().join("node_modules/dep")).unwrap();
+        std::fs::create_dir_all(from.path().join("lib")).unwrap();
+        std::fs::write(from.path().join("lib/index.js"), "module.exports = 1").unwrap();
+
+        copy_dir(from.path(), &to.path().join("package")).unwrap();
+
+        assert!(to.path().join("package/lib/index.js").exists());
+        assert!(!to.path().join("package/node_modules").exists());
+    }
+}
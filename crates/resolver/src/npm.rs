@@ -1,56 +1,134 @@
-use crate::{DependencySpec, ResolvedArtifact};
+use crate::{ArtifactSource, DependencySpec, ResolvedArtifact};
 use anyhow::Result;
-use network::Network;
+use contract::DEFAULT_REGISTRY;
+use network::{Network, Transport};
 use node_semver::{Range, Version};
 use package::{InstallPackage, NpmPackage};
+use registry::Storage;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct NpmResolver {
-    client: Network,
+    client: Arc<dyn Transport>,
+    registry: String,
+    mirror: Option<Storage>,
+    offline: bool,
 }
 
 impl NpmResolver {
     pub fn new() -> Self {
         Self {
-            client: Network::new(),
+            client: Arc::new(Network::new()),
+            registry: DEFAULT_REGISTRY.to_string(),
+            mirror: None,
+            offline: false,
         }
     }
 
-    pub async fn resolve(&self, package: &InstallPackage) -> Result<ResolvedArtifact> {
-        // Convert to DependencySpec (handles npm aliases and regular versions)
-        let dep_spec: DependencySpec = package.into();
+    /// Fetch registry documents through `transport`
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.client = transport;
+        self
+    }
+
+    /// Fetch registry documents from `registry` instead of the npm registry
+    pub fn with_registry(mut self, registry: String) -> Self {
+        self.registry = registry.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Resolve packages from the tarballs in `dir` when one of them matches,
+    /// before asking the registry
+    pub fn with_offline_mirror(mut self, dir: PathBuf) -> Self {
+        self.mirror = Some(Storage::new(dir));
+        self
+    }
 
-        let url = format!("https://registry.npmjs.org/{}", dep_spec.package_name);
-        let npm_package = match self.client.fetch::<NpmPackage>(&url).await {
-            Ok(package) => package,
+    /// Resolve packages only from the offline mirror, without asking the registry
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Fetch a package's registry document (all versions and dist-tags)
+    pub async fn fetch_package(&self, name: &str) -> Result<NpmPackage> {
+        let url = format!("{}/{}", self.registry, name);
+        match self.client.fetch::<NpmPackage>(&url).await {
+            Ok(package) => Ok(package),
             Err(e) => {
                 debug::error!("Failed to fetch npm package: {} {}", url, e);
-                return Err(e);
+                Err(e)
             }
+        }
+    }
+
+    /// The registry document of the versions of `name` in the offline mirror,
+    /// when one of them is `version`
+    async fn mirrored_package(
+        &self,
+        name: &str,
+        version: Option<&str>,
+    ) -> Result<Option<NpmPackage>> {
+        let Some(mirror) = &self.mirror else {
+            return Ok(None);
+        };
+        let Some(packument) = mirror.packument(name, &self.registry).await? else {
+            return Ok(None);
         };
 
-        let version = if let Some(ref req_version) = dep_spec.version {
-            // Find a version that satisfies the requirement
-            npm_package
-                .versions
-                .keys()
-                .filter(|v| {
-                    let req = Range::parse(req_version).expect("should not panic");
-                    let ver = Version::parse(v).expect("should not panic");
-                    req.satisfies(&ver)
-                })
-                .max_by(|a, b| {
-                    let ver_a = Version::parse(a).expect("should not panic");
-                    let ver_b = Version::parse(b).expect("should not panic");
-                    ver_a.partial_cmp(&ver_b).expect("should not panic")
-                })
-                .cloned()
+        let npm_package: NpmPackage = serde_json::from_value(packument)?;
+        Ok(Self::select_version(&npm_package, version).map(|_| npm_package))
+    }
+
+    /// The version of `npm_package` a dependency on `version` installs, the
+    /// `latest` one without a version
+    fn select_version(npm_package: &NpmPackage, version: Option<&str>) -> Option<String> {
+        match version {
+            Some(range) => Self::max_satisfying(npm_package, range),
+            None => npm_package.dist_tags.get("latest").cloned(),
+        }
+    }
+
+    /// The highest published version of `npm_package` satisfying `range`
+    pub fn max_satisfying(npm_package: &NpmPackage, range: &str) -> Option<String> {
+        let range = Range::parse(range).ok()?;
+        npm_package
+            .versions
+            .keys()
+            .filter_map(|v| Version::parse(v).ok().map(|ver| (v, ver)))
+            .filter(|(_, ver)| range.satisfies(ver))
+            .max_by(|(_, a), (_, b)| a.cmp(b))
+            .map(|(v, _)| v.clone())
+    }
+
+    pub async fn resolve(&self, package: &InstallPackage) -> Result<ResolvedArtifact> {
+        // Convert to DependencySpec (handles npm aliases and regular versions)
+        let dep_spec: DependencySpec = package.into();
+
+        let name = &dep_spec.package_name;
+        let requested = dep_spec.version.as_deref();
+        let npm_package = if self.offline {
+            self.mirrored_package(name, requested)
+                .await?
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "No version of {} matching {} in the offline mirror",
+                        name,
+                        requested.unwrap_or("latest")
+                    )
+                })?
         } else {
-            // Use latest version from dist-tags
-            npm_package.dist_tags.get("latest").cloned()
+            match self.mirrored_package(name, requested).await? {
+                Some(npm_package) => {
+                    debug::info!("Resolving {} from the offline mirror", name);
+                    npm_package
+                }
+                None => self.fetch_package(name).await?,
+            }
         };
 
-        let version = version.ok_or_else(|| {
+        let version = Self::select_version(&npm_package, requested).ok_or_else(|| {
             anyhow::anyhow!("Version not found for package {}", dep_spec.package_name)
         })?;
 
@@ -75,6 +153,11 @@ impl NpmResolver {
                 .expect("Resolved package should have version property"),
             download_url: dist.tarball.clone(),
             package: Some(pkg_json.clone()),
+            source: ArtifactSource::Registry,
+            patch: None,
+            cache: None,
+            scope: None,
+            dependency_scope: None,
         };
 
         debug::trace!(
@@ -96,27 +179,46 @@ impl Default for NpmResolver {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use registry::MockRegistry;
+
+    async fn registry() -> MockRegistry {
+        let registry = MockRegistry::start().unwrap();
+        for (name, version) in [
+            ("react", "17.0.2"),
+            ("react", "18.2.0"),
+            ("react", "19.0.0-rc.1"),
+            ("wrap-ansi", "7.0.0"),
+            ("wrap-ansi", "8.1.0"),
+        ] {
+            registry
+                .add(name, version, serde_json::json!({}))
+                .await
+                .unwrap();
+        }
+        registry
+    }
 
     #[tokio::test]
     async fn test_resolve_react_latest() {
+        let registry = registry().await;
         let pkg = InstallPackage::new("react".to_string(), None, false);
-        let resolver = NpmResolver::new();
+        let resolver = NpmResolver::new().with_registry(format!("{}/", registry.url()));
         let result = resolver.resolve(&pkg).await;
         assert!(result.is_ok());
         let artifact = result.expect("Failed to resolve react package");
         assert_eq!(artifact.name, "react");
-        assert!(!artifact.version.is_empty());
-        assert!(
-            artifact
-                .download_url
-                .starts_with("https://registry.npmjs.org/react/-/react-")
+        assert_eq!(artifact.version, "18.2.0");
+        assert_eq!(
+            artifact.download_url,
+            format!("{}/react/-/react-18.2.0.tgz", registry.url())
         );
     }
 
     #[tokio::test]
     async fn test_resolve_react_specific_version() {
+        let registry = registry().await;
         let pkg = InstallPackage::new("react".to_string(), Some("17.0.2".to_string()), false);
-        let resolver = NpmResolver::new();
+        let resolver = NpmResolver::new().with_registry(registry.url().to_string());
         let result = resolver.resolve(&pkg).await;
         assert!(result.is_ok());
         let artifact = result.expect("Failed to resolve react 17.0.2");
@@ -124,31 +226,33 @@ mod tests {
         assert_eq!(artifact.version, "17.0.2");
         assert_eq!(
             artifact.download_url,
-            "https://registry.npmjs.org/react/-/react-17.0.2.tgz"
+            format!("{}/react/-/react-17.0.2.tgz", registry.url())
         );
     }
 
     #[tokio::test]
     async fn test_resolve_invalid_package() {
+        let registry = registry().await;
         let pkg = InstallPackage::new("invalid-package-name-12345".to_string(), None, false);
-        let resolver = NpmResolver::new();
+        let resolver = NpmResolver::new().with_registry(registry.url().to_string());
         let result = resolver.resolve(&pkg).await;
         assert!(result.is_err());
     }
 
     #[tokio::test]
     async fn test_resolve_npm_alias() {
+        let registry = registry().await;
         // Test npm:package@version format (e.g., "wrap-ansi-cjs": "npm:wrap-ansi@^7.0.0")
         let pkg = InstallPackage::new(
             "wrap-ansi-cjs".to_string(),
             Some("npm:wrap-ansi@^7.0.0".to_string()),
             false,
         );
-        let resolver = NpmResolver::new();
+        let resolver = NpmResolver::new().with_registry(registry.url().to_string());
         let result = resolver.resolve(&pkg).await;
         assert!(result.is_ok());
         let artifact = result.expect("Failed to resolve npm alias");
         assert_eq!(artifact.name, "wrap-ansi");
-        assert!(artifact.version.starts_with("7."));
+        assert_eq!(artifact.version, "7.0.0");
     }
 }
@@ -0,0 +1,92 @@
+use crate::resolved_artifact::cache_root;
+use crate::{ArtifactSource, ResolvedArtifact};
+use anyhow::{Context, Result};
+use network::{Network, Transport};
+use package::{InstallPackage, PackageJson};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Resolves dependencies given as a URL to a package tarball
+#[derive(Debug)]
+pub struct RemoteTarballResolver {
+    network: Arc<dyn Transport>,
+    cache: Option<PathBuf>,
+}
+
+impl RemoteTarballResolver {
+    pub fn new() -> Self {
+        Self {
+            network: Arc::new(Network::new()),
+            cache: None,
+        }
+    }
+
+    /// Download tarballs through `transport`
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.network = transport;
+        self
+    }
+
+    /// Download tarballs into `dir` instead of the global package cache
+    pub fn with_cache_dir(mut self, dir: PathBuf) -> Self {
+        self.cache = Some(dir);
+        self
+    }
+
+    /// Download and extract the tarball to read its package.json.
+    ///
+    /// Tarball URLs are treated as immutable, so an existing cache entry is reused.
+    pub async fn resolve(&self, package: &InstallPackage, url: &str) -> Result<ResolvedArtifact> {
+        let source = ArtifactSource::Tarball {
+            url: url.to_string(),
+        };
+        let cache_key = source
+            .cache_key()
+            .context("Tarball sources always have a cache key")?;
+        let cache_dir = cache_root(self.cache.as_deref());
+        let package_dir = cache_dir.join(&cache_key).join("package");
+
+        if !package_dir.join("package.json").exists() {
+            tokio::fs::create_dir_all(&cache_dir).await?;
+
+            let archive_path = cache_dir.join(format!("{}.tgz", cache_key));
+            debug::info!("Downloading tarball {} to {:?}", url, archive_path);
+
+            self.network.download(url, archive_path.clone()).await?;
+            tarball::gzip::unpack(archive_path, cache_dir.join(&cache_key)).await?;
+        } else {
+            debug::info!("Tarball {} already downloaded", url);
+        }
+
+        let pkg_json = PackageJson::from_path(&package_dir.join("package.json"))
+            .await
+            .with_context(|| format!("Tarball {} has no package.json", url))?;
+
+        let name = pkg_json
+            .name
+            .clone()
+            .unwrap_or_else(|| package.name.clone());
+        let version = pkg_json
+            .version
+            .clone()
+            .unwrap_or_else(|| "0.0.0".to_string());
+
+        Ok(ResolvedArtifact {
+            name,
+            version,
+            download_url: url.to_string(),
+            package: Some(pkg_json),
+            source,
+            patch: None,
+            cache: self.cache.clone(),
+            scope: None,
+            dependency_scope: None,
+        })
+    }
+}
+
+impl Default for RemoteTarballResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
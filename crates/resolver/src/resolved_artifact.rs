@@ -1,6 +1,50 @@
 use std::fmt::Display;
+use std::path::{Path, PathBuf};
 
+use contract::get_package_cache_dir;
 use package::PackageJson;
+use sha2::{Digest, Sha256};
+
+/// Where an artifact's contents come from
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArtifactSource {
+    /// Tarball published to the npm registry
+    Registry,
+    /// Git repository, fetched as a host archive or cloned; `spec` is the
+    /// specification it was requested with and `commit` the one it resolved to
+    Git { spec: String, commit: String },
+    /// Local directory or tarball, copied into the cache on every install
+    File { spec: String, path: PathBuf },
+    /// Local directory used in place, its dependencies are not installed
+    Link { spec: String, path: PathBuf },
+    /// Tarball downloaded from an arbitrary URL
+    Tarball { url: String },
+}
+
+impl ArtifactSource {
+    /// Cache key for sources that are not identified by name and version alone
+    pub fn cache_key(&self) -> Option<String> {
+        match self {
+            Self::Registry => None,
+            Self::Git { spec, commit } => Some(cache_name("git", &format!("{}#{}", spec, commit))),
+            Self::File { path, .. } | Self::Link { path, .. } => {
+                Some(cache_name("file", &path.to_string_lossy()))
+            }
+            Self::Tarball { url } => Some(cache_name("tarball", url)),
+        }
+    }
+
+    /// The specification this source was requested with, registry packages have none
+    pub fn spec(&self) -> Option<&str> {
+        match self {
+            Self::Registry => None,
+            Self::Git { spec, .. } | Self::File { spec, .. } | Self::Link { spec, .. } => {
+                Some(spec)
+            }
+            Self::Tarball { url } => Some(url),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct ResolvedArtifact {
@@ -8,12 +52,94 @@ pub struct ResolvedArtifact {
     pub version: String,
     pub download_url: String,
     pub package: Option<PackageJson>,
+    pub source: ArtifactSource,
+    /// Hash of the patch applied when extracting, see [ResolvedArtifact::cache_dir]
+    pub patch: Option<String>,
+    /// Package cache the artifact is stored in, the global one when unset
+    pub cache: Option<PathBuf>,
+    /// Scope of the overrides the package was reached in, when some matched its
+    /// parents. Dependencies may be overridden differently below each scope, so
+    /// every one gets its own copy, see [ResolvedArtifact::cache_dir]
+    pub scope: Option<String>,
+    /// Scope the package's own dependencies were resolved in, the copies to link
+    pub dependency_scope: Option<String>,
 }
 
 impl ResolvedArtifact {
     pub fn to_cache_key(&self) -> String {
-        format!("{}-{}", self.name, self.version)
+        self.source
+            .cache_key()
+            .unwrap_or_else(|| format!("{}-{}", self.name, self.version))
+    }
+
+    /// Directory the artifact is extracted to in its package cache. Patched
+    /// packages get their own entry, keyed by the patch's hash, next to the
+    /// pristine one, and so do packages reached in an override scope.
+    pub fn cache_dir(&self) -> PathBuf {
+        let mut name = self.to_cache_key();
+        if let Some(hash) = &self.patch {
+            name.push_str(&format!("-patch-{}", &hash[..hash.len().min(16)]));
+        }
+        if let Some(scope) = &self.scope {
+            let hash = format!("{:x}", Sha256::digest(scope.as_bytes()));
+            name.push_str(&format!("-scope-{}", &hash[..16]));
+        }
+        cache_root(self.cache.as_deref()).join(name)
+    }
+
+    /// Root of the package contents, linked packages are used in place
+    pub fn package_dir(&self) -> PathBuf {
+        match &self.source {
+            ArtifactSource::Link { path, .. } => path.clone(),
+            _ => self.cache_dir().join("package"),
+        }
+    }
+
+    /// Directory the package's own relative `file:` and `link:` dependencies
+    /// point from: where a local package lives, not its copy in the cache
+    pub fn source_dir(&self) -> PathBuf {
+        match &self.source {
+            ArtifactSource::File { path, .. } if !path.is_dir() => path
+                .parent()
+                .map_or_else(|| path.clone(), Path::to_path_buf),
+            ArtifactSource::File { path, .. } | ArtifactSource::Link { path, .. } => path.clone(),
+            _ => self.package_dir(),
+        }
+    }
+
+    /// Whether this artifact was resolved from the given non-registry specification
+    pub fn matches_spec(&self, spec: &str) -> bool {
+        self.source.spec() == Some(spec)
     }
+
+    /// Whether the artifact's own dependencies should be installed and linked
+    pub fn installs_dependencies(&self) -> bool {
+        !matches!(self.source, ArtifactSource::Link { .. })
+    }
+}
+
+/// `cache`, or the global package cache without one
+pub(crate) fn cache_root(cache: Option<&Path>) -> PathBuf {
+    cache.map_or_else(get_package_cache_dir, Path::to_path_buf)
+}
+
+/// A safe, stable file name for `identity`: a readable prefix, which different
+/// identities can share, and a hash of the whole identity to tell them apart
+fn cache_name(kind: &str, identity: &str) -> String {
+    let readable: String = identity
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .take(48)
+        .collect();
+    let hash = format!("{:x}", Sha256::digest(identity.as_bytes()));
+
+    format!("{}-{}-{}", kind, readable, &hash[..16])
 }
 
 impl Display for ResolvedArtifact {
@@ -21,3 +147,117 @@ impl Display for ResolvedArtifact {
         write!(f, "{}-{}", self.name, self.version)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifact(source: ArtifactSource) -> ResolvedArtifact {
+        ResolvedArtifact {
+            name: "lib".to_string(),
+            version: "1.0.0".to_string(),
+            download_url: String::new(),
+            package: None,
+            source,
+            patch: None,
+            cache: None,
+            scope: None,
+            dependency_scope: None,
+        }
+    }
+
+    #[test]
+    fn test_registry_cache_key() {
+        let artifact = artifact(ArtifactSource::Registry);
+        assert_eq!(artifact.to_cache_key(), "lib-1.0.0");
+        assert!(artifact.package_dir().ends_with("lib-1.0.0/package"));
+        assert!(!artifact.matches_spec("lib"));
+
+        let patched = ResolvedArtifact {
+            patch: Some("0123456789abcdef0123".to_string()),
+            ..artifact
+        };
+        assert_eq!(patched.to_cache_key(), "lib-1.0.0");
+        assert!(
+            patched
+                .package_dir()
+                .ends_with("lib-1.0.0-patch-0123456789abcdef/package")
+        );
+
+        let cached = ResolvedArtifact {
+            cache: Some(PathBuf::from("/tmp/cache")),
+            ..patched
+        };
+        assert_eq!(
+            cached.cache_dir(),
+            PathBuf::from("/tmp/cache/lib-1.0.0-patch-0123456789abcdef")
+        );
+
+        let scoped = ResolvedArtifact {
+            scope: Some("foo/**/bar=1.0.0:1".to_string()),
+            ..cached.clone()
+        };
+        assert_ne!(scoped.cache_dir(), cached.cache_dir());
+        assert!(
+            scoped
+                .cache_dir()
+                .to_string_lossy()
+                .starts_with("/tmp/cache/lib-1.0.0-patch-0123456789abcdef-scope-")
+        );
+    }
+
+    #[test]
+    fn test_local_sources() {
+        let file = artifact(ArtifactSource::File {
+            spec: "file:../lib".to_string(),
+            path: PathBuf::from("/work/lib"),
+        });
+        assert!(file.to_cache_key().starts_with("file--work-lib-"));
+        assert!(file.matches_spec("file:../lib"));
+        assert!(file.installs_dependencies());
+
+        let link = artifact(ArtifactSource::Link {
+            spec: "link:../lib".to_string(),
+            path: PathBuf::from("/work/lib"),
+        });
+        assert_eq!(link.package_dir(), PathBuf::from("/work/lib"));
+        assert!(!link.installs_dependencies());
+    }
+
+    #[test]
+    fn test_tarball_cache_key() {
+        let artifact = artifact(ArtifactSource::Tarball {
+            url: "https://example.com/lib-1.0.0.tgz".to_string(),
+        });
+        assert!(
+            artifact
+                .to_cache_key()
+                .starts_with("tarball-https---example.com-lib-1.0.0.tgz-")
+        );
+    }
+
+    #[test]
+    fn test_cache_keys_are_distinct() {
+        let git = |spec: &str| ArtifactSource::Git {
+            spec: spec.to_string(),
+            commit: "a".repeat(40),
+        };
+        assert_ne!(
+            git("github:a/b-c").cache_key(),
+            git("github:a-b/c").cache_key()
+        );
+
+        let file = |path: &str| ArtifactSource::File {
+            spec: format!("file:{}", path),
+            path: PathBuf::from(path),
+        };
+        assert_ne!(file("/x/a-b").cache_key(), file("/x/a/b").cache_key());
+
+        // A moved branch is a new entry
+        let moved = ArtifactSource::Git {
+            spec: "github:a/b-c".to_string(),
+            commit: "b".repeat(40),
+        };
+        assert_ne!(git("github:a/b-c").cache_key(), moved.cache_key());
+    }
+}
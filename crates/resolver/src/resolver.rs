@@ -1,8 +1,14 @@
-use crate::{GitResolver, NpmResolver, ResolvedArtifact, download_artifact::DownloadArtifact};
-use contract::{Result, get_package_cache_dir};
-use network::Network;
+use crate::resolved_artifact::cache_root;
+use crate::{
+    ArtifactSource, DependencySpec, GitResolver, LocalResolver, NpmResolver, RemoteTarballResolver,
+    ResolvedArtifact, SpecKind, download_artifact::DownloadArtifact,
+};
+use contract::Result;
+use network::{Network, Transport};
 use package::InstallPackage;
+use registry::Storage;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -10,45 +16,132 @@ use tokio::sync::Mutex;
 pub struct Resolver {
     npm_resolver: NpmResolver,
     git_resolver: GitResolver,
-    network: Network,
+    local_resolver: LocalResolver,
+    tarball_resolver: RemoteTarballResolver,
+    network: Arc<dyn Transport>,
+    cache_dir: Option<PathBuf>,
+    mirror: Option<Storage>,
     // File-level locks to prevent concurrent downloads to the same file
     download_locks: Arc<Mutex<HashMap<String, Arc<Mutex<()>>>>>,
 }
 
 impl Resolver {
     pub fn new() -> Self {
+        // One connection pool for the registry documents and every download
+        let network: Arc<dyn Transport> = Arc::new(Network::new());
         Self {
-            npm_resolver: NpmResolver::new(),
-            git_resolver: GitResolver::new(),
-            network: Network::new(),
+            npm_resolver: NpmResolver::new().with_transport(network.clone()),
+            git_resolver: GitResolver::new().with_transport(network.clone()),
+            local_resolver: LocalResolver::new(),
+            tarball_resolver: RemoteTarballResolver::new().with_transport(network.clone()),
+            network,
+            cache_dir: None,
+            mirror: None,
             download_locks: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    /// Fetch every package through `transport`, sharing its connections
+    /// across the whole install
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.npm_resolver = self.npm_resolver.with_transport(transport.clone());
+        self.git_resolver = self.git_resolver.with_transport(transport.clone());
+        self.tarball_resolver = self.tarball_resolver.with_transport(transport.clone());
+        self.network = transport;
+        self
+    }
+
+    /// Resolve registry packages against `registry` instead of the npm registry
+    pub fn with_registry(mut self, registry: String) -> Self {
+        self.npm_resolver = self.npm_resolver.with_registry(registry);
+        self
+    }
+
+    /// Download and extract packages into `dir` instead of the global package cache
+    pub fn with_cache_dir(mut self, dir: PathBuf) -> Self {
+        self.git_resolver = self.git_resolver.with_cache_dir(dir.clone());
+        self.local_resolver = self.local_resolver.with_cache_dir(dir.clone());
+        self.tarball_resolver = self.tarball_resolver.with_cache_dir(dir.clone());
+        self.cache_dir = Some(dir);
+        self
+    }
+
+    /// Copy every registry tarball to `dir`, and resolve and download from it
+    /// before the network
+    pub fn with_offline_mirror(mut self, dir: PathBuf) -> Self {
+        self.npm_resolver = self.npm_resolver.with_offline_mirror(dir.clone());
+        self.mirror = Some(Storage::new(dir));
+        self
+    }
+
+    /// Resolve registry packages only from the offline mirror
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.npm_resolver = self.npm_resolver.with_offline(offline);
+        self
+    }
+
     pub async fn resolve(&self, package: &InstallPackage) -> Result<ResolvedArtifact> {
-        if package.is_git() {
-            debug::info!("Resolving git package: {}", package.name);
-            self.git_resolver.resolve(package)
-        } else {
-            debug::info!(
-                "Resolving npm package: {} {}",
-                package.name,
-                package.version.clone().unwrap_or("".to_string()),
-            );
-            self.npm_resolver.resolve(package).await
-        }
+        let spec: DependencySpec = package.into();
+
+        let mut artifact = match &spec.kind {
+            SpecKind::Git(git_spec) => {
+                debug::info!("Resolving git package: {}", git_spec);
+                self.git_resolver.resolve(package).await
+            }
+            SpecKind::File(path) => {
+                debug::info!("Resolving local package: {:?}", path);
+                self.local_resolver.resolve_file(package, path).await
+            }
+            SpecKind::Link(path) => {
+                debug::info!("Resolving linked package: {:?}", path);
+                self.local_resolver.resolve_link(package, path).await
+            }
+            SpecKind::Tarball(url) => {
+                debug::info!("Resolving tarball package: {}", url);
+                self.tarball_resolver.resolve(package, url).await
+            }
+            SpecKind::Registry => {
+                debug::info!(
+                    "Resolving npm package: {} {}",
+                    package.name,
+                    package.version.clone().unwrap_or("".to_string()),
+                );
+                self.npm_resolver.resolve(package).await
+            }
+        }?;
+        artifact.cache = self.cache_dir.clone();
+
+        Ok(artifact)
     }
 
     pub async fn download(
         &self,
         artifact: &ResolvedArtifact,
     ) -> contract::Result<DownloadArtifact> {
-        let cache_dir = get_package_cache_dir();
+        // Non-registry sources are fetched and extracted while resolving, since
+        // the package name is only known from their package.json
+        match artifact.source {
+            ArtifactSource::Registry => {}
+            ArtifactSource::Link { .. } => {
+                return Ok(DownloadArtifact {
+                    key: artifact.to_cache_key(),
+                    path: artifact.package_dir(),
+                });
+            }
+            _ => {
+                return Ok(DownloadArtifact {
+                    key: artifact.to_cache_key(),
+                    path: artifact.cache_dir(),
+                });
+            }
+        }
+
+        let cache_dir = cache_root(self.cache_dir.as_deref());
 
         tokio::fs::create_dir_all(&cache_dir).await?;
 
         // Construct the file path: ~/.craft/packages/{name}-{version}.tgz
-        let filename = format!("{}-{}.tgz", artifact.name, artifact.version);
+        let filename = format!("{}.tgz", artifact.to_cache_key());
         let file_path = cache_dir.join(&filename);
 
         // Get or create a lock for this specific file
@@ -70,23 +163,39 @@ impl Resolver {
                 artifact.name,
                 file_path
             );
-            return Ok(DownloadArtifact {
-                key: artifact.to_cache_key(),
-                path: file_path,
-            });
-        }
-
-        debug::info!("Downloading {} to: {:?}", artifact.name, file_path);
+        } else {
+            if let Some(parent) = file_path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
 
-        if let Some(parent) = file_path.parent() {
-            tokio::fs::create_dir_all(parent).await?;
+            let mirrored = self
+                .mirror
+                .as_ref()
+                .map(|mirror| mirror.tarball_path(&artifact.name, &artifact.version))
+                .filter(|path| path.exists());
+            if let Some(mirrored) = mirrored {
+                debug::info!("Copying {} from the offline mirror", artifact.name);
+                tokio::fs::copy(&mirrored, &file_path).await?;
+            } else {
+                debug::info!("Downloading {} to: {:?}", artifact.name, file_path);
+                self.network
+                    .download(&artifact.download_url, file_path.clone())
+                    .await?;
+                debug::info!("Successfully downloaded {}", artifact.name);
+            }
         }
 
-        self.network
-            .download(&artifact.download_url, file_path.clone())
-            .await?;
-
-        debug::info!("Successfully downloaded {}", artifact.name);
+        // Cached tarballs are mirrored too, so the mirror has the whole install
+        if let Some(mirror) = &self.mirror
+            && !mirror
+                .tarball_path(&artifact.name, &artifact.version)
+                .exists()
+        {
+            let tarball = tokio::fs::read(&file_path).await?;
+            mirror
+                .write_tarball(&artifact.name, &artifact.version, &tarball)
+                .await?;
+        }
 
         Ok(DownloadArtifact {
             key: artifact.to_cache_key(),
@@ -104,51 +213,217 @@ impl Default for Resolver {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use network::{ByteStream, Conditional};
+    use registry::MockRegistry;
+
+    /// Records the URLs it's asked for and fetches them over the network
+    #[derive(Debug, Default)]
+    struct RecordingTransport {
+        network: Network,
+        urls: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl RecordingTransport {
+        fn record(&self, url: &str) {
+            self.urls.lock().unwrap().push(url.to_string());
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Transport for RecordingTransport {
+        async fn fetch_json(&self, url: &str) -> Result<serde_json::Value> {
+            self.record(url);
+            self.network.fetch_json(url).await
+        }
+
+        async fn fetch_stream(&self, url: &str) -> Result<ByteStream> {
+            self.record(url);
+            self.network.fetch_stream(url).await
+        }
+
+        async fn fetch_if_modified(&self, url: &str, etag: Option<&str>) -> Result<Conditional> {
+            self.record(url);
+            self.network.fetch_if_modified(url, etag).await
+        }
+    }
 
     #[tokio::test]
     async fn test_resolve_npm_package() {
+        let registry = MockRegistry::start().unwrap();
+        for version in ["16.14.0", "17.0.2"] {
+            registry
+                .add("react", version, serde_json::json!({}))
+                .await
+                .unwrap();
+        }
+        let cache = tempfile::tempdir().unwrap();
+
         let pkg = InstallPackage::new("react".to_string(), Some("17.0.2".to_string()), false);
-        let resolver = Resolver::new();
+        let resolver = Resolver::new()
+            .with_registry(registry.url().to_string())
+            .with_cache_dir(cache.path().to_path_buf());
         let result = resolver.resolve(&pkg).await;
 
         assert!(result.is_ok());
         let artifact = result.expect("Failed to resolve npm package");
         assert_eq!(artifact.name, "react");
         assert_eq!(artifact.version, "17.0.2");
+        assert!(artifact.cache_dir().starts_with(cache.path()));
+
+        let download = resolver.download(&artifact).await.unwrap();
+        assert_eq!(download.path, cache.path().join("react-17.0.2.tgz"));
+        assert!(download.path.exists());
     }
 
     #[tokio::test]
-    async fn test_resolve_git_package() {
-        let pkg = InstallPackage::new("git:github.com/user/repo.git".to_string(), None, false);
-        let resolver = Resolver::new();
-        let result = resolver.resolve(&pkg).await;
+    async fn test_fetch_through_transport() {
+        let registry = MockRegistry::start().unwrap();
+        registry
+            .add("lib", "1.0.0", serde_json::json!({}))
+            .await
+            .unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        let transport = Arc::new(RecordingTransport::default());
 
-        assert!(result.is_ok());
-        let artifact = result.expect("Failed to resolve git package");
-        assert_eq!(artifact.name, "repo");
-        assert_eq!(artifact.version, "git");
+        let pkg = InstallPackage::new("lib".to_string(), None, false);
+        let resolver = Resolver::new()
+            .with_transport(transport.clone())
+            .with_registry(registry.url().to_string())
+            .with_cache_dir(cache.path().to_path_buf());
+        let artifact = resolver.resolve(&pkg).await.unwrap();
+        resolver.download(&artifact).await.unwrap();
+
+        assert_eq!(
+            *transport.urls.lock().unwrap(),
+            [
+                format!("{}/lib", registry.url()),
+                format!("{}/lib/-/lib-1.0.0.tgz", registry.url()),
+            ]
+        );
     }
 
     #[tokio::test]
-    async fn test_resolve_npm_latest() {
-        let pkg = InstallPackage::new("express".to_string(), None, false);
+    async fn test_offline_mirror() {
+        let registry = MockRegistry::start().unwrap();
+        for version in ["1.0.0", "2.0.0"] {
+            registry
+                .add("@acme/lib", version, serde_json::json!({}))
+                .await
+                .unwrap();
+        }
+        let url = registry.url().to_string();
+        let dir = tempfile::tempdir().unwrap();
+        let mirror = dir.path().join("vendor/npm");
+
+        let online = Resolver::new()
+            .with_registry(url.clone())
+            .with_cache_dir(dir.path().join("cache"))
+            .with_offline_mirror(mirror.clone());
+        let pkg = InstallPackage::new("@acme/lib".to_string(), Some("^1.0.0".to_string()), false);
+        let artifact = online.resolve(&pkg).await.unwrap();
+        online.download(&artifact).await.unwrap();
+        assert!(mirror.join("@acme/lib-1.0.0.tgz").exists());
+
+        // Mirrored versions are used before the registry, which is asked for the
+        // ranges the mirror can't satisfy, unless offline
+        registry
+            .add("@acme/lib", "1.0.1", serde_json::json!({}))
+            .await
+            .unwrap();
+        assert_eq!(online.resolve(&pkg).await.unwrap().version, "1.0.0");
+        let next = InstallPackage::new("@acme/lib".to_string(), Some("^2.0.0".to_string()), false);
+        assert_eq!(online.resolve(&next).await.unwrap().version, "2.0.0");
+        let offline = Resolver::new()
+            .with_registry(url.clone())
+            .with_offline_mirror(mirror.clone())
+            .with_offline(true);
+        assert!(offline.resolve(&next).await.is_err());
+
+        // Without the registry, mirrored versions still resolve and install
+        drop(registry);
+        let offline = Resolver::new()
+            .with_registry(url.clone())
+            .with_cache_dir(dir.path().join("empty-cache"))
+            .with_offline_mirror(mirror);
+        let artifact = offline.resolve(&pkg).await.unwrap();
+        assert_eq!(artifact.version, "1.0.0");
+        assert_eq!(
+            artifact.download_url,
+            format!("{}/@acme/lib/-/lib-1.0.0.tgz", url)
+        );
+        let download = offline.download(&artifact).await.unwrap();
+        assert!(download.path.starts_with(dir.path().join("empty-cache")));
+        assert!(download.path.exists());
+
+        let pkg = InstallPackage::new("@acme/lib".to_string(), Some("^2.0.0".to_string()), false);
+        assert!(offline.resolve(&pkg).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_download_git_package_uses_resolved_dir() {
+        let artifact = ResolvedArtifact {
+            name: "repo".to_string(),
+            version: "1.0.0".to_string(),
+            download_url: "https://codeload.github.com/user/repo/tar.gz/HEAD".to_string(),
+            package: None,
+            source: ArtifactSource::Git {
+                spec: "github:user/repo".to_string(),
+                commit: "0123456789abcdef0123456789abcdef01234567".to_string(),
+            },
+            patch: None,
+            cache: None,
+            scope: None,
+            dependency_scope: None,
+        };
         let resolver = Resolver::new();
-        let result = resolver.resolve(&pkg).await;
+        let download = resolver
+            .download(&artifact)
+            .await
+            .expect("Git download should not hit the network");
 
-        assert!(result.is_ok());
-        let artifact = result.expect("Failed to resolve express package");
-        assert_eq!(artifact.name, "express");
-        assert!(!artifact.version.is_empty());
+        assert_eq!(download.path, artifact.cache_dir());
+        assert!(download.key.starts_with("git-github"));
     }
 
     #[tokio::test]
-    async fn test_resolve_gitlab_package() {
-        let pkg = InstallPackage::new("git:gitlab.com/package/psc".to_string(), None, false);
+    async fn test_resolve_link_package() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("package.json"),
+            r#"{"name": "vendored", "version": "1.0.0"}"#,
+        )
+        .unwrap();
+
+        let spec = format!("link:{}", dir.path().display());
+        let pkg = InstallPackage::new("vendored".to_string(), Some(spec.clone()), false);
         let resolver = Resolver::new();
+        let artifact = resolver.resolve(&pkg).await.unwrap();
+
+        assert_eq!(artifact.name, "vendored");
+        assert!(artifact.matches_spec(&spec));
+
+        let download = resolver.download(&artifact).await.unwrap();
+        assert_eq!(download.path, dir.path());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_npm_latest() {
+        let registry = MockRegistry::start().unwrap();
+        for version in ["4.18.2", "5.0.0"] {
+            registry
+                .add("express", version, serde_json::json!({}))
+                .await
+                .unwrap();
+        }
+        registry.tag("express", "latest", "4.18.2").await.unwrap();
+
+        let pkg = InstallPackage::new("express".to_string(), None, false);
+        let resolver = Resolver::new().with_registry(registry.url().to_string());
         let result = resolver.resolve(&pkg).await;
 
         assert!(result.is_ok());
-        let artifact = result.expect("Failed to resolve gitlab package");
-        assert_eq!(artifact.name, "psc");
+        let artifact = result.expect("Failed to resolve express package");
+        assert_eq!(artifact.name, "express");
+        assert_eq!(artifact.version, "4.18.2");
     }
 }
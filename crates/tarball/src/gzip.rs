@@ -1,7 +1,8 @@
 use flate2::read::GzDecoder;
 use std::fs::File;
-use std::path::PathBuf;
-use tar::Archive;
+use std::io::Read;
+use std::path::{Component, PathBuf};
+use tar::{Archive, EntryType};
 
 pub async fn unzip(source: PathBuf, dest: PathBuf) -> contract::Result<()> {
     tokio::task::spawn_blocking(move || {
@@ -16,6 +17,76 @@ pub async fn unzip(source: PathBuf, dest: PathBuf) -> contract::Result<()> {
     .await?
 }
 
+/// Extract a package tarball into `dest/package`.
+///
+/// Registry tarballs usually have a `package/` root, but some use another name
+/// (e.g. `node/` for `@types/node`) and git host archives use `<repo>-<ref>/`,
+/// so the first path component is replaced instead of trusted. Like npm,
+/// symlink and hardlink entries are skipped, and nothing is written outside
+/// `dest/package`.
+pub async fn unpack(source: PathBuf, dest: PathBuf) -> contract::Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let tar_gz = File::open(source)?;
+        let tar = GzDecoder::new(tar_gz);
+        let mut archive = Archive::new(tar);
+        let package_dir = dest.join("package");
+
+        std::fs::create_dir_all(&package_dir)?;
+        let root = package_dir.canonicalize()?;
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !matches!(
+                entry.header().entry_type(),
+                EntryType::Regular | EntryType::Directory
+            ) {
+                continue;
+            }
+            let path = entry.path()?.into_owned();
+
+            // Skip the root directory and anything trying to escape it
+            let relative: PathBuf = path.components().skip(1).collect();
+            if relative.as_os_str().is_empty()
+                || relative
+                    .components()
+                    .any(|c| !matches!(c, Component::Normal(_)))
+            {
+                continue;
+            }
+
+            let target = package_dir.join(relative);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+                if !parent.canonicalize()?.starts_with(&root) {
+                    continue;
+                }
+            }
+            entry.unpack(&target)?;
+        }
+
+        Ok(())
+    })
+    .await?
+}
+
+/// Contents of the file at `path` below a package tarball's root directory,
+/// whatever that directory is called, `None` when there is no such file
+pub fn read_entry(tarball: &[u8], path: &str) -> contract::Result<Option<Vec<u8>>> {
+    let mut archive = Archive::new(GzDecoder::new(tarball));
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let relative: PathBuf = entry.path()?.components().skip(1).collect();
+        if relative.as_os_str() == path {
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents)?;
+            return Ok(Some(contents));
+        }
+    }
+
+    Ok(None)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -49,4 +120,70 @@ mod tests {
         let content = std::fs::read_to_string(dest.join("test.txt")).unwrap();
         assert_eq!(content, "Hello World!");
     }
+
+    #[tokio::test]
+    async fn test_unpack_normalizes_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("repo.tar.gz");
+        let dest = dir.path().join("output");
+
+        // Git host archives use `<repo>-<ref>/` instead of `package/`
+        let file = File::create(&source).unwrap();
+        let enc = GzEncoder::new(file, Compression::default());
+        let mut tar = tar::Builder::new(enc);
+
+        let mut header = tar::Header::new_gnu();
+        header.set_path("repo-v1.2.0/package.json").unwrap();
+        header.set_size(2);
+        header.set_cksum();
+
+        tar.append(&header, "{}".as_bytes()).unwrap();
+        let enc = tar.into_inner().unwrap();
+        enc.finish().unwrap();
+
+        unpack(source.clone(), dest.clone()).await.unwrap();
+
+        let content = std::fs::read_to_string(dest.join("package/package.json")).unwrap();
+        assert_eq!(content, "{}");
+
+        let tarball = std::fs::read(&source).unwrap();
+        assert_eq!(
+            read_entry(&tarball, "package.json").unwrap(),
+            Some(b"{}".to_vec())
+        );
+        assert_eq!(read_entry(&tarball, "index.js").unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_unpack_skips_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("evil.tar.gz");
+        let dest = dir.path().join("output");
+        let outside = dir.path().join("outside");
+        std::fs::create_dir(&outside).unwrap();
+
+        let file = File::create(&source).unwrap();
+        let enc = GzEncoder::new(file, Compression::default());
+        let mut tar = tar::Builder::new(enc);
+
+        // `package/lib` links outside, then a file is written through it
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(EntryType::Symlink);
+        header.set_size(0);
+        tar.append_link(&mut header, "package/lib", &outside)
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_path("package/lib/evil.js").unwrap();
+        header.set_size(2);
+        header.set_cksum();
+        tar.append(&header, "{}".as_bytes()).unwrap();
+        let enc = tar.into_inner().unwrap();
+        enc.finish().unwrap();
+
+        unpack(source, dest.clone()).await.unwrap();
+
+        assert!(!outside.join("evil.js").exists());
+        assert!(!dest.join("package/lib").is_symlink());
+        assert!(dest.join("package/lib/evil.js").is_file());
+    }
 }
@@ -0,0 +1,103 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tar::{Builder, EntryType, Header};
+
+/// npm's modification time for every tarball entry, 1985-10-26T08:15:00Z, so
+/// packing the same files always gives the same bytes
+pub const MTIME: u64 = 499_162_500;
+
+/// A file of a package tarball, at `path` below `package/`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackFile {
+    pub path: String,
+    pub contents: Vec<u8>,
+    pub executable: bool,
+}
+
+impl PackFile {
+    pub fn new(path: String, contents: Vec<u8>) -> Self {
+        Self {
+            path,
+            contents,
+            executable: false,
+        }
+    }
+
+    pub fn with_executable(mut self, executable: bool) -> Self {
+        self.executable = executable;
+        self
+    }
+}
+
+/// A gzipped tarball of `files` under a `package/` root, the way registries
+/// expect them. Entries are sorted by path and have fixed owners, modes and
+/// times, so the tarball only depends on the files.
+pub fn pack(files: &[PackFile]) -> contract::Result<Vec<u8>> {
+    let mut sorted: Vec<&PackFile> = files.iter().collect();
+    sorted.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let mut tar = Builder::new(encoder);
+
+    for file in sorted {
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Regular);
+        header.set_size(file.contents.len() as u64);
+        header.set_mode(if file.executable { 0o755 } else { 0o644 });
+        header.set_mtime(MTIME);
+        header.set_uid(0);
+        header.set_gid(0);
+
+        tar.append_data(
+            &mut header,
+            format!("package/{}", file.path),
+            file.contents.as_slice(),
+        )?;
+    }
+
+    Ok(tar.into_inner()?.finish()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use tar::Archive;
+
+    #[test]
+    fn test_pack() {
+        let files = vec![
+            PackFile::new("package.json".to_string(), b"{}".to_vec()),
+            PackFile::new("bin/cli.js".to_string(), b"#!/usr/bin/env node".to_vec())
+                .with_executable(true),
+        ];
+
+        let tarball = pack(&files).unwrap();
+        let mut reversed = files.clone();
+        reversed.reverse();
+        assert_eq!(pack(&reversed).unwrap(), tarball);
+
+        let mut archive = Archive::new(GzDecoder::new(tarball.as_slice()));
+        let entries: Vec<(String, u32, u64)> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let header = entry.header();
+                (
+                    entry.path().unwrap().to_string_lossy().to_string(),
+                    header.mode().unwrap(),
+                    header.mtime().unwrap(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            entries,
+            [
+                ("package/bin/cli.js".to_string(), 0o755, MTIME),
+                ("package/package.json".to_string(), 0o644, MTIME),
+            ]
+        );
+    }
+}